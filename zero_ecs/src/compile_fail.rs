@@ -0,0 +1,103 @@
+//! Code the macros have to reject, checked as doctests.
+//!
+//! A `#[system]` that borrows a resource gets the world's entities, so the resource can be
+//! borrowed next to `&World` or `&mut World`, mutably or not:
+//!
+//! ```
+//! use zero_ecs::*;
+//!
+//! #[derive(Default)]
+//! pub struct Score(usize);
+//!
+//! #[derive(Default)]
+//! pub struct Position(f32);
+//!
+//! #[entity]
+//! pub struct EnemyEntity {
+//!     position: Position,
+//! }
+//!
+//! ecs_world!(EnemyEntity; resources: Score);
+//!
+//! #[system(World)]
+//! fn read_score(world: &mut World, score: &Score) {
+//!     world.create(EnemyEntity {
+//!         position: Position(score.0 as f32),
+//!     });
+//! }
+//!
+//! #[system(World)]
+//! fn add_score(world: &World, score: &mut Score) {
+//!     score.0 += world.len();
+//! }
+//!
+//! fn main() {
+//!     let mut world = World::default();
+//!     world.read_score();
+//!     world.add_score();
+//!     assert_eq!(world.resource::<Score>().0, 1);
+//! }
+//! ```
+//!
+//! A generic entity can be put in a world with different components:
+//!
+//! ```
+//! use zero_ecs::*;
+//!
+//! #[derive(Default)]
+//! pub struct Position(f32);
+//!
+//! #[derive(Default)]
+//! pub struct Velocity(f32);
+//!
+//! #[entity]
+//! pub struct Pair<A, B> {
+//!     a: A,
+//!     b: B,
+//! }
+//!
+//! ecs_world!(Pair<Position, Velocity>);
+//!
+//! fn main() {
+//!     World::default().create(Pair {
+//!         a: Position(0.0),
+//!         b: Velocity(1.0),
+//!     });
+//! }
+//! ```
+//!
+//! An entity can't have two components of the same type:
+//!
+//! ```compile_fail
+//! use zero_ecs::*;
+//!
+//! #[derive(Default)]
+//! pub struct Position(f32);
+//!
+//! #[entity]
+//! pub struct EnemyEntity {
+//!     position: Position,
+//!     spawn: Position,
+//! }
+//!
+//! fn main() {}
+//! ```
+//!
+//! Also not when a generic entity gets the same type for two of its parameters:
+//!
+//! ```compile_fail
+//! use zero_ecs::*;
+//!
+//! #[derive(Default)]
+//! pub struct Position(f32);
+//!
+//! #[entity]
+//! pub struct Pair<A, B> {
+//!     a: A,
+//!     b: B,
+//! }
+//!
+//! ecs_world!(Pair<Position, Position>);
+//!
+//! fn main() {}
+//! ```
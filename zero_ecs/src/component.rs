@@ -0,0 +1,39 @@
+use std::any::{Any, TypeId};
+
+/// Converts between component types. Components are matched by name first, and the world
+/// names the type of each of its columns through an alias, so the types they resolve to are
+/// compared here. `physics::Velocity` and an imported `Velocity` are the same component,
+/// `physics::Velocity` and `render::Velocity` are not.
+#[doc(hidden)]
+pub trait SameComponent<T> {
+    /// The component as a `T`, or back if it isn't one.
+    fn into_component(self) -> Result<T, Self>
+    where
+        Self: Sized;
+    fn component_ref(&self) -> Option<&T>;
+    fn component_mut(&mut self) -> Option<&mut T>;
+}
+
+impl<T: 'static, U: 'static> SameComponent<U> for T {
+    fn into_component(self) -> Result<U, T> {
+        let mut component = Some(self);
+        match (&mut component as &mut dyn Any).downcast_mut::<Option<U>>() {
+            Some(same) => Ok(same.take().expect("the component was just put in")),
+            None => Err(component.expect("the component was just put in")),
+        }
+    }
+
+    fn component_ref(&self) -> Option<&U> {
+        (self as &dyn Any).downcast_ref()
+    }
+
+    fn component_mut(&mut self) -> Option<&mut U> {
+        (self as &mut dyn Any).downcast_mut()
+    }
+}
+
+/// Whether `T` and `U` resolve to the same component type.
+#[doc(hidden)]
+pub fn is_same_component<T: 'static, U: 'static>() -> bool {
+    TypeId::of::<T>() == TypeId::of::<U>()
+}
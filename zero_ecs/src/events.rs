@@ -0,0 +1,120 @@
+/// Events of one type, kept by the world. They are double-buffered: events sent during a
+/// frame can be read after the next `update`, until the one after that.
+#[derive(Debug)]
+pub struct Events<T> {
+    read: Vec<T>,
+    sent: Vec<T>,
+}
+
+impl<T> Default for Events<T> {
+    fn default() -> Self {
+        Self {
+            read: Vec::new(),
+            sent: Vec::new(),
+        }
+    }
+}
+
+impl<T> Events<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queues the event, it can be read after the next `update`.
+    pub fn send(&mut self, event: T) {
+        self.sent.push(event);
+    }
+
+    pub fn send_batch(&mut self, events: impl IntoIterator<Item = T>) {
+        self.sent.extend(events);
+    }
+
+    /// The events sent before the last `update`.
+    pub fn read(&self) -> &[T] {
+        &self.read
+    }
+
+    /// Drops the events that could be read, and makes the ones sent since readable.
+    pub fn update(&mut self) {
+        self.read.clear();
+        std::mem::swap(&mut self.read, &mut self.sent);
+    }
+
+    pub fn clear(&mut self) {
+        self.read.clear();
+        self.sent.clear();
+    }
+}
+
+/// Reads the events of one type in a system, the ones sent before the last `update_events`.
+#[derive(Debug)]
+pub struct EventReader<'a, T> {
+    events: &'a [T],
+}
+
+impl<'a, T> EventReader<'a, T> {
+    pub fn new(events: &'a [T]) -> Self {
+        Self { events }
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'a, T> {
+        self.events.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+}
+
+impl<'a, T> IntoIterator for &EventReader<'a, T> {
+    type Item = &'a T;
+    type IntoIter = std::slice::Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Sends events of one type from a system. They are added to the world when the system
+/// returns, the same as commands.
+#[derive(Debug)]
+pub struct EventWriter<T> {
+    events: Vec<T>,
+}
+
+impl<T> Default for EventWriter<T> {
+    fn default() -> Self {
+        Self { events: Vec::new() }
+    }
+}
+
+impl<T> EventWriter<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn send(&mut self, event: T) {
+        self.events.push(event);
+    }
+
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+}
+
+impl<T> IntoIterator for EventWriter<T> {
+    type Item = T;
+    type IntoIter = std::vec::IntoIter<T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.events.into_iter()
+    }
+}
@@ -0,0 +1,138 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+
+/// Parent/child relationships between entities. Children are kept in the order they were
+/// added to their parent.
+#[derive(Debug)]
+pub struct Hierarchy<E> {
+    parents: HashMap<E, E>,
+    children: HashMap<E, Vec<E>>,
+}
+
+impl<E> Default for Hierarchy<E> {
+    fn default() -> Self {
+        Self {
+            parents: HashMap::new(),
+            children: HashMap::new(),
+        }
+    }
+}
+
+impl<E: Copy + Eq + Hash> Hierarchy<E> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.parents.is_empty()
+    }
+
+    pub fn parent(&self, child: E) -> Option<E> {
+        self.parents.get(&child).copied()
+    }
+
+    pub fn children(&self, parent: E) -> &[E] {
+        self.children.get(&parent).map_or(&[], Vec::as_slice)
+    }
+
+    /// Whether `ancestor` is `entity` or one of its ancestors.
+    pub fn is_ancestor(&self, ancestor: E, entity: E) -> bool {
+        let mut current = Some(entity);
+        while let Some(entity) = current {
+            if entity == ancestor {
+                return true;
+            }
+            current = self.parent(entity);
+        }
+        false
+    }
+
+    /// Makes `child` a child of `parent`, moving it from its old parent. Returns false, and
+    /// leaves the hierarchy as it was, if that would make an entity its own ancestor.
+    pub fn set_parent(&mut self, child: E, parent: E) -> bool {
+        if self.is_ancestor(child, parent) {
+            return false;
+        }
+        self.remove_parent(child);
+        self.parents.insert(child, parent);
+        self.children.entry(parent).or_default().push(child);
+        true
+    }
+
+    /// Detaches `child` from its parent and returns the parent.
+    pub fn remove_parent(&mut self, child: E) -> Option<E> {
+        let parent = self.parents.remove(&child)?;
+        if let Some(siblings) = self.children.get_mut(&parent) {
+            siblings.retain(|sibling| *sibling != child);
+            if siblings.is_empty() {
+                self.children.remove(&parent);
+            }
+        }
+        Some(parent)
+    }
+
+    /// Removes the entity from the hierarchy. It is detached from its parent, and its children
+    /// no longer have a parent. Returns the children.
+    pub fn remove(&mut self, entity: E) -> Vec<E> {
+        if self.is_empty() {
+            return Vec::new();
+        }
+        self.remove_parent(entity);
+        let children = self.children.remove(&entity).unwrap_or_default();
+        for child in &children {
+            self.parents.remove(child);
+        }
+        children
+    }
+
+    /// The entity's children, their children and so on, parents before their children.
+    pub fn descendants(&self, entity: E) -> Vec<E> {
+        let mut descendants = self.children(entity).to_vec();
+        let mut index = 0;
+        while let Some(&descendant) = descendants.get(index) {
+            descendants.extend_from_slice(self.children(descendant));
+            index += 1;
+        }
+        descendants
+    }
+
+    /// Gives the relationships of `old` to `new`, for when an entity gets a new handle.
+    pub fn replace(&mut self, old: E, new: E) {
+        if self.is_empty() {
+            return;
+        }
+        if let Some(parent) = self.parents.remove(&old) {
+            self.parents.insert(new, parent);
+            if let Some(siblings) = self.children.get_mut(&parent) {
+                for sibling in siblings.iter_mut().filter(|sibling| **sibling == old) {
+                    *sibling = new;
+                }
+            }
+        }
+        if let Some(children) = self.children.remove(&old) {
+            for child in &children {
+                self.parents.insert(*child, new);
+            }
+            self.children.insert(new, children);
+        }
+    }
+
+    /// Removes every entity for which `f` returns false.
+    pub fn retain(&mut self, mut f: impl FnMut(E) -> bool) {
+        let removed: Vec<E> = self
+            .parents
+            .keys()
+            .chain(self.children.keys())
+            .copied()
+            .filter(|entity| !f(*entity))
+            .collect();
+        for entity in removed {
+            self.remove(entity);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.parents.clear();
+        self.children.clear();
+    }
+}
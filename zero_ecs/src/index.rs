@@ -0,0 +1,100 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::ops::{Deref, DerefMut};
+
+/// Index of an `#[indexed]` entity field, from component value to the entities that have it.
+#[derive(Debug)]
+pub struct ColumnIndex<T, E> {
+    entities: HashMap<T, Vec<E>>,
+}
+
+impl<T, E> Default for ColumnIndex<T, E> {
+    fn default() -> Self {
+        Self {
+            entities: HashMap::new(),
+        }
+    }
+}
+
+impl<T: Clone + Eq + Hash, E: Copy + Eq> ColumnIndex<T, E> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The entities with the value, in no particular order.
+    pub fn get(&self, value: &T) -> &[E] {
+        self.entities.get(value).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn insert(&mut self, value: &T, entity: E) {
+        match self.entities.get_mut(value) {
+            Some(entities) => entities.push(entity),
+            None => {
+                self.entities.insert(value.clone(), vec![entity]);
+            }
+        }
+    }
+
+    pub fn remove(&mut self, value: &T, entity: E) {
+        let Some(entities) = self.entities.get_mut(value) else {
+            return;
+        };
+        if let Some(position) = entities.iter().position(|e| *e == entity) {
+            entities.swap_remove(position);
+        }
+        if entities.is_empty() {
+            self.entities.remove(value);
+        }
+    }
+
+    /// Moves the entity from `old` to `new`.
+    pub fn replace(&mut self, old: &T, new: &T, entity: E) {
+        if old != new {
+            self.remove(old, entity);
+            self.insert(new, entity);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.entities.clear();
+    }
+}
+
+/// Mutable access to an `#[indexed]` component. The index is updated when it is dropped.
+pub struct IndexedMut<'a, T: Clone + Eq + Hash, E: Copy + Eq> {
+    value: &'a mut T,
+    old: T,
+    entity: E,
+    index: &'a mut ColumnIndex<T, E>,
+}
+
+impl<'a, T: Clone + Eq + Hash, E: Copy + Eq> IndexedMut<'a, T, E> {
+    pub fn new(value: &'a mut T, entity: E, index: &'a mut ColumnIndex<T, E>) -> Self {
+        Self {
+            old: value.clone(),
+            value,
+            entity,
+            index,
+        }
+    }
+}
+
+impl<T: Clone + Eq + Hash, E: Copy + Eq> Deref for IndexedMut<'_, T, E> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.value
+    }
+}
+
+impl<T: Clone + Eq + Hash, E: Copy + Eq> DerefMut for IndexedMut<'_, T, E> {
+    fn deref_mut(&mut self) -> &mut T {
+        self.value
+    }
+}
+
+impl<T: Clone + Eq + Hash, E: Copy + Eq> Drop for IndexedMut<'_, T, E> {
+    fn drop(&mut self) {
+        self.index.replace(&self.old, self.value, self.entity);
+    }
+}
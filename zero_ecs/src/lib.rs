@@ -1,3 +1,13 @@
+#[cfg(doctest)]
+mod compile_fail;
+mod component;
+mod events;
+mod hierarchy;
+mod index;
+mod permutation;
+mod sparse;
+mod spatial;
+
 pub use itertools::chain;
 pub use itertools::izip;
 pub use macro_magic;
@@ -17,6 +27,15 @@ pub use zero_ecs_macros::query;
 pub use zero_ecs_macros::system;
 pub use zero_ecs_macros::system_for_each;
 pub use zero_ecs_macros::tag_world;
+pub use zero_ecs_macros::EntityAttributes;
+
+pub use component::{is_same_component, SameComponent};
+pub use events::{EventReader, EventWriter, Events};
+pub use hierarchy::Hierarchy;
+pub use index::{ColumnIndex, IndexedMut};
+pub use permutation::Permutation;
+pub use sparse::{par_chunk_size, SparseChunk, SparseColumn};
+pub use spatial::{SpatialHash, SpatialPoint};
 
 pub use derive_more;
 pub use derive_more::From;
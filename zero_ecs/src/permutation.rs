@@ -0,0 +1,42 @@
+/// A reordering of a column, stored as the swaps that perform it, so that the same
+/// reordering can be applied to every column of a collection without cloning.
+#[derive(Debug, Default)]
+pub struct Permutation {
+    swaps: Vec<(usize, usize)>,
+}
+
+impl Permutation {
+    /// `order[i]` is the current index of the value that should end up at index `i`.
+    pub fn from_order(order: &[usize]) -> Self {
+        // which original value is at each position, and where each original value is
+        let mut value_at: Vec<usize> = (0..order.len()).collect();
+        let mut position_of: Vec<usize> = (0..order.len()).collect();
+        let mut swaps = vec![];
+
+        for (target, &value) in order.iter().enumerate() {
+            let source = position_of[value];
+            if source == target {
+                continue;
+            }
+            swaps.push((target, source));
+
+            let displaced = value_at[target];
+            value_at[source] = displaced;
+            position_of[displaced] = source;
+            value_at[target] = value;
+            position_of[value] = target;
+        }
+
+        Self { swaps }
+    }
+
+    pub fn is_identity(&self) -> bool {
+        self.swaps.is_empty()
+    }
+
+    pub fn apply<T>(&self, column: &mut [T]) {
+        for &(a, b) in &self.swaps {
+            column.swap(a, b);
+        }
+    }
+}
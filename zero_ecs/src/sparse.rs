@@ -0,0 +1,141 @@
+use std::collections::HashMap;
+use std::iter::{Copied, Zip};
+use std::slice;
+
+/// Storage for `#[sparse]` entity fields. Only entities that have the component take up
+/// space. Values are keyed by entity id.
+#[derive(Debug)]
+pub struct SparseColumn<T> {
+    values: Vec<T>,
+    ids: Vec<usize>,
+    slots: HashMap<usize, usize>,
+}
+
+impl<T> Default for SparseColumn<T> {
+    fn default() -> Self {
+        Self {
+            values: Vec::new(),
+            ids: Vec::new(),
+            slots: HashMap::new(),
+        }
+    }
+}
+
+impl<T> SparseColumn<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    pub fn contains(&self, id: usize) -> bool {
+        self.slots.contains_key(&id)
+    }
+
+    /// Inserts the value for id, returning the previous value if there was one.
+    pub fn insert(&mut self, id: usize, value: T) -> Option<T> {
+        if let Some(&slot) = self.slots.get(&id) {
+            return Some(std::mem::replace(&mut self.values[slot], value));
+        }
+        self.slots.insert(id, self.values.len());
+        self.values.push(value);
+        self.ids.push(id);
+        None
+    }
+
+    pub fn remove(&mut self, id: usize) -> Option<T> {
+        let slot = self.slots.remove(&id)?;
+        self.ids.swap_remove(slot);
+        if let Some(&moved_id) = self.ids.get(slot) {
+            self.slots.insert(moved_id, slot);
+        }
+        Some(self.values.swap_remove(slot))
+    }
+
+    pub fn clear(&mut self) {
+        self.values.clear();
+        self.ids.clear();
+        self.slots.clear();
+    }
+
+    pub fn get(&self, id: usize) -> Option<&T> {
+        self.slots.get(&id).map(|&slot| &self.values[slot])
+    }
+
+    pub fn get_mut(&mut self, id: usize) -> Option<&mut T> {
+        self.slots.get(&id).map(|&slot| &mut self.values[slot])
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &T)> {
+        self.ids.iter().copied().zip(self.values.iter())
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (usize, &mut T)> {
+        self.ids.iter().copied().zip(self.values.iter_mut())
+    }
+
+    /// Orders the values by where their entity is in the dense columns, so that they can be
+    /// walked together with the dense columns in a single pass.
+    pub fn sort_by_index(&mut self, index_lookup: &[Option<usize>]) {
+        let index_of = |id: &usize| index_lookup.get(*id).copied().flatten();
+        if self
+            .ids
+            .windows(2)
+            .all(|w| index_of(&w[0]) <= index_of(&w[1]))
+        {
+            return;
+        }
+
+        let mut pairs: Vec<(usize, T)> = self.ids.drain(..).zip(self.values.drain(..)).collect();
+        pairs.sort_by_key(|(id, _)| index_of(id));
+        (self.ids, self.values) = pairs.into_iter().unzip();
+
+        for (slot, id) in self.ids.iter().enumerate() {
+            self.slots.insert(*id, slot);
+        }
+    }
+}
+
+/// The values of a `SparseColumn` for one chunk of rows, in row order.
+pub type SparseChunk<'a, T> = Zip<Copied<slice::Iter<'a, usize>>, slice::IterMut<'a, T>>;
+
+impl<T> SparseColumn<T> {
+    /// Splits the values by the rows `0..chunk_size`, `chunk_size..2 * chunk_size` and so on,
+    /// one chunk for each of the `rows.div_ceil(chunk_size)` dense chunks, so that each chunk
+    /// can be walked together with the dense columns on its own thread.
+    pub fn chunks_by_index_mut(
+        &mut self,
+        index_lookup: &[Option<usize>],
+        rows: usize,
+        chunk_size: usize,
+    ) -> Vec<SparseChunk<'_, T>> {
+        self.sort_by_index(index_lookup);
+        let index_of = |id: &usize| index_lookup.get(*id).copied().flatten();
+
+        let mut ids = &self.ids[..];
+        let mut values = &mut self.values[..];
+        let mut chunks = Vec::with_capacity(rows.div_ceil(chunk_size));
+        for start in (0..rows).step_by(chunk_size) {
+            let end = start + chunk_size;
+            let split = ids.partition_point(|id| index_of(id).is_some_and(|index| index < end));
+            let (chunk_ids, rest_ids) = ids.split_at(split);
+            let (chunk_values, rest_values) = std::mem::take(&mut values).split_at_mut(split);
+            chunks.push(chunk_ids.iter().copied().zip(chunk_values.iter_mut()));
+            ids = rest_ids;
+            values = rest_values;
+        }
+        chunks
+    }
+}
+
+/// How many rows each task takes in the parallel queries that can't split the columns
+/// row by row.
+pub fn par_chunk_size(rows: usize) -> usize {
+    rows.div_ceil(rayon::current_num_threads() * 4).max(1)
+}
@@ -0,0 +1,113 @@
+use std::collections::HashMap;
+use std::marker::PhantomData;
+
+/// The entities in one cell, with their positions.
+type Cell<E> = Vec<(E, [f32; 2])>;
+
+/// A component with a position in 2D, that a world can keep a `SpatialHash` of.
+pub trait SpatialPoint {
+    fn point(&self) -> [f32; 2];
+}
+
+/// Entities bucketed by the position of their `T` component, in square cells. Finds the
+/// entities in an area without checking every entity. Positions are copied in when the hash
+/// is built, so it has to be rebuilt when they change.
+#[derive(Debug)]
+pub struct SpatialHash<T, E> {
+    cell_size: f32,
+    cells: HashMap<(i32, i32), Cell<E>>,
+    phantom: PhantomData<fn(&T)>,
+}
+
+impl<T, E> Default for SpatialHash<T, E> {
+    fn default() -> Self {
+        Self {
+            cell_size: 1.0,
+            cells: HashMap::new(),
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<T: SpatialPoint, E: Copy> SpatialHash<T, E> {
+    pub fn new(cell_size: f32) -> Self {
+        let mut hash = Self::default();
+        hash.reset(cell_size);
+        hash
+    }
+
+    pub fn cell_size(&self) -> f32 {
+        self.cell_size
+    }
+
+    /// Removes all entities, and changes the size of the cells.
+    pub fn reset(&mut self, cell_size: f32) {
+        assert!(cell_size > 0.0, "cell size must be positive");
+        self.cell_size = cell_size;
+        self.cells.clear();
+    }
+
+    pub fn insert(&mut self, entity: E, component: &T) {
+        let point = component.point();
+        self.cells
+            .entry(self.cell(point))
+            .or_default()
+            .push((entity, point));
+    }
+
+    fn cell(&self, point: [f32; 2]) -> (i32, i32) {
+        (
+            (point[0] / self.cell_size).floor() as i32,
+            (point[1] / self.cell_size).floor() as i32,
+        )
+    }
+
+    fn in_cells(&self, min: [f32; 2], max: [f32; 2]) -> impl Iterator<Item = &(E, [f32; 2])> + '_ {
+        let (min_x, min_y) = self.cell(min);
+        let (max_x, max_y) = self.cell(max);
+        // a box with more cells than the hash holds is cheaper to check against every cell that
+        // holds something. Counted in u128, a box over all of i32 has 2^64 cells
+        let span = |min: i32, max: i32| (i64::from(max) - i64::from(min) + 1).max(0) as u64;
+        let box_cells = u128::from(span(min_x, max_x)) * u128::from(span(min_y, max_y));
+        let check_all = box_cells > self.cells.len() as u128;
+        let visited = (!check_all).then(|| {
+            (min_x..=max_x)
+                .flat_map(move |x| (min_y..=max_y).map(move |y| (x, y)))
+                .filter_map(|cell| self.cells.get(&cell))
+        });
+        let kept = check_all.then(|| {
+            self.cells
+                .iter()
+                .filter(move |((x, y), _)| {
+                    (min_x..=max_x).contains(x) && (min_y..=max_y).contains(y)
+                })
+                .map(|(_, cell)| cell)
+        });
+        visited
+            .into_iter()
+            .flatten()
+            .chain(kept.into_iter().flatten())
+            .flatten()
+    }
+
+    /// The entities inside the box from `min` to `max`, edges included.
+    pub fn query_aabb(&self, min: [f32; 2], max: [f32; 2]) -> impl Iterator<Item = E> + '_ {
+        self.in_cells(min, max)
+            .filter(move |(_, point)| {
+                (min[0]..=max[0]).contains(&point[0]) && (min[1]..=max[1]).contains(&point[1])
+            })
+            .map(|(entity, _)| *entity)
+    }
+
+    /// The entities at most `radius` from `center`.
+    pub fn query_radius(&self, center: [f32; 2], radius: f32) -> impl Iterator<Item = E> + '_ {
+        let min = [center[0] - radius, center[1] - radius];
+        let max = [center[0] + radius, center[1] + radius];
+        self.in_cells(min, max)
+            .filter(move |(_, point)| {
+                let (dx, dy) = (point[0] - center[0], point[1] - center[1]);
+                dx * dx + dy * dy <= radius * radius
+            })
+            .map(|(entity, _)| *entity)
+    }
+}
@@ -0,0 +1,256 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use std::collections::BTreeMap;
+use syn::{Ident, Type};
+
+use crate::{
+    helpers::{format_collection_name, format_field_name, format_index_name, format_tick_names},
+    query_impl::CollectionComponentField,
+};
+
+/// Generates the deferred command buffers, `Commands` and `SyncCommands`, and `World::apply`.
+/// The component edits they can hold are generated by `get_component_edits`, once all
+/// entities have been tagged on the world.
+pub fn get_commands(world: &Ident, types: &[&Ident]) -> TokenStream {
+    let create_variants: Vec<_> = types
+        .iter()
+        .map(|ty| format_ident!("Create__{}", ty))
+        .collect();
+
+    let from_implementations = types.iter().zip(&create_variants).map(|(ty, variant)| {
+        quote! {
+            impl From<#ty> for WorldCommand {
+                fn from(e: #ty) -> Self {
+                    WorldCommand::#variant(e)
+                }
+            }
+        }
+    });
+
+    quote! {
+        #[allow(non_camel_case_types, private_interfaces)]
+        pub enum WorldCommand {
+            Destroy(Entity),
+            DestroyRecursive(Entity),
+            SetParent(Entity, Entity),
+            Set(ComponentEdit),
+            #(#create_variants(#types),)*
+        }
+
+        #(#from_implementations)*
+
+        /// Queues creates, destroys and component edits, to be applied with `World::apply`.
+        /// Can be used while iterating a query.
+        #[derive(Default)]
+        pub struct Commands {
+            commands: Vec<WorldCommand>,
+        }
+
+        #[allow(dead_code)]
+        impl Commands {
+            pub fn new() -> Self {
+                Self::default()
+            }
+
+            pub fn create<T>(&mut self, e: T)
+            where
+                WorldCommand: From<T>,
+            {
+                self.commands.push(e.into());
+            }
+
+            pub fn destroy(&mut self, entity: impl Into<Entity>) {
+                self.commands.push(WorldCommand::Destroy(entity.into()));
+            }
+
+            pub fn destroy_recursive(&mut self, entity: impl Into<Entity>) {
+                self.commands.push(WorldCommand::DestroyRecursive(entity.into()));
+            }
+
+            pub fn set_parent(&mut self, child: impl Into<Entity>, parent: impl Into<Entity>) {
+                self.commands
+                    .push(WorldCommand::SetParent(child.into(), parent.into()));
+            }
+
+            pub fn set<T: Send + 'static>(&mut self, entity: impl Into<Entity>, component: T) {
+                self.commands
+                    .push(WorldCommand::Set(ComponentEdit::new(entity.into(), component)));
+            }
+
+            pub fn len(&self) -> usize {
+                self.commands.len()
+            }
+
+            pub fn is_empty(&self) -> bool {
+                self.commands.is_empty()
+            }
+        }
+
+        /// Thread safe version of `Commands`, for use inside `par_iter` and `par_iter_mut`.
+        #[derive(Default)]
+        pub struct SyncCommands {
+            commands: std::sync::Mutex<Vec<WorldCommand>>,
+        }
+
+        #[allow(dead_code)]
+        impl SyncCommands {
+            pub fn new() -> Self {
+                Self::default()
+            }
+
+            fn push(&self, command: WorldCommand) {
+                self.commands
+                    .lock()
+                    .unwrap_or_else(|poisoned| poisoned.into_inner())
+                    .push(command);
+            }
+
+            pub fn create<T>(&self, e: T)
+            where
+                WorldCommand: From<T>,
+            {
+                self.push(e.into());
+            }
+
+            pub fn destroy(&self, entity: impl Into<Entity>) {
+                self.push(WorldCommand::Destroy(entity.into()));
+            }
+
+            pub fn destroy_recursive(&self, entity: impl Into<Entity>) {
+                self.push(WorldCommand::DestroyRecursive(entity.into()));
+            }
+
+            pub fn set_parent(&self, child: impl Into<Entity>, parent: impl Into<Entity>) {
+                self.push(WorldCommand::SetParent(child.into(), parent.into()));
+            }
+
+            pub fn set<T: Send + 'static>(&self, entity: impl Into<Entity>, component: T) {
+                self.push(WorldCommand::Set(ComponentEdit::new(entity.into(), component)));
+            }
+        }
+
+        impl From<SyncCommands> for Commands {
+            fn from(commands: SyncCommands) -> Self {
+                Commands {
+                    commands: commands
+                        .commands
+                        .into_inner()
+                        .unwrap_or_else(|poisoned| poisoned.into_inner()),
+                }
+            }
+        }
+
+        #[allow(dead_code)]
+        impl #world {
+            /// Applies queued commands, in the order they were queued.
+            pub fn apply(&mut self, commands: impl Into<Commands>) {
+                for command in commands.into().commands {
+                    match command {
+                        WorldCommand::Destroy(entity) => self.destroy(entity),
+                        WorldCommand::DestroyRecursive(entity) => self.destroy_recursive(entity),
+                        WorldCommand::SetParent(child, parent) => {
+                            self.set_parent(child, parent);
+                        }
+                        WorldCommand::Set(edit) => self.apply_component_edit(edit),
+                        #(WorldCommand::#create_variants(e) => {
+                            self.create(e);
+                        })*
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Generates `ComponentEdit` and `WorldSet`. Every column of the entity is tried, the component
+/// is set in the one whose type it resolves to.
+pub fn get_component_edits(
+    world: &Ident,
+    collection_component_fields: &[CollectionComponentField],
+) -> TokenStream {
+    // entity type -> its columns
+    let mut collections: BTreeMap<&String, Vec<&CollectionComponentField>> = BTreeMap::new();
+    for field in collection_component_fields {
+        collections
+            .entry(&field.collection_name)
+            .or_default()
+            .push(field);
+    }
+
+    let match_arms = collections.iter().map(|(collection, fields)| {
+        let enum_name = format_ident!("{}", collection);
+        let collection_field_name = format_field_name(collection);
+        let collection_name = format_collection_name(collection);
+        let set_columns = fields.iter().map(|field| {
+            let column = format_ident!("{}", field.field_name);
+            let field_type = syn::parse_str::<Type>(&field.field_type).expect("component type");
+            let set_code = if field.sparse {
+                quote! { collection.#column.insert(entity.id, component); }
+            } else if field.indexed {
+                let index_name = format_index_name(&column);
+                quote! {
+                    let old = std::mem::replace(&mut collection.#column[index], component);
+                    collection.#index_name.replace(&old, &collection.#column[index], entity);
+                }
+            } else if field.tracked {
+                let (_, changed_name) = format_tick_names(&column);
+                quote! {
+                    collection.#column[index] = component;
+                    collection.#changed_name[index] = collection.change_tick;
+                }
+            } else {
+                quote! { collection.#column[index] = component; }
+            };
+            quote! {
+                let component = match SameComponent::<#field_type>::into_component(component) {
+                    Ok(component) => {
+                        #set_code
+                        return true;
+                    }
+                    Err(component) => component,
+                };
+            }
+        });
+        quote! {
+            EntityType::#enum_name => {
+                let collection: &mut #collection_name = &mut self.#collection_field_name;
+                let Some(index) = collection.index_of(entity) else {
+                    return false;
+                };
+                #(#set_columns)*
+                false
+            }
+        }
+    });
+
+    quote! {
+        /// A component queued by `Commands::set`, set with `WorldSet` when the commands are
+        /// applied.
+        pub struct ComponentEdit(Box<dyn FnOnce(&mut #world) + Send>);
+
+        impl ComponentEdit {
+            fn new<T: Send + 'static>(entity: Entity, component: T) -> Self {
+                ComponentEdit(Box::new(move |world: &mut #world| {
+                    WorldSet::<T>::set(world, entity, component);
+                }))
+            }
+        }
+
+        impl<T: 'static> WorldSet<T> for #world {
+            #[allow(unused_variables)]
+            fn set(&mut self, entity: Entity, component: T) -> bool {
+                match entity.entity_type {
+                    #(#match_arms)*
+                    _ => false,
+                }
+            }
+        }
+
+        #[allow(dead_code)]
+        impl #world {
+            fn apply_component_edit(&mut self, edit: ComponentEdit) {
+                (edit.0)(self);
+            }
+        }
+    }
+}
@@ -1,6 +1,8 @@
 use proc_macro2::TokenStream;
 use quote::quote;
-pub fn get_default_queries() -> TokenStream {
+use syn::Ident;
+
+pub fn get_default_queries(world: &Ident) -> TokenStream {
     quote! {
         #[derive(Default, Debug)]
         pub struct Query<T> {
@@ -51,71 +53,65 @@ pub fn get_default_queries() -> TokenStream {
 
         impl<'a, T: 'a + Send> Query<T>
         {
-            pub fn iter(&self, world: &'a World) -> impl Iterator<Item = T> + 'a
+            pub fn iter(&self, world: &'a #world) -> impl Iterator<Item = T> + 'a
             where
-                World: QueryFrom<'a, T>,
+                #world: QueryFrom<'a, T>,
             {
                 world.query_from()
             }
         }
         impl<'a, T: 'a + Send> Query<T>
         {
-            pub fn par_iter(&self, world: &'a World) -> impl ParallelIterator<Item = T> + 'a
+            pub fn par_iter(&self, world: &'a #world) -> impl ParallelIterator<Item = T> + 'a
             where
-                World: QueryFrom<'a, T>,
+                #world: QueryFrom<'a, T>,
             {
                 world.par_query_from()
             }
         }
         impl<'a, T: 'a + Send> Query<T> {
-            pub fn iter_mut(&self, world: &'a mut World) -> impl Iterator<Item = T> + 'a
+            pub fn iter_mut(&self, world: &'a mut #world) -> impl Iterator<Item = T> + 'a
             where
-                World: QueryMutFrom<'a, T>,
+                #world: QueryMutFrom<'a, T>,
             {
                 world.query_mut_from()
             }
         }
         impl<'a, T: 'a + Send> Query<T>
         {
-            pub fn par_iter_mut(&self, world: &'a mut World) -> impl ParallelIterator<Item = T> + 'a
+            pub fn par_iter_mut(&self, world: &'a mut #world) -> impl ParallelIterator<Item = T> + 'a
             where
-                World: QueryMutFrom<'a, T>,
+                #world: QueryMutFrom<'a, T>,
             {
                 world.par_query_mut_from()
             }
         }
         impl<'a, T: 'a + Send> Query<T> {
-            pub fn get(&self, world: &'a World, entity: Entity) -> Option<T>
-            where
-                World: QueryFrom<'a, T>,
-            {
-                world.get_from(entity)
+            pub fn get(&self, world: &'a #world, entity: impl EntityGet<'a, T>) -> Option<T> {
+                entity.get_in(world)
             }
         }
         impl<'a, T: 'a + Send> Query<T> {
-            pub fn get_mut(&self, world: &'a mut World, entity: Entity) -> Option<T>
-            where
-                World: QueryMutFrom<'a, T>,
-            {
-                world.get_mut_from(entity)
+            pub fn get_mut(&self, world: &'a mut #world, entity: impl EntityGetMut<'a, T>) -> Option<T> {
+                entity.get_mut_in(world)
             }
         }
 
         // implement len
         impl<'a, T: 'a + Send> Query<T> {
-            pub fn len(&self, world: &'a World) -> usize
+            pub fn len(&self, world: &'a #world) -> usize
             where
-                World: LenFrom<'a, T>,
+                #world: LenFrom<'a, T>,
             {
-                world.len()
+                LenFrom::<'a, T>::len(world)
             }
         }
 
         // impl at_mut
         impl<'a, T: 'a + Send> Query<T> {
-            pub fn at_mut(&self, world: &'a mut World, index: usize) -> Option<T>
+            pub fn at_mut(&self, world: &'a mut #world, index: usize) -> Option<T>
             where
-                World: QueryMutFrom<'a, T>,
+                #world: QueryMutFrom<'a, T>,
             {
                 world.at_mut(index)
             }
@@ -123,9 +119,9 @@ pub fn get_default_queries() -> TokenStream {
 
         // impl at
         impl<'a, T: 'a + Send> Query<T> {
-            pub fn at(&self, world: &'a World, index: usize) -> Option<T>
+            pub fn at(&self, world: &'a #world, index: usize) -> Option<T>
             where
-                World: QueryFrom<'a, T>,
+                #world: QueryFrom<'a, T>,
             {
                 world.at(index)
             }
@@ -134,17 +130,17 @@ pub fn get_default_queries() -> TokenStream {
 
         pub struct WithQueryMut<'a, T> {
             query: Query<T>,
-            world: &'a mut World,
+            world: &'a mut #world,
         }
         pub struct WithQuery<'a, T> {
             query: Query<T>,
-            world: &'a World,
+            world: &'a #world,
         }
 
         #[allow(dead_code)]
         impl<'a, T> WithQueryMut<'a, T>
-            where World: QueryMutFrom<'a, T>,
-                World: LenFrom<'a, T>,
+            where #world: QueryMutFrom<'a, T>,
+                #world: LenFrom<'a, T>,
                 T: 'a + Send,
         {
             pub fn iter_mut<U>(&'a mut self) -> impl Iterator<Item = U> + 'a
@@ -157,7 +153,7 @@ pub fn get_default_queries() -> TokenStream {
             {
                 self.query.par_iter_mut(self.world).map(|e|e.into())
             }
-            pub fn get_mut<U>(&'a mut self, entity: Entity) -> Option<U>
+            pub fn get_mut<U>(&'a mut self, entity: impl EntityGetMut<'a, T>) -> Option<U>
                 where T: Into<U>,
             {
                 self.query.get_mut(self.world, entity).map(|e| e.into())
@@ -180,8 +176,8 @@ pub fn get_default_queries() -> TokenStream {
 
         #[allow(dead_code)]
         impl<'a, T> WithQuery<'a, T>
-            where World: QueryFrom<'a, T>,
-                World: LenFrom<'a, T>,
+            where #world: QueryFrom<'a, T>,
+                #world: LenFrom<'a, T>,
                 T: 'a + Send,
         {
             pub fn iter<U>(&'a self) -> impl Iterator<Item = U> + 'a
@@ -194,7 +190,7 @@ pub fn get_default_queries() -> TokenStream {
             {
                 self.query.par_iter(self.world).map(|e|e.into())
             }
-            pub fn get<U>(&'a self, entity: Entity) -> Option<U>
+            pub fn get<U>(&'a self, entity: impl EntityGet<'a, T>) -> Option<U>
                 where T: Into<U>, U: Send
             {
                 self.query.get(self.world, entity).map(|e|e.into())
@@ -213,10 +209,10 @@ pub fn get_default_queries() -> TokenStream {
         }
 
         #[allow(dead_code)]
-        impl World {
+        impl #world {
             pub fn with_query_mut<'a, T: 'a + Send>(&'a mut self, query: Query<T>) -> WithQueryMut<'a, T>
             where
-                World: QueryMutFrom<'a, T>,
+                #world: QueryMutFrom<'a, T>,
             {
                 WithQueryMut {
                     query,
@@ -225,10 +221,10 @@ pub fn get_default_queries() -> TokenStream {
             }
         }
         #[allow(dead_code)]
-        impl World {
+        impl #world {
             pub fn with_query<'a, T: 'a + Send>(&'a self, query: Query<T>) -> WithQuery<'a, T>
             where
-                World: QueryFrom<'a, T>,
+                #world: QueryFrom<'a, T>,
             {
                 WithQuery {
                     query,
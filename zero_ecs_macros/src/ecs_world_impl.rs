@@ -1,30 +1,135 @@
 use crate::{
+    commands::get_commands,
     default_queries::get_default_queries,
-    helpers::{format_collection_name, format_field_name},
+    events::{format_events_field_name, get_events},
+    helpers::{
+        entity_type_path, format_collection_name, format_entity_name, format_field_name,
+        format_ref_name, format_world_entities_name, is_plain_entity_type, world_module_name,
+    },
+    resources::{format_resource_field_name, get_resources},
+    typed_entity::get_typed_entity,
 };
+use macro_magic::mm_core::export_tokens_macro_ident;
 use proc_macro::TokenStream;
-use quote::quote;
+use quote::{format_ident, quote};
 use syn::{
     parse::{Parse, ParseStream},
     punctuated::Punctuated,
-    Ident, Token,
+    Ident, Token, Type, TypePath,
 };
 
-pub struct StructList(pub Punctuated<Ident, Token![,]>);
+pub struct StructList(pub Punctuated<Type, Token![,]>);
 
 impl Parse for StructList {
     fn parse(input: ParseStream) -> syn::Result<Self> {
-        // Parse zero or more identifiers separated by commas.
-        let list = Punctuated::<Ident, Token![,]>::parse_terminated(input)?;
+        // Parse zero or more entity types separated by commas.
+        let list = Punctuated::<Type, Token![,]>::parse_terminated(input)?;
         Ok(StructList(list))
     }
 }
 
+/// `Name: A, B; resources: X, Y; events: E, F; spatial: P`, the name of the world, its entity
+/// types, its resources, its event types and the component it keeps a `SpatialHash` of. Only
+/// the entity types are required, the name defaults to `World`.
+struct WorldInput {
+    name: Option<Ident>,
+    types: StructList,
+    resources: Vec<Type>,
+    events: Vec<Type>,
+    spatial: Option<Type>,
+}
+
+/// Types separated by commas, up to the next `;`.
+fn parse_type_list(input: ParseStream) -> syn::Result<Punctuated<Type, Token![,]>> {
+    let mut types = Punctuated::<Type, Token![,]>::new();
+    while !input.is_empty() && !input.peek(Token![;]) {
+        types.push_value(input.parse()?);
+        if !input.peek(Token![,]) {
+            break;
+        }
+        types.push_punct(input.parse()?);
+    }
+    Ok(types)
+}
+
+impl Parse for WorldInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut name = None;
+        if input.peek(Ident) && input.peek2(Token![:]) && !input.peek2(Token![::]) {
+            name = Some(input.parse()?);
+            input.parse::<Token![:]>()?;
+        }
+
+        let types = parse_type_list(input)?;
+
+        let mut resources = vec![];
+        let mut events = vec![];
+        let mut spatial = None;
+        while input.parse::<Option<Token![;]>>()?.is_some() && !input.is_empty() {
+            let keyword: Ident = input.parse()?;
+            input.parse::<Token![:]>()?;
+            if keyword == "resources" {
+                resources.extend(parse_type_list(input)?);
+            } else if keyword == "events" {
+                events.extend(parse_type_list(input)?);
+            } else if keyword == "spatial" {
+                spatial = Some(input.parse()?);
+            } else {
+                return Err(syn::Error::new(
+                    keyword.span(),
+                    "expected `resources:`, `events:` or `spatial:`",
+                ));
+            }
+        }
+        if !input.is_empty() {
+            return Err(
+                input.error("expected `;` followed by `resources:`, `events:` or `spatial:`")
+            );
+        }
+
+        Ok(WorldInput {
+            name,
+            types: StructList(types),
+            resources,
+            events,
+            spatial,
+        })
+    }
+}
+
 pub fn ecs_world(input: TokenStream) -> TokenStream {
-    // Parse the input tokens as a list of identifiers separated by commas.
-    let types = syn::parse_macro_input!(input as StructList);
+    // Parse the name, the entity types, and the resources, events and spatial component after `;`.
+    let WorldInput {
+        name,
+        types,
+        resources,
+        events,
+        spatial,
+    } = syn::parse_macro_input!(input as WorldInput);
+    let world = name.unwrap_or_else(|| format_ident!("World"));
+    let entities = format_world_entities_name(&world);
+
+    let entity_types: Vec<&TypePath> = match types.0.iter().map(entity_type_path).collect() {
+        Ok(entity_types) => entity_types,
+        Err(err) => return err.to_compile_error().into(),
+    };
+    // everything generated per entity type is named after this, for `Projectile<Arrow>` it is
+    // an alias, `ProjectileArrow`
+    let names: Vec<Ident> = entity_types
+        .iter()
+        .map(|ty| format_entity_name(ty))
+        .collect();
+    let entity_aliases = entity_types
+        .iter()
+        .zip(&names)
+        .filter(|(ty, _)| !is_plain_entity_type(ty))
+        .map(|(ty, name)| {
+            quote! {
+                pub type #name = #ty;
+            }
+        });
 
-    let fields = types.0.iter().map(|ty| {
+    let fields = names.iter().map(|ty| {
         let field_name = format_field_name(ty);
         // type name should be collection
         let type_name = format_collection_name(ty);
@@ -33,8 +138,45 @@ pub fn ecs_world(input: TokenStream) -> TokenStream {
         }
     });
 
-    let enum_names: Vec<_> = types
-        .0
+    // the spatial hash is filled by `rebuild_spatial_hash`, generated with the other code
+    // that needs to know which entities have the component
+    let spatial_field = spatial.map(|ty| {
+        quote! {
+            pub __spatial: SpatialHash<#ty, Entity>,
+        }
+    });
+
+    let resource_fields = resources.iter().map(|ty| {
+        let field_name = format_resource_field_name(ty);
+        quote! {
+            pub #field_name: #ty
+        }
+    });
+
+    let event_fields = events.iter().map(|ty| {
+        let field_name = format_events_field_name(ty);
+        quote! {
+            pub #field_name: Events<#ty>
+        }
+    });
+
+    // the resources and events are on the world, the entities are tagged with them so the
+    // systems, which only see the tokens of the entities, know about them
+    let resource_tags = resources.iter().map(|ty| {
+        let field_name = format_resource_field_name(ty);
+        quote! {
+            #field_name: PhantomData<#ty>
+        }
+    });
+
+    let event_tags = events.iter().map(|ty| {
+        let field_name = format_events_field_name(ty);
+        quote! {
+            #field_name: PhantomData<#ty>
+        }
+    });
+
+    let enum_names: Vec<_> = names
         .iter()
         .map(|ty| {
             quote! {
@@ -43,44 +185,130 @@ pub fn ecs_world(input: TokenStream) -> TokenStream {
         })
         .collect();
 
-    let create_implementations = types.0.iter().map(|ty| {
+    let create_implementations = names.iter().map(|ty| {
         let collection_field_name = format_field_name(ty);
         quote! {
-            impl WorldCreate<#ty> for World {
-                fn create(&mut self, e: #ty) -> Entity {
+            impl WorldCreate<#ty> for #entities {
+                fn create(&mut self, e: #ty) -> TypedEntity<#ty> {
                     self.#collection_field_name.create(e)
                 }
+                fn create_batch(&mut self, entities: impl IntoIterator<Item = #ty>) -> Vec<TypedEntity<#ty>> {
+                    self.#collection_field_name.create_batch(entities)
+                }
+                fn reserve(&mut self, additional: usize) {
+                    WorldCreate::<#ty>::reserve(&mut self.#collection_field_name, additional);
+                }
+            }
+        }
+    });
+
+    let take_implementations = names.iter().map(|ty| {
+        let collection_field_name = format_field_name(ty);
+        let collection_name = format_collection_name(ty);
+        quote! {
+            impl WorldTake<#ty> for #entities {
+                fn take(&mut self, e: TypedEntity<#ty>) -> Option<#ty> {
+                    let taken = <#collection_name as WorldTake<#ty>>::take(&mut self.#collection_field_name, e)?;
+                    self.__hierarchy.remove(e.entity());
+                    Some(taken)
+                }
             }
         }
     });
 
-    let destroy_match_calls = types.0.iter().map(|ty| {
+    let sort_implementations = names.iter().map(|ty| {
+        let collection_field_name = format_field_name(ty);
+        let ref_name = format_ref_name(ty);
+        quote! {
+            impl WorldSort<#ty> for #entities {
+                type Item<'a> = #ref_name<'a>;
+                fn sort_by_key<K: Ord>(&mut self, f: impl FnMut(#ref_name<'_>) -> K) {
+                    self.#collection_field_name.sort_by_key(f)
+                }
+            }
+        }
+    });
+
+    let destroy_match_calls = names.iter().map(|ty| {
         let collection_field_name = format_field_name(ty);
         quote! {
             EntityType::#ty => self.#collection_field_name.destroy(e),
         }
     });
 
+    let collection_field_names: Vec<_> = names.iter().map(format_field_name).collect();
+    let collection_type_names: Vec<_> = names.iter().map(format_collection_name).collect();
+
     let destroy_implementation = quote! {
-        impl WorldDestroy for World {
+        impl WorldDestroy for #entities {
             fn destroy(&mut self, e: Entity) {
                 match e.entity_type {
                     #(#destroy_match_calls)*
                 }
+                self.__hierarchy.remove(e);
             }
         }
+
+        #(
+            impl WorldDestroy<TypedEntity<#enum_names>> for #entities {
+                fn destroy(&mut self, e: TypedEntity<#enum_names>) {
+                    self.#collection_field_names.destroy(e.entity());
+                    self.__hierarchy.remove(e.entity());
+                }
+            }
+        )*
     };
 
-    let default_queries = get_default_queries();
+    let default_queries = get_default_queries(&entities);
+    let commands = get_commands(&entities, &names.iter().collect::<Vec<_>>());
+    let typed_entity = get_typed_entity(&entities, &names.iter().collect::<Vec<_>>());
+    let resources = get_resources(&world, &resources);
+    let events = get_events(&world, &events);
     // Generate the struct World with the computed fields.
     let expanded = quote! {
-        #[expand_world(#(#enum_names),*)]
-        #[export_tokens]
+        #[expand_world(#(#entity_types),*)]
+        #[export_tokens(#world)]
         #[derive(Default)]
         #[allow(non_camel_case_types)]
         #[allow(non_snake_case)]
-        pub struct World {
+        pub struct #entities {
             #(#fields,)*
+            #(#resource_tags,)*
+            #(#event_tags,)*
+            #spatial_field
+            pub __hierarchy: Hierarchy<Entity>,
+        }
+
+        /// The entities, with the resources and events of the world next to them. Derefs to
+        /// the entities, a `#[system]` that borrows a resource or reads events gets only those.
+        #[derive(Default)]
+        #[allow(non_snake_case)]
+        pub struct #world {
+            pub __entities: #entities,
+            #(#resource_fields,)*
+            #(#event_fields,)*
+        }
+
+        impl std::ops::Deref for #world {
+            type Target = #entities;
+
+            fn deref(&self) -> &#entities {
+                &self.__entities
+            }
+        }
+
+        impl std::ops::DerefMut for #world {
+            fn deref_mut(&mut self) -> &mut #entities {
+                &mut self.__entities
+            }
+        }
+
+        /// Borrows the collections of the world apart from its other fields, so
+        /// `#[system_for_each]` can borrow resources and events next to a query.
+        #[doc(hidden)]
+        pub struct __WorldEntities<'a> {
+            #( pub #collection_field_names: &'a mut #collection_type_names, )*
+            pub __phantom: PhantomData<&'a mut ()>,
         }
 
         #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -88,70 +316,348 @@ pub fn ecs_world(input: TokenStream) -> TokenStream {
             #(#enum_names,)*
         }
 
+        #(#entity_aliases)*
+
+        /// Implemented by every entity type in the world.
+        pub trait EntityKind {
+            const ENTITY_TYPE: EntityType;
+        }
+
+        #(
+            impl EntityKind for #enum_names {
+                const ENTITY_TYPE: EntityType = EntityType::#enum_names;
+            }
+        )*
+
         #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
         pub struct Entity {
             pub entity_type: EntityType,
-            pub id: usize
+            pub id: usize,
+            pub generation: u32,
         }
 
+        #typed_entity
+
         #default_queries
 
+        #resources
+
+        #events
+
+        #commands
+
         #destroy_implementation
 
         // creates
         #(#create_implementations)*
 
+        #(#take_implementations)*
+
+        #(#sort_implementations)*
+
         #[allow(dead_code)]
-        impl World {
+        impl #entities {
             pub fn query_mut<'a, T: 'a + Send>(&'a mut self) -> impl Iterator<Item = T> + 'a
             where
-                World: QueryMutFrom<'a, T>,
+                #entities: QueryMutFrom<'a, T>,
             {
                 QueryMutFrom::<T>::query_mut_from(self)
             }
             pub fn par_query_mut<'a, T: 'a + Send>(&'a mut self) -> impl ParallelIterator<Item = T> + 'a
             where
-                World: QueryMutFrom<'a, T>,
+                #entities: QueryMutFrom<'a, T>,
             {
                 QueryMutFrom::<T>::par_query_mut_from(self)
             }
 
-            pub fn get_mut<'a, T: 'a + Send>(&'a mut self, entity: Entity) -> Option<T>
+            pub fn get_mut<'a, T: 'a + Send>(
+                &'a mut self,
+                entity: impl EntityGetMut<'a, T>,
+            ) -> Option<T> {
+                entity.get_mut_in(self)
+            }
+
+            /// Number of entities, of all types.
+            pub fn len(&self) -> usize {
+                0 #(+ self.#collection_field_names.len())*
+            }
+
+            pub fn is_empty(&self) -> bool {
+                self.len() == 0
+            }
+
+            /// Number of entities of the given type.
+            pub fn len_of(&self, entity_type: EntityType) -> usize {
+                match entity_type {
+                    #(EntityType::#enum_names => self.#collection_field_names.len(),)*
+                }
+            }
+
+            /// Returns false once the entity has been destroyed, taken or migrated.
+            pub fn is_alive(&self, entity: impl Into<Entity>) -> bool {
+                let entity = entity.into();
+                match entity.entity_type {
+                    #(EntityType::#enum_names => self.#collection_field_names.is_alive(entity),)*
+                }
+            }
+
+            /// Destroys all entities. Handles to them become stale.
+            pub fn clear(&mut self) {
+                #(self.#collection_field_names.clear();)*
+                self.__hierarchy.clear();
+            }
+
+            /// Destroys all entities of the given type.
+            pub fn clear_type(&mut self, entity_type: EntityType) {
+                match entity_type {
+                    #(EntityType::#enum_names => self.#collection_field_names.clear(),)*
+                }
+                self.__hierarchy.retain(|entity| entity.entity_type != entity_type);
+            }
+
+            /// Ends the frame for `Added` and `Changed` filters, usually called after the
+            /// systems have run. What was added or changed before no longer matches, and the
+            /// recorded destroyed entities are dropped.
+            pub fn clear_trackers(&mut self) {
+                #(self.#collection_field_names.clear_trackers();)*
+            }
+
+            pub fn create<T>(&mut self, e: T) -> TypedEntity<T>
+            where
+                #entities: WorldCreate<T>,
+            {
+                WorldCreate::<T>::create(self, e)
+            }
+
+            /// Creates all entities, reserving room for them up front.
+            pub fn create_batch<T>(
+                &mut self,
+                entities: impl IntoIterator<Item = T>,
+            ) -> Vec<TypedEntity<T>>
+            where
+                #entities: WorldCreate<T>,
+            {
+                WorldCreate::<T>::create_batch(self, entities)
+            }
+
+            pub fn destroy<E>(&mut self, e: E)
             where
-                World: QueryMutFrom<'a, T>,
+                #entities: WorldDestroy<E>,
             {
-                QueryMutFrom::<T>::get_mut_from(self, entity)
+                WorldDestroy::<E>::destroy(self, e)
+            }
+
+            /// Destroys the entity, its children, their children and so on.
+            pub fn destroy_recursive(&mut self, e: impl Into<Entity>) {
+                let e = e.into();
+                let descendants = self.__hierarchy.descendants(e);
+                self.destroy(e);
+                for descendant in descendants {
+                    self.destroy(descendant);
+                }
+            }
+
+            /// Makes `child` a child of `parent`, moving it from its old parent. Returns false
+            /// if either is not alive, or if `parent` is `child` or one of its descendants.
+            pub fn set_parent(&mut self, child: impl Into<Entity>, parent: impl Into<Entity>) -> bool {
+                let (child, parent) = (child.into(), parent.into());
+                self.is_alive(child)
+                    && self.is_alive(parent)
+                    && self.__hierarchy.set_parent(child, parent)
+            }
+
+            /// Detaches the entity from its parent and returns the parent.
+            pub fn remove_parent(&mut self, child: impl Into<Entity>) -> Option<Entity> {
+                self.__hierarchy.remove_parent(child.into())
+            }
+
+            pub fn parent(&self, child: impl Into<Entity>) -> Option<Entity> {
+                self.__hierarchy.parent(child.into())
+            }
+
+            /// The children of the entity, in the order they were added.
+            pub fn children(&self, parent: impl Into<Entity>) -> &[Entity] {
+                self.__hierarchy.children(parent.into())
+            }
+
+            /// Reserves room for at least `additional` more entities of type `T`.
+            pub fn reserve<T>(&mut self, additional: usize)
+            where
+                #entities: WorldCreate<T>,
+            {
+                WorldCreate::<T>::reserve(self, additional)
+            }
+
+            /// Removes the entity and returns it, rebuilt from its components. `on_destroy` is
+            /// called as for `destroy`.
+            pub fn take<T>(&mut self, entity: impl EntityHandle<T>) -> Option<T>
+            where
+                #entities: WorldTake<T>,
+            {
+                WorldTake::<T>::take(self, entity.typed()?)
+            }
+
+            /// Replaces a component of the entity. Returns false if the entity is gone or
+            /// doesn't have that component.
+            pub fn set<T>(&mut self, entity: impl Into<Entity>, component: T) -> bool
+            where
+                #entities: WorldSet<T>,
+            {
+                WorldSet::<T>::set(self, entity.into(), component)
+            }
+
+            /// Adds a `#[sparse]` component to the entity, replacing the old one if it had
+            /// it. Returns false if the entity is gone or has no such sparse field.
+            pub fn insert<T>(&mut self, entity: impl Into<Entity>, component: T) -> bool
+            where
+                #entities: WorldSparse<T>,
+            {
+                WorldSparse::<T>::insert(self, entity.into(), component)
+            }
+
+            /// The entities whose `#[indexed]` component `T` equals `value`, looked up in the
+            /// index instead of iterating.
+            pub fn find_by<T>(&self, value: &T) -> Vec<Entity>
+            where
+                T: Clone + Eq + std::hash::Hash,
+                #entities: WorldIndex<T>,
+            {
+                WorldIndex::<T>::find_by(self, value)
+            }
+
+            /// Mutable access to an `#[indexed]` component of the entity. The index is updated
+            /// when the returned guard is dropped.
+            pub fn indexed_mut<T>(&mut self, entity: impl Into<Entity>) -> Option<IndexedMut<'_, T, Entity>>
+            where
+                T: Clone + Eq + std::hash::Hash,
+                #entities: WorldIndex<T>,
+            {
+                WorldIndex::<T>::indexed_mut(self, entity.into())
+            }
+
+            /// The entities of type `T` destroyed, taken or migrated since the last
+            /// `clear_trackers`, with the components destroyed ones kept.
+            pub fn removed<T>(&self) -> &[<#entities as WorldRemoved<T>>::Removed]
+            where
+                #entities: WorldRemoved<T>,
+            {
+                WorldRemoved::<T>::removed(self)
+            }
+
+            /// Removes a `#[sparse]` component from the entity and returns it.
+            pub fn remove<T>(&mut self, entity: impl Into<Entity>) -> Option<T>
+            where
+                #entities: WorldSparse<T>,
+            {
+                WorldSparse::<T>::remove(self, entity.into())
+            }
+
+            /// Reorders the storage of one entity type by key, which changes the order queries
+            /// iterate it in. Handles stay valid.
+            pub fn sort_by_key<T, K: Ord>(
+                &mut self,
+                f: impl FnMut(<#entities as WorldSort<T>>::Item<'_>) -> K,
+            ) where
+                #entities: WorldSort<T>,
+            {
+                WorldSort::<T>::sort_by_key(self, f)
+            }
+
+            /// Turns the entity into another entity type. Components of the same type are
+            /// moved over, the others are taken from `to`. Returns the new handle, the old
+            /// one is no longer valid. Calls `on_destroy` of the old type and `on_create` of
+            /// the new one.
+            pub fn migrate<F, T>(
+                &mut self,
+                entity: impl EntityHandle<F>,
+                to: T,
+            ) -> Option<TypedEntity<T>>
+            where
+                #entities: WorldMigrate<F, T>,
+            {
+                WorldMigrate::<F, T>::migrate(self, entity.typed()?, to)
             }
         }
 
         #[allow(dead_code)]
-        impl World {
+        impl #entities {
             pub fn query<'a, T: 'a + Send>(&'a self) -> impl Iterator<Item = T> + 'a
             where
-                World: QueryFrom<'a, T>,
+                #entities: QueryFrom<'a, T>,
             {
                 QueryFrom::<T>::query_from(self)
             }
             pub fn par_query<'a, T: 'a + Send>(&'a self) -> impl ParallelIterator<Item = T> + 'a
             where
-                World: QueryFrom<'a, T>,
+                #entities: QueryFrom<'a, T>,
             {
                 QueryFrom::<T>::par_query_from(self)
             }
-            pub fn get<'a, T: 'a + Send>(&'a self, entity: Entity) -> Option<T>
-            where
-                World: QueryFrom<'a, T>,
-            {
-                QueryFrom::<T>::get_from(self, entity)
+            pub fn get<'a, T: 'a + Send>(&'a self, entity: impl EntityGet<'a, T>) -> Option<T> {
+                entity.get_in(self)
             }
         }
         pub trait WorldCreate<T> {
-            fn create(&mut self, e: T) -> Entity;
+            fn create(&mut self, e: T) -> TypedEntity<T>;
+            /// Creates all entities, reserving room for them up front.
+            fn create_batch(&mut self, entities: impl IntoIterator<Item = T>) -> Vec<TypedEntity<T>>;
+            fn reserve(&mut self, additional: usize);
+        }
+        pub trait WorldTake<T> {
+            fn take(&mut self, e: TypedEntity<T>) -> Option<T>;
+        }
+        pub trait WorldSet<T> {
+            fn set(&mut self, entity: Entity, component: T) -> bool;
+        }
+        pub trait WorldSparse<T> {
+            fn insert(&mut self, entity: Entity, component: T) -> bool;
+            fn remove(&mut self, entity: Entity) -> Option<T>;
         }
-        pub trait WorldDestroy {
-            fn destroy(&mut self, e: Entity);
+        pub trait WorldIndex<T: Clone + Eq + std::hash::Hash> {
+            fn find_by(&self, value: &T) -> Vec<Entity>;
+            fn indexed_mut(&mut self, entity: Entity) -> Option<IndexedMut<'_, T, Entity>>;
+        }
+        pub trait WorldMigrate<F, T> {
+            fn migrate(&mut self, entity: TypedEntity<F>, to: T) -> Option<TypedEntity<T>>;
+        }
+        pub trait WorldSort<T> {
+            type Item<'a>
+            where
+                Self: 'a;
+            fn sort_by_key<K: Ord>(&mut self, f: impl FnMut(Self::Item<'_>) -> K);
+        }
+        pub trait WorldDestroy<E = Entity> {
+            fn destroy(&mut self, e: E);
+        }
+        /// Implemented for entity types with `#[entity(track_removed)]`.
+        pub trait WorldRemoved<T> {
+            /// A removed entity, with the components it kept if it was destroyed.
+            type Removed;
+            /// The entities destroyed, taken or migrated since the last `clear_trackers`,
+            /// oldest first.
+            fn removed(&self) -> &[Self::Removed];
         }
     };
 
-    expanded.into()
+    // a named world keeps what it generates in its own module, so several can live side by side
+    match world_module_name(&world) {
+        Some(module) => {
+            // `#[system(Name)]` and friends look up the world's exported tokens next to `Name`
+            let tokens_macro = export_tokens_macro_ident(&world);
+            quote! {
+                pub mod #module {
+                    use super::*;
+
+                    #expanded
+                }
+                pub use #module::#world;
+                #[allow(unused_imports)]
+                pub use #module::#entities;
+                #[doc(hidden)]
+                pub use #module::#tokens_macro;
+            }
+        }
+        None => expanded,
+    }
+    .into()
 }
@@ -1,103 +1,663 @@
 use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
 use quote::quote;
-use syn::{spanned::Spanned, Error, Fields, ItemStruct};
+use std::collections::HashMap;
+use syn::{
+    meta::ParseNestedMeta, parse::Parser, spanned::Spanned, Error, Field, Fields, Ident,
+    ItemStruct, Member, Path, Type,
+};
 
-use crate::helpers::format_collection_name;
+use crate::helpers::{
+    column_type_params, component_key, field_member, format_collection_name, format_column_name,
+    format_column_type_name, format_index_name, format_ref_name, format_removed_name,
+    format_tick_names, is_indexed, is_keep_removed, is_sparse, is_tracked, option_inner_type,
+};
 
-pub fn entity(_: TokenStream, input: TokenStream) -> TokenStream {
+/// Options given as `#[entity(...)]`. They are kept on the exported struct as
+/// `#[entity_options(...)]`, for the world to read when it generates the storage.
+#[derive(Default)]
+pub struct EntityArgs {
+    /// Destroy shifts the columns down instead of swap-removing, keeping creation order.
+    pub ordered: bool,
+    /// Destroyed, taken and migrated entities are recorded until `clear_trackers`, see
+    /// `WorldRemoved`.
+    pub track_removed: bool,
+    /// Called with the handle and the components of each created entity.
+    pub on_create: Option<Path>,
+    /// Called with the handle and the components of each destroyed entity.
+    pub on_destroy: Option<Path>,
+}
+
+impl EntityArgs {
+    fn parse(&mut self, meta: ParseNestedMeta) -> syn::Result<()> {
+        if meta.path.is_ident("ordered") {
+            self.ordered = true;
+            Ok(())
+        } else if meta.path.is_ident("track_removed") {
+            self.track_removed = true;
+            Ok(())
+        } else if meta.path.is_ident("on_create") {
+            self.on_create = Some(meta.value()?.parse()?);
+            Ok(())
+        } else if meta.path.is_ident("on_destroy") {
+            self.on_destroy = Some(meta.value()?.parse()?);
+            Ok(())
+        } else {
+            Err(meta.error("unsupported entity option"))
+        }
+    }
+
+    pub fn from_entity(entity: &ItemStruct) -> syn::Result<Self> {
+        let mut args = EntityArgs::default();
+        for attr in &entity.attrs {
+            if attr.path().is_ident("entity_options") {
+                attr.parse_nested_meta(|meta| args.parse(meta))?;
+            }
+        }
+        Ok(args)
+    }
+
+    fn to_attribute(&self) -> TokenStream2 {
+        let mut options = Vec::new();
+        if self.ordered {
+            options.push(quote! { ordered });
+        }
+        if self.track_removed {
+            options.push(quote! { track_removed });
+        }
+        if let Some(on_create) = &self.on_create {
+            options.push(quote! { on_create = #on_create });
+        }
+        if let Some(on_destroy) = &self.on_destroy {
+            options.push(quote! { on_destroy = #on_destroy });
+        }
+        if options.is_empty() {
+            quote! {}
+        } else {
+            quote! { #[entity_options(#(#options),*)] }
+        }
+    }
+}
+
+/// Whether destroyed entities of this type are recorded. Keeping a field implies it.
+pub fn tracks_removed(entity: &ItemStruct) -> syn::Result<bool> {
+    Ok(EntityArgs::from_entity(entity)?.track_removed || entity.fields.iter().any(is_keep_removed))
+}
+
+/// The fields of an entity, with how they are accessed and the column they are stored in.
+/// Tuple struct fields are stored in columns named by position.
+fn entity_fields(entity: &ItemStruct) -> Vec<(Member, Ident, &Field)> {
+    entity
+        .fields
+        .iter()
+        .enumerate()
+        .map(|(index, field)| {
+            (
+                field_member(index, field),
+                format_column_name(index, field),
+                field,
+            )
+        })
+        .collect()
+}
+
+/// The component a field holds. Sparse fields are `Option<T>`, but hold a `T`.
+pub fn component_type(field: &Field) -> &Type {
+    option_inner_type(&field.ty)
+        .filter(|_| is_sparse(field))
+        .unwrap_or(&field.ty)
+}
+
+fn check_fields(fields: &[(Member, Ident, &Field)]) -> syn::Result<()> {
+    if let Some((_, _, field)) = fields
+        .iter()
+        .find(|(_, _, field)| is_sparse(field) && option_inner_type(&field.ty).is_none())
+    {
+        return Err(Error::new(
+            field.ty.span(),
+            "#[sparse] fields must be of type Option<T>",
+        ));
+    }
+
+    if let Some((_, _, field)) = fields
+        .iter()
+        .find(|(_, _, field)| is_sparse(field) && is_indexed(field))
+    {
+        return Err(Error::new(
+            field.ty.span(),
+            "#[indexed] fields can't be #[sparse]",
+        ));
+    }
+
+    if let Some((_, _, field)) = fields
+        .iter()
+        .find(|(_, _, field)| is_tracked(field) && (is_sparse(field) || is_indexed(field)))
+    {
+        return Err(Error::new(
+            field.ty.span(),
+            "#[tracked] fields can't be #[sparse] or #[indexed]",
+        ));
+    }
+
+    // queries find a field by its type, so two fields of the same type would be ambiguous
+    let mut component_fields = HashMap::new();
+    for (_, field_name, field) in fields {
+        if let Some(other_field) =
+            component_fields.insert(component_key(component_type(field)), field_name)
+        {
+            return Err(Error::new(
+                field.ty.span(),
+                format!(
+                    "`{}` has the same type as `{}`, an entity can only have one component of each type",
+                    field_name, other_field
+                ),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Checks the entity and exports its tokens. Its storage is generated by the world it is
+/// listed in, see `get_collection`.
+pub fn entity(attr: TokenStream, input: TokenStream) -> TokenStream {
+    let mut args = EntityArgs::default();
+    if let Err(err) = syn::meta::parser(|meta| args.parse(meta)).parse(attr) {
+        return err.to_compile_error().into();
+    }
     let input_struct = syn::parse_macro_input!(input as ItemStruct);
 
-    let Fields::Named(fields) = input_struct.clone().fields else {
-        return Error::new(
-            input_struct.fields.span(),
-            "unnamed fields are not supported",
-        )
-        .to_compile_error()
-        .into();
-    };
+    if let Fields::Unit = input_struct.fields {
+        return Error::new(input_struct.span(), "unit structs are not supported")
+            .to_compile_error()
+            .into();
+    }
+    if let Some(param) = input_struct.generics.lifetimes().next() {
+        return Error::new(param.span(), "entities can't have lifetime parameters")
+            .to_compile_error()
+            .into();
+    }
+    let fields = entity_fields(&input_struct);
+    if let Err(err) = check_fields(&fields) {
+        return err.to_compile_error().into();
+    }
+
+    let name = &input_struct.ident;
+    let vis = &input_struct.vis;
+    let column_types = fields.iter().map(|(_, column, field)| {
+        let alias = format_column_type_name(name, column);
+        let component_type = component_type(field);
+        let params = column_type_params(&input_struct.generics, component_type);
+        quote! {
+            #[doc(hidden)]
+            #[allow(non_camel_case_types, dead_code)]
+            #vis type #alias<#(#params),*> = #component_type;
+        }
+    });
+
+    let options = args.to_attribute();
+    quote! {
+        #[export_tokens]
+        #[derive(EntityAttributes)]
+        #options
+        #input_struct
+
+        #(#column_types)*
+    }
+    .into()
+}
+
+/// Generates the storage of one entity type in a world, `__{name}Collection`, and the
+/// `__{name}Ref` that borrows one entity's components. Generic entities are passed with
+/// their parameters replaced by the arguments given in `ecs_world!`, and `name` is the
+/// entity type as the world names it.
+pub fn get_collection(entity: &ItemStruct, name: &Ident) -> syn::Result<TokenStream2> {
+    let args = EntityArgs::from_entity(entity)?;
+    let fields = entity_fields(entity);
+    check_fields(&fields)?;
 
     let collection_fields: Vec<_> = fields
-        .named
         .iter()
-        .map(|field| {
-            let field_name = &field.ident;
+        .map(|(_, field_name, field)| {
             let field_type = &field.ty;
 
-            quote! {
-                #field_name: Vec<#field_type>
+            if let (true, Some(inner_type)) = (is_sparse(field), option_inner_type(field_type)) {
+                quote! {
+                    #field_name: SparseColumn<#inner_type>
+                }
+            } else {
+                quote! {
+                    #field_name: Vec<#field_type>
+                }
             }
         })
         .collect();
 
-    let ident = &input_struct.ident;
-    let vis = &input_struct.vis;
-    let collection_name = format_collection_name(ident);
+    let indexed_fields: Vec<_> = fields
+        .iter()
+        .filter(|(_, _, field)| is_indexed(field))
+        .map(|(member, field_name, field)| {
+            (member, field_name, format_index_name(field_name), &field.ty)
+        })
+        .collect();
+    let index_fields = indexed_fields.iter().map(|(_, _, index_name, field_type)| {
+        quote! {
+            #index_name: ColumnIndex<#field_type, Entity>
+        }
+    });
 
-    let create_push_calls: Vec<_> = fields
-        .named
+    // the ticks a tracked column was added and last changed at, one per entity
+    let tick_names: Vec<_> = fields
         .iter()
-        .map(|field| {
-            let field_name = &field.ident;
+        .filter(|(_, _, field)| is_tracked(field))
+        .flat_map(|(_, field_name, _)| {
+            let (added_name, changed_name) = format_tick_names(field_name);
+            [added_name, changed_name]
+        })
+        .collect();
+
+    let collection_name = format_collection_name(name);
+    let ref_name = format_ref_name(name);
+
+    // destroyed entities are recorded in `removed`, with the components they keep. Taken and
+    // migrated entities are recorded without them, those go to the caller or the new entity
+    let track_removed = tracks_removed(entity)?;
+    let removed_name = format_removed_name(name);
+    let kept_fields: Vec<_> = fields
+        .iter()
+        .filter(|(_, _, field)| is_keep_removed(field))
+        .collect();
+    let kept_names: Vec<_> = kept_fields
+        .iter()
+        .map(|(_, field_name, _)| field_name)
+        .collect();
+    // sparse fields are already optional
+    let kept_types = kept_fields.iter().map(|(_, _, field)| {
+        let ty = &field.ty;
+        if is_sparse(field) {
+            quote! { #ty }
+        } else {
+            quote! { Option<#ty> }
+        }
+    });
+    let kept_values = kept_fields.iter().map(|(member, _, field)| {
+        if is_sparse(field) {
+            quote! { __removed.#member }
+        } else {
+            quote! { Some(__removed.#member) }
+        }
+    });
+    let removed_struct = track_removed.then(|| {
+        quote! {
+            /// A destroyed, taken or migrated entity, see `WorldRemoved`. The kept components
+            /// are only set if it was destroyed.
+            #[allow(non_camel_case_types, private_interfaces)]
+            pub struct #removed_name {
+                pub entity: Entity,
+                /// The new handle of a migrated entity.
+                pub migrated_to: Option<Entity>,
+                #( pub #kept_names: #kept_types, )*
+            }
+        }
+    });
+    let removed_field = track_removed.then(|| quote! { pub removed: Vec<#removed_name>, });
+    let removed_default = track_removed.then(|| quote! { removed: Vec::new(), });
+    // hooks are called with the components, before they are stored and after they are taken
+    let on_create_call = args.on_create.as_ref().map(|on_create| {
+        quote! {
+            #on_create(TypedEntity::new(self.next_entity()), &e);
+        }
+    });
+    let on_destroy_call = args.on_destroy.as_ref().map(|on_destroy| {
+        quote! { #on_destroy(TypedEntity::new(e), &__removed); }
+    });
+    let record_removed = track_removed.then(|| {
+        quote! {
+            self.removed.push(#removed_name {
+                entity: e,
+                migrated_to: None,
+                #( #kept_names: #kept_values, )*
+            });
+        }
+    });
+    let record_taken = track_removed.then(|| {
+        quote! {
+            self.removed.push(#removed_name {
+                entity: e,
+                migrated_to,
+                #( #kept_names: None, )*
+            });
+        }
+    });
+    // only recorded if removals are tracked
+    let migrated_to = if track_removed {
+        quote! { migrated_to }
+    } else {
+        quote! { _migrated_to }
+    };
+    let destroy_body = if track_removed || on_destroy_call.is_some() {
+        quote! {
+            if let Some(__removed) = self.take_components(e) {
+                #on_destroy_call
+                #record_removed
+            }
+        }
+    } else {
+        quote! {
+            let _ = self.take_components(e);
+        }
+    };
+    let clear_removed = (track_removed || on_destroy_call.is_some()).then(|| {
+        quote! {
+            // destroyed one at a time, from the back, so they are recorded and hooks are called
+            while let Some(&entity) = self.entity.last() {
+                WorldDestroy::destroy(self, entity);
+            }
+        }
+    });
+    let clear_removed_trackers = track_removed.then(|| quote! { self.removed.clear(); });
+
+    let default_values = fields
+        .iter()
+        .map(|(_, field_name, _)| field_name)
+        .chain(
+            indexed_fields
+                .iter()
+                .map(|(_, _, index_name, _)| index_name),
+        )
+        .chain(&tick_names)
+        .map(|field_name| {
+            quote! {
+                #field_name: Default::default()
+            }
+        });
+    let create_index_calls: Vec<_> = indexed_fields
+        .iter()
+        .map(|(member, _, index_name, _)| {
             quote! {
-                self.#field_name.push(e.#field_name);
+                self.#index_name.insert(&e.#member, entity);
             }
         })
         .collect();
-    let destroy_swap_and_pops = fields.named.iter().map(|field| {
-        let field_name = &field.ident;
+    let take_index_calls = indexed_fields.iter().map(|(_, field_name, index_name, _)| {
         quote! {
-            self.#field_name.swap(old_index, last_index);
-            self.#field_name.pop();
+            self.#index_name.remove(&self.#field_name[old_index], e);
         }
     });
 
-    quote! {
-        #[export_tokens]
-        #input_struct
+    let create_push_calls: Vec<_> = fields
+        .iter()
+        .map(|(member, field_name, field)| {
+            if is_sparse(field) {
+                quote! {
+                    if let Some(value) = e.#member {
+                        self.#field_name.insert(entity.id, value);
+                    }
+                }
+            } else {
+                quote! {
+                    self.#field_name.push(e.#member);
+                }
+            }
+        })
+        .collect();
 
-        #[export_tokens]
-        #[derive(Default)]
-        #vis struct #collection_name {
+    // without freed ids to reuse, the new ids follow each other with generation 0, so the
+    // columns are extended in one pass and the bookkeeping after it
+    let create_one_by_one = quote! {
+        entities
+            .map(|e| WorldCreate::<#name>::create(self, e))
+            .collect()
+    };
+    let create_batch_body = if on_create_call.is_some() {
+        create_one_by_one
+    } else {
+        quote! {
+            if !self.free_ids.is_empty() {
+                return #create_one_by_one;
+            }
+            let first_id = self.index_lookup.len();
+            let first_index = self.entity.len();
+            let mut created = Vec::with_capacity(additional);
+            for e in entities {
+                let entity = Entity {
+                    entity_type: <#name as EntityKind>::ENTITY_TYPE,
+                    id: first_id + created.len(),
+                    generation: 0,
+                };
+                self.entity.push(entity);
+                #(#create_index_calls)*
+                #(#create_push_calls)*
+                created.push(TypedEntity::new(entity));
+            }
+            self.index_lookup.extend((first_index..self.entity.len()).map(Some));
+            self.generations.resize(self.index_lookup.len(), 0);
+            #(self.#tick_names.resize(self.entity.len(), self.change_tick);)*
+            created
+        }
+    };
+    let reserve_calls = fields
+        .iter()
+        .filter(|(_, _, field)| !is_sparse(field))
+        .map(|(_, field_name, _)| field_name)
+        .chain(&tick_names)
+        .map(|field_name| {
+            quote! {
+                self.#field_name.reserve(additional);
+            }
+        });
+    let clear_calls = fields
+        .iter()
+        .map(|(_, field_name, _)| field_name)
+        .chain(
+            indexed_fields
+                .iter()
+                .map(|(_, _, index_name, _)| index_name),
+        )
+        .chain(&tick_names)
+        .map(|field_name| {
+            quote! {
+                self.#field_name.clear();
+            }
+        });
+    let ref_fields = fields.iter().map(|(_, field_name, field)| {
+        let field_type = &field.ty;
+        if let (true, Some(inner_type)) = (is_sparse(field), option_inner_type(field_type)) {
+            quote! {
+                pub #field_name: Option<&'a #inner_type>
+            }
+        } else {
+            quote! {
+                pub #field_name: &'a #field_type
+            }
+        }
+    });
+    let ref_values = fields.iter().map(|(_, field_name, field)| {
+        if is_sparse(field) {
+            quote! {
+                #field_name: self.#field_name.get(entity.id)
+            }
+        } else {
+            quote! {
+                #field_name: &self.#field_name[index]
+            }
+        }
+    });
+    let permute_calls = fields
+        .iter()
+        .filter(|(_, _, field)| !is_sparse(field))
+        .map(|(_, field_name, _)| field_name)
+        .chain(&tick_names)
+        .map(|field_name| {
+            quote! {
+                permutation.apply(&mut self.#field_name);
+            }
+        });
+    let take_removes = fields.iter().map(|(member, field_name, field)| {
+        if is_sparse(field) {
+            quote! {
+                #member: self.#field_name.remove(e.id)
+            }
+        } else if args.ordered {
+            quote! {
+                #member: self.#field_name.remove(old_index)
+            }
+        } else {
+            quote! {
+                #member: self.#field_name.swap_remove(old_index)
+            }
+        }
+    });
+    let take_tick_removes = tick_names.iter().map(|tick_name| {
+        if args.ordered {
+            quote! { self.#tick_name.remove(old_index); }
+        } else {
+            quote! { self.#tick_name.swap_remove(old_index); }
+        }
+    });
+    let take_entity_remove = if args.ordered {
+        quote! {
+            self.entity.remove(old_index);
+            for (index, moved_entity) in self.entity.iter().enumerate().skip(old_index) {
+                self.index_lookup[moved_entity.id] = Some(index);
+            }
+        }
+    } else {
+        quote! {
+            self.entity.swap_remove(old_index);
+            if let Some(moved_entity) = self.entity.get(old_index) {
+                self.index_lookup[moved_entity.id] = Some(old_index);
+            }
+        }
+    };
+
+    Ok(quote! {
+        #[allow(non_camel_case_types, private_interfaces)]
+        pub struct #collection_name {
            #( pub #collection_fields, )*
+           #( pub #index_fields, )*
+           #( pub #tick_names: Vec<u32>, )*
            pub entity: Vec<Entity>,
-           pub next_id: usize,
            pub index_lookup: Vec<Option<usize>>,
+           pub generations: Vec<u32>,
+           pub free_ids: Vec<usize>,
+           /// Ticks of tracked columns equal to this were set since `clear_trackers`.
+           pub change_tick: u32,
+           #removed_field
         }
 
-        impl WorldCreate<#ident> for #collection_name {
-            fn create(&mut self, e: #ident) -> Entity {
-                self.index_lookup.push(Some(self.entity.len()));
-                let entity = Entity {
-                    entity_type: EntityType::#ident,
-                    id: self.next_id,
-                };
+        #removed_struct
+
+        // not derived, the columns don't need their components to implement Default
+        impl Default for #collection_name {
+            fn default() -> Self {
+                Self {
+                    #( #default_values, )*
+                    entity: Vec::new(),
+                    index_lookup: Vec::new(),
+                    generations: Vec::new(),
+                    free_ids: Vec::new(),
+                    change_tick: 0,
+                    #removed_default
+                }
+            }
+        }
+
+        /// Borrows the components of one entity, see `sort_by_key`.
+        #[allow(non_camel_case_types, private_interfaces)]
+        pub struct #ref_name<'a> {
+            pub entity: &'a Entity,
+            #( #ref_fields, )*
+        }
+
+        impl WorldCreate<#name> for #collection_name {
+            fn create(&mut self, e: #name) -> TypedEntity<#name> {
+                #on_create_call
+                let entity = self.next_entity();
+                if self.free_ids.pop().is_some() {
+                    self.index_lookup[entity.id] = Some(self.entity.len());
+                } else {
+                    self.index_lookup.push(Some(self.entity.len()));
+                    self.generations.push(0);
+                }
                 self.entity.push(entity);
 
+                #(#create_index_calls)*
                 #(#create_push_calls)*
+                #(self.#tick_names.push(self.change_tick);)*
 
-                self.next_id += 1;
-                entity
+                TypedEntity::new(entity)
+            }
+
+            fn create_batch(
+                &mut self,
+                entities: impl IntoIterator<Item = #name>,
+            ) -> Vec<TypedEntity<#name>> {
+                let entities = entities.into_iter();
+                let (lower, upper) = entities.size_hint();
+                let additional = upper.unwrap_or(lower);
+                WorldCreate::<#name>::reserve(self, additional);
+                #create_batch_body
+            }
+
+            fn reserve(&mut self, additional: usize) {
+                self.entity.reserve(additional);
+                #(#reserve_calls)*
+                // freed ids are reused before new slots are pushed
+                let new_ids = additional.saturating_sub(self.free_ids.len());
+                self.index_lookup.reserve(new_ids);
+                self.generations.reserve(new_ids);
             }
         }
 
+        impl #collection_name {
+            /// The handle the next stored entity gets.
+            pub fn next_entity(&self) -> Entity {
+                // reuse a freed id if there is one, its generation was bumped on destroy
+                let id = self.free_ids.last().copied().unwrap_or(self.index_lookup.len());
+                Entity {
+                    entity_type: <#name as EntityKind>::ENTITY_TYPE,
+                    id,
+                    generation: self.generations.get(id).copied().unwrap_or(0),
+                }
+            }
+        }
 
-        impl WorldDestroy for #collection_name {
-            fn destroy(&mut self, e: Entity) {
-                if let Some(&Some(old_index)) = self.index_lookup.get(e.id) {
-                    self.index_lookup[e.id] = None;
-                    let last_index = self.entity.len() - 1;
-                    let last_entity = self.entity[last_index];
-                    let is_now_last = old_index == last_index;
+        impl WorldTake<#name> for #collection_name {
+            fn take(&mut self, e: TypedEntity<#name>) -> Option<#name> {
+                self.take_entity(e.entity(), None)
+            }
+        }
 
-                    #(#destroy_swap_and_pops)*
+        impl #collection_name {
+            /// Removes the entity for `take`, or for `migrate` with its new handle. Calls
+            /// `on_destroy` and records it as removed.
+            pub fn take_entity(&mut self, e: Entity, #migrated_to: Option<Entity>) -> Option<#name> {
+                let __removed = self.take_components(e)?;
+                #on_destroy_call
+                #record_taken
+                Some(__removed)
+            }
 
-                    self.entity.swap(old_index, last_index);
-                    self.entity.pop();
+            /// Removes the entity without calling `on_destroy`.
+            fn take_components(&mut self, e: Entity) -> Option<#name> {
+                let old_index = self.index_of(e)?;
+                self.index_lookup[e.id] = None;
+                self.generations[e.id] = self.generations[e.id].wrapping_add(1);
+                self.free_ids.push(e.id);
+                #(#take_index_calls)*
+                #(#take_tick_removes)*
 
-                    if !is_now_last {
-                        self.index_lookup[last_entity.id] = Some(old_index);
-                    }
-                }
+                #take_entity_remove
+
+                Some(#name {
+                    #(#take_removes,)*
+                })
+            }
+        }
+
+        impl WorldDestroy for #collection_name {
+            fn destroy(&mut self, e: Entity) {
+                #destroy_body
             }
         }
 
@@ -110,50 +670,117 @@ pub fn entity(_: TokenStream, input: TokenStream) -> TokenStream {
                 self.entity.len()
             }
 
-            pub fn query_mut<'a, T: 'a>(&'a mut self) -> impl Iterator<Item = T> + 'a
+            pub fn is_empty(&self) -> bool {
+                self.entity.is_empty()
+            }
+
+            pub fn is_alive(&self, entity: impl Into<Entity>) -> bool {
+                self.index_of(entity).is_some()
+            }
+
+            /// Destroys all entities. Their ids are freed and their handles become stale, the
+            /// same as with `destroy`.
+            pub fn clear(&mut self) {
+                #clear_removed
+                for entity in self.entity.drain(..) {
+                    self.index_lookup[entity.id] = None;
+                    self.generations[entity.id] = self.generations[entity.id].wrapping_add(1);
+                    self.free_ids.push(entity.id);
+                }
+                #(#clear_calls)*
+            }
+
+            /// Starts a new frame for `Added` and `Changed` filters, what was added and
+            /// changed before no longer counts. Forgets the destroyed entities.
+            pub fn clear_trackers(&mut self) {
+                self.change_tick = self.change_tick.wrapping_add(1);
+                #clear_removed_trackers
+            }
+
+            fn row(&self, index: usize) -> #ref_name<'_> {
+                let entity = &self.entity[index];
+                #ref_name {
+                    entity,
+                    #( #ref_values, )*
+                }
+            }
+
+            /// Reorders the entities by key. The sort is stable, and all handles stay valid.
+            pub fn sort_by_key<__K: Ord>(
+                &mut self,
+                mut f: impl FnMut(#ref_name<'_>) -> __K,
+            ) {
+                let mut keys: Vec<(__K, usize)> = (0..self.entity.len())
+                    .map(|index| (f(self.row(index)), index))
+                    .collect();
+                keys.sort_by(|(a, _), (b, _)| a.cmp(b));
+                let order: Vec<usize> = keys.into_iter().map(|(_, index)| index).collect();
+
+                let permutation = Permutation::from_order(&order);
+                if permutation.is_identity() {
+                    return;
+                }
+                permutation.apply(&mut self.entity);
+                #(#permute_calls)*
+                for (index, entity) in self.entity.iter().enumerate() {
+                    self.index_lookup[entity.id] = Some(index);
+                }
+            }
+
+            /// Returns the index of the entity in the columns, or None if the handle is stale.
+            pub fn index_of(&self, entity: impl Into<Entity>) -> Option<usize> {
+                let entity = entity.into();
+                match self.index_lookup.get(entity.id) {
+                    Some(&Some(index)) if self.generations[entity.id] == entity.generation => {
+                        Some(index)
+                    }
+                    _ => None,
+                }
+            }
+
+            pub fn query_mut<'a, __Q: 'a>(&'a mut self) -> impl Iterator<Item = __Q> + 'a
             where
-                #collection_name: QueryMutFrom<'a, T>,
-                T: 'a + Send,
+                Self: QueryMutFrom<'a, __Q>,
+                __Q: 'a + Send,
             {
-                QueryMutFrom::<T>::query_mut_from(self)
+                QueryMutFrom::<__Q>::query_mut_from(self)
             }
-            fn par_query_mut<'a, T: 'a>(&'a mut self) -> impl ParallelIterator<Item = T> + 'a
+            fn par_query_mut<'a, __Q: 'a>(&'a mut self) -> impl ParallelIterator<Item = __Q> + 'a
             where
-                #collection_name: QueryMutFrom<'a, T>,
-                T: 'a + Send,
+                Self: QueryMutFrom<'a, __Q>,
+                __Q: 'a + Send,
             {
-                QueryMutFrom::<T>::par_query_mut_from(self)
+                QueryMutFrom::<__Q>::par_query_mut_from(self)
             }
-            pub fn get_mut<'a, T: 'a>(&'a mut self, entity: Entity) -> Option<T>
+            pub fn get_mut<'a, __Q: 'a>(&'a mut self, entity: Entity) -> Option<__Q>
             where
-                #collection_name: QueryMutFrom<'a, T>,
-                T: 'a + Send,
+                Self: QueryMutFrom<'a, __Q>,
+                __Q: 'a + Send,
             {
-                QueryMutFrom::<T>::get_mut_from(self, entity)
+                QueryMutFrom::<__Q>::get_mut_from(self, entity)
             }
 
-            pub fn query<'a, T: 'a>(&'a self) -> impl Iterator<Item = T> + 'a
+            pub fn query<'a, __Q: 'a>(&'a self) -> impl Iterator<Item = __Q> + 'a
             where
-                #collection_name: QueryFrom<'a, T>,
-                T: 'a + Send,
+                Self: QueryFrom<'a, __Q>,
+                __Q: 'a + Send,
             {
-                QueryFrom::<T>::query_from(self)
+                QueryFrom::<__Q>::query_from(self)
             }
-            pub fn par_query<'a, T: 'a>(&'a self) -> impl ParallelIterator<Item = T> + 'a
+            pub fn par_query<'a, __Q: 'a>(&'a self) -> impl ParallelIterator<Item = __Q> + 'a
             where
-                #collection_name: QueryFrom<'a, T>,
-                T: 'a + Send,
+                Self: QueryFrom<'a, __Q>,
+                __Q: 'a + Send,
             {
-                QueryFrom::<T>::par_query_from(self)
+                QueryFrom::<__Q>::par_query_from(self)
             }
-            pub fn get<'a, T: 'a>(&'a self, entity: Entity) -> Option<T>
+            pub fn get<'a, __Q: 'a>(&'a self, entity: Entity) -> Option<__Q>
             where
-                #collection_name: QueryFrom<'a, T>,
-                T: 'a + Send,
+                Self: QueryFrom<'a, __Q>,
+                __Q: 'a + Send,
             {
-                QueryFrom::<T>::get_from(self, entity)
+                QueryFrom::<__Q>::get_from(self, entity)
             }
         }
-    }
-    .into()
+    })
 }
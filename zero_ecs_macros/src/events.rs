@@ -0,0 +1,148 @@
+use convert_case::{Case, Casing};
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{Fields, GenericArgument, Ident, ItemStruct, PathArguments, Type};
+
+use crate::resources::type_string;
+
+/// World fields that hold events are named `__twe__{type}`, of type `Events<T>`. The world's
+/// entities are tagged with a `PhantomData<T>` of the same name.
+const EVENTS_FIELD_PREFIX: &str = "__twe__";
+
+pub struct WorldEvent {
+    pub field_name: Ident,
+    /// The event type, `T` in `Events<T>`.
+    pub ty: Type,
+}
+
+pub fn format_events_field_name(ty: &Type) -> Ident {
+    let name: String = type_string(ty)
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '_' })
+        .collect();
+    let name = name.to_case(Case::Snake);
+    format_ident!("{}{}", EVENTS_FIELD_PREFIX, name.trim_matches('_'))
+}
+
+/// The type argument of a path type, `T` in `EventReader<T>`.
+pub fn first_type_argument(ty: &Type) -> Option<&Type> {
+    let Type::Path(type_path) = ty else {
+        return None;
+    };
+    let PathArguments::AngleBracketed(args) = &type_path.path.segments.last()?.arguments else {
+        return None;
+    };
+    args.args.iter().find_map(|arg| match arg {
+        GenericArgument::Type(ty) => Some(ty),
+        _ => None,
+    })
+}
+
+/// Finds the event types in the tags of the world's entities, passed to `#[system]` and
+/// `#[system_for_each]`.
+pub fn get_world_events(world_struct: &ItemStruct) -> Vec<WorldEvent> {
+    let Fields::Named(fields) = &world_struct.fields else {
+        return vec![];
+    };
+    fields
+        .named
+        .iter()
+        .filter_map(|field| {
+            let field_name = field.ident.as_ref()?;
+            if !field_name.to_string().starts_with(EVENTS_FIELD_PREFIX) {
+                return None;
+            }
+            Some(WorldEvent {
+                field_name: field_name.clone(),
+                ty: first_type_argument(&field.ty)?.clone(),
+            })
+        })
+        .collect()
+}
+
+pub fn find_event<'a>(events: &'a [WorldEvent], ty: &Type) -> Option<&'a WorldEvent> {
+    let ty = type_string(ty);
+    events.iter().find(|event| type_string(&event.ty) == ty)
+}
+
+/// An `EventReader<T>` or `EventWriter<T>` argument of a system, with the world field that
+/// holds the events.
+pub enum EventArg<'a> {
+    Reader(&'a WorldEvent),
+    Writer(&'a WorldEvent),
+}
+
+/// Recognizes `EventReader<T>` and `EventWriter<T>`, panics if `T` isn't an event of the world.
+pub fn find_event_arg<'a>(events: &'a [WorldEvent], ty: &Type) -> Option<EventArg<'a>> {
+    let Type::Path(type_path) = ty else {
+        return None;
+    };
+    let ident = &type_path.path.segments.last()?.ident;
+    if ident != "EventReader" && ident != "EventWriter" {
+        return None;
+    }
+    let event_type = first_type_argument(ty)
+        .unwrap_or_else(|| panic!("expected the event type in {}", type_string(ty)));
+    let event = find_event(events, event_type).unwrap_or_else(|| {
+        panic!(
+            "`{}` is not an event of the world, add it to `events:` in `ecs_world!`",
+            type_string(event_type)
+        )
+    });
+    Some(if ident == "EventReader" {
+        EventArg::Reader(event)
+    } else {
+        EventArg::Writer(event)
+    })
+}
+
+/// Generates `WorldEvents` and the world's methods to send, read and update events.
+pub fn get_events(world: &Ident, events: &[Type]) -> TokenStream {
+    let field_names: Vec<_> = events.iter().map(format_events_field_name).collect();
+    let event_implementations = events.iter().zip(&field_names).map(|(ty, field_name)| {
+        quote! {
+            impl WorldEvents<#ty> for #world {
+                fn events(&self) -> &Events<#ty> {
+                    &self.#field_name
+                }
+                fn events_mut(&mut self) -> &mut Events<#ty> {
+                    &mut self.#field_name
+                }
+            }
+        }
+    });
+
+    quote! {
+        pub trait WorldEvents<T> {
+            fn events(&self) -> &Events<T>;
+            fn events_mut(&mut self) -> &mut Events<T>;
+        }
+
+        #(#event_implementations)*
+
+        #[allow(dead_code)]
+        impl #world {
+            /// Queues the event, systems can read it after the next `update_events`.
+            pub fn send_event<T>(&mut self, event: T)
+            where
+                #world: WorldEvents<T>,
+            {
+                WorldEvents::<T>::events_mut(self).send(event)
+            }
+
+            /// The events sent before the last `update_events`.
+            pub fn read_events<T>(&self) -> EventReader<'_, T>
+            where
+                #world: WorldEvents<T>,
+            {
+                EventReader::new(WorldEvents::<T>::events(self).read())
+            }
+
+            /// Swaps the event buffers of every event type, usually once per frame. The events
+            /// sent since the last call become readable, the ones read before are dropped.
+            pub fn update_events(&mut self) {
+                #(self.#field_names.update();)*
+            }
+        }
+    }
+}
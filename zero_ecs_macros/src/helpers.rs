@@ -1,13 +1,343 @@
 use convert_case::{Case, Casing};
-use quote::format_ident;
-use syn::Ident;
+use proc_macro2::{Delimiter, TokenStream, TokenTree};
+use quote::{format_ident, quote, ToTokens};
+use syn::{
+    spanned::Spanned, Field, GenericArgument, Generics, Ident, Index, Member, Path, PathArguments,
+    Type, TypePath,
+};
 
 pub fn format_collection_name(ident: &impl ToString) -> Ident {
     format_ident!("__{}Collection", ident.to_string())
 }
 
+pub fn format_ref_name(ident: &impl ToString) -> Ident {
+    format_ident!("__{}Ref", ident.to_string())
+}
+
+pub fn format_removed_name(ident: &impl ToString) -> Ident {
+    format_ident!("__{}Removed", ident.to_string())
+}
+
 pub fn format_field_name(ident: &impl ToString) -> Ident {
     let s = ident.to_string();
     let s = s.to_case(Case::Snake);
     format_ident!("__{}", s)
 }
+
+/// The module a named world keeps its generated types in, `SimWorld` has them in `sim_world`.
+/// The default `World` has them next to it.
+pub fn world_module_name(world: &Ident) -> Option<Ident> {
+    if world == "World" {
+        return None;
+    }
+    let name = world.to_string().to_case(Case::Snake);
+    Some(format_ident!("{}", name, span = world.span()))
+}
+
+/// The path to the types generated for a world, as a prefix, for code generated where the world
+/// is referred to by `world_path`. `game::SimWorld` has them in `game::sim_world::`.
+pub fn world_items_path(world_path: &Path) -> TokenStream {
+    let mut path = world_path.clone();
+    let world = path.segments.pop().map(|pair| pair.into_value().ident);
+    if let Some(module) = world.as_ref().and_then(world_module_name) {
+        path.segments.push(module.into());
+    }
+    path_prefix(&path)
+}
+
+/// The part of the world without its resources and events, `World` derefs to `WorldEntities`.
+/// Everything about entities is implemented on it, and the world's tokens are its tokens.
+pub fn format_world_entities_name(world: &Ident) -> Ident {
+    format_ident!("{}Entities", world)
+}
+
+/// The module of the item `path` refers to, as a prefix. `entities::Unit` gives `entities::`.
+pub fn parent_path(path: &Path) -> TokenStream {
+    let mut path = path.clone();
+    path.segments.pop();
+    path_prefix(&path)
+}
+
+fn path_prefix(path: &Path) -> TokenStream {
+    if path.segments.is_empty() {
+        quote! {}
+    } else {
+        let segments = path.segments.pairs().map(|pair| pair.into_value());
+        let leading_colon = path.leading_colon;
+        quote! { #leading_colon #(#segments::)* }
+    }
+}
+
+/// The hidden alias `#[entity]` defines for the component type of a field. The world names
+/// component types through these, so they don't have to be in scope where it is defined.
+pub fn format_column_type_name(entity: &Ident, column: &Ident) -> Ident {
+    format_ident!("__{}__{}", entity, column)
+}
+
+/// The alias a world defines for the component type of a column of one of its entity types.
+/// Its tag fields name component types through these, their tokens are exported.
+pub fn format_world_column_type_name(entity_name: &Ident, column: &Ident) -> Ident {
+    format_ident!("__{}__{}__Type", entity_name, column)
+}
+
+/// The type parameters of the entity that its field type uses, the parameters of the field's
+/// alias. An alias can't have parameters it doesn't use.
+pub fn column_type_params(generics: &Generics, ty: &Type) -> Vec<Ident> {
+    fn collect_idents(tokens: TokenStream, idents: &mut Vec<Ident>) {
+        for token in tokens {
+            match token {
+                TokenTree::Ident(ident) => idents.push(ident),
+                TokenTree::Group(group) => collect_idents(group.stream(), idents),
+                _ => {}
+            }
+        }
+    }
+    let mut idents = vec![];
+    collect_idents(ty.to_token_stream(), &mut idents);
+    generics
+        .type_params()
+        .map(|param| param.ident.clone())
+        .filter(|param| idents.contains(param))
+        .collect()
+}
+
+/// The name of the column an entity field is stored in. Fields of tuple structs are
+/// stored as `field_0`, `field_1` and so on.
+pub fn format_column_name(index: usize, field: &Field) -> Ident {
+    match &field.ident {
+        Some(ident) => ident.clone(),
+        None => format_ident!("field_{}", index, span = field.ty.span()),
+    }
+}
+
+/// How an entity field is accessed, by name or by position.
+pub fn field_member(index: usize, field: &Field) -> Member {
+    match &field.ident {
+        Some(ident) => Member::Named(ident.clone()),
+        None => Member::Unnamed(Index::from(index)),
+    }
+}
+
+/// `#[sparse]` fields are stored in a `SparseColumn` instead of a `Vec`.
+pub fn is_sparse(field: &Field) -> bool {
+    field
+        .attrs
+        .iter()
+        .any(|attr| attr.path().is_ident("sparse"))
+}
+
+/// `#[indexed]` fields have a `ColumnIndex` next to their column, to find entities by value.
+pub fn is_indexed(field: &Field) -> bool {
+    field
+        .attrs
+        .iter()
+        .any(|attr| attr.path().is_ident("indexed"))
+}
+
+/// `#[tracked]` fields have the ticks they were added and last changed at next to their column.
+pub fn is_tracked(field: &Field) -> bool {
+    field
+        .attrs
+        .iter()
+        .any(|attr| attr.path().is_ident("tracked"))
+}
+
+/// `#[keep_removed]` fields are moved into the record of a destroyed entity instead of dropped.
+pub fn is_keep_removed(field: &Field) -> bool {
+    field
+        .attrs
+        .iter()
+        .any(|attr| attr.path().is_ident("keep_removed"))
+}
+
+/// The name of the index of an `#[indexed]` column in a collection.
+pub fn format_index_name(column: &impl ToString) -> Ident {
+    format_ident!("{}__index", column.to_string())
+}
+
+/// The names of the ticks an `#[tracked]` column was added and last changed at.
+pub fn format_tick_names(column: &impl ToString) -> (Ident, Ident) {
+    let column = column.to_string();
+    (
+        format_ident!("{}__added", column),
+        format_ident!("{}__changed", column),
+    )
+}
+
+/// Returns `T` if the type is `Option<T>`.
+pub fn option_inner_type(ty: &Type) -> Option<&Type> {
+    let Type::Path(type_path) = ty else {
+        return None;
+    };
+    let segment = type_path.path.segments.last()?;
+    if segment.ident != "Option" {
+        return None;
+    }
+    let PathArguments::AngleBracketed(args) = &segment.arguments else {
+        return None;
+    };
+    match args.args.first()? {
+        GenericArgument::Type(ty) => Some(ty),
+        _ => None,
+    }
+}
+
+/// Returns the type path of an entity type listed in `ecs_world!`.
+pub fn entity_type_path(ty: &Type) -> syn::Result<&TypePath> {
+    match ty {
+        Type::Path(type_path) if type_path.qself.is_none() => Ok(type_path),
+        _ => Err(syn::Error::new_spanned(ty, "expected an entity type")),
+    }
+}
+
+/// Whether the entity type is written as just its name, like `EnemyEntity`.
+pub fn is_plain_entity_type(ty: &TypePath) -> bool {
+    ty.path.get_ident().is_some()
+}
+
+/// The name used for an entity type listed in `ecs_world!`, for its `EntityType` variant
+/// and the generated aliases. `Projectile<Arrow>` becomes `ProjectileArrow`.
+pub fn format_entity_name(ty: &TypePath) -> Ident {
+    let last_segment = ty.path.segments.last().expect("entity type path");
+    if last_segment.arguments.is_none() {
+        return last_segment.ident.clone();
+    }
+
+    fn collect_idents(tokens: TokenStream, name: &mut String) {
+        for token in tokens {
+            match token {
+                TokenTree::Ident(ident) => {
+                    let ident = ident.to_string();
+                    let mut chars = ident.chars();
+                    if let Some(first) = chars.next() {
+                        name.extend(first.to_uppercase());
+                        name.extend(chars);
+                    }
+                }
+                TokenTree::Group(group) => collect_idents(group.stream(), name),
+                _ => {}
+            }
+        }
+    }
+    let mut name = String::new();
+    collect_idents(last_segment.to_token_stream(), &mut name);
+    format_ident!("{}", name, span = last_segment.ident.span())
+}
+
+/// Replaces every identifier in `tokens` that is a key of `substitutions` with its value.
+/// Used to instantiate the fields of generic entities.
+pub fn substitute_idents(
+    tokens: TokenStream,
+    substitutions: &[(Ident, TokenStream)],
+) -> TokenStream {
+    tokens
+        .into_iter()
+        .flat_map(|token| -> TokenStream {
+            match token {
+                TokenTree::Ident(ident) => {
+                    match substitutions.iter().find(|(from, _)| *from == ident) {
+                        Some((_, to)) => to.clone(),
+                        None => TokenTree::Ident(ident).into(),
+                    }
+                }
+                TokenTree::Group(group) => {
+                    let mut new_group = proc_macro2::Group::new(
+                        group.delimiter(),
+                        substitute_idents(group.stream(), substitutions),
+                    );
+                    new_group.set_span(group.span());
+                    TokenTree::Group(new_group).into()
+                }
+                token => token.into(),
+            }
+        })
+        .collect()
+}
+
+/// The key components are looked up by in queries and systems. Paths are reduced to their last
+/// segment, so `physics::Velocity` matches `Velocity`, while generic arguments are kept,
+/// so `Vec<Waypoint>` doesn't match `Vec<Target>`. Different types with the same key are told
+/// apart by the types the world's column aliases resolve to, see `is_ambiguous`.
+pub fn component_key(ty: &impl ToTokens) -> String {
+    fn write_key(tokens: TokenStream, key: &mut String) {
+        let mut path_start = 0;
+        let mut after_ident = false;
+        let mut after_path_separator = false;
+        let mut tokens = tokens.into_iter().peekable();
+        while let Some(token) = tokens.next() {
+            match token {
+                TokenTree::Ident(ident) => {
+                    if !after_path_separator {
+                        if after_ident {
+                            key.push(' ');
+                        }
+                        path_start = key.len();
+                    }
+                    key.push_str(&ident.to_string());
+                    after_ident = true;
+                    after_path_separator = false;
+                }
+                TokenTree::Punct(punct)
+                    if punct.as_char() == ':'
+                        && matches!(tokens.peek(), Some(TokenTree::Punct(next)) if next.as_char() == ':') =>
+                {
+                    // drop the qualifier written so far
+                    tokens.next();
+                    key.truncate(path_start);
+                    after_ident = false;
+                    after_path_separator = true;
+                }
+                TokenTree::Group(group) => {
+                    let (open, close) = match group.delimiter() {
+                        Delimiter::Parenthesis => ("(", ")"),
+                        Delimiter::Bracket => ("[", "]"),
+                        Delimiter::Brace => ("{", "}"),
+                        Delimiter::None => ("", ""),
+                    };
+                    key.push_str(open);
+                    write_key(group.stream(), key);
+                    key.push_str(close);
+                    after_ident = false;
+                    after_path_separator = false;
+                }
+                token => {
+                    key.push_str(&token.to_string());
+                    after_ident = false;
+                    after_path_separator = false;
+                }
+            }
+        }
+    }
+
+    let mut key = String::new();
+    write_key(ty.to_token_stream(), &mut key);
+    key
+}
+
+/// Encodes a type so it can be part of an identifier, see `decode_type`. Letters and digits
+/// are kept, every other character is written as its hex code between underscores.
+pub fn encode_type(ty: &str) -> String {
+    ty.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_string()
+            } else {
+                format!("_{:x}_", c as u32)
+            }
+        })
+        .collect()
+}
+
+pub fn decode_type(encoded: &str) -> Option<String> {
+    let mut decoded = String::new();
+    let mut chars = encoded.chars();
+    while let Some(c) = chars.next() {
+        if c == '_' {
+            let code: String = chars.by_ref().take_while(|c| *c != '_').collect();
+            decoded.push(char::from_u32(u32::from_str_radix(&code, 16).ok()?)?);
+        } else {
+            decoded.push(c);
+        }
+    }
+    Some(decoded)
+}
@@ -0,0 +1,89 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use std::collections::BTreeMap;
+use syn::{Ident, Type};
+
+use crate::{
+    helpers::{format_collection_name, format_field_name, format_index_name},
+    query_impl::CollectionComponentField,
+};
+
+/// Generates `WorldIndex`, to find entities by the value of an `#[indexed]` component and to
+/// change it. The value is looked up in the indexed columns whose type it resolves to.
+pub fn get_indexed_components(
+    world: &Ident,
+    collection_component_fields: &[CollectionComponentField],
+) -> TokenStream {
+    // entity type -> its indexed columns
+    let mut collections: BTreeMap<&String, Vec<&CollectionComponentField>> = BTreeMap::new();
+    for field in collection_component_fields
+        .iter()
+        .filter(|field| field.indexed)
+    {
+        collections
+            .entry(&field.collection_name)
+            .or_default()
+            .push(field);
+    }
+
+    // a world without indexed components doesn't implement it, `find_by` doesn't compile
+    if collections.is_empty() {
+        return quote! {};
+    }
+
+    let find_calls = collections.iter().flat_map(|(collection, fields)| {
+        let collection_field_name = format_field_name(collection);
+        fields.iter().map(move |field| {
+            let index_name = format_index_name(&field.field_name);
+            let field_type = syn::parse_str::<Type>(&field.field_type).expect("component type");
+            quote! {
+                if let Some(value) = SameComponent::<#field_type>::component_ref(value) {
+                    entities.extend_from_slice(self.#collection_field_name.#index_name.get(value));
+                }
+            }
+        })
+    });
+
+    let indexed_mut_arms = collections.iter().map(|(collection, fields)| {
+        let enum_name = format_ident!("{}", collection);
+        let collection_field_name = format_field_name(collection);
+        let collection_name = format_collection_name(collection);
+        let indexed_columns = fields.iter().map(|field| {
+            let column = format_ident!("{}", field.field_name);
+            let index_name = format_index_name(&field.field_name);
+            quote! {
+                if let (Some(column), Some(column_index)) = (
+                    SameComponent::<Vec<T>>::component_mut(&mut collection.#column),
+                    SameComponent::<ColumnIndex<T, Entity>>::component_mut(&mut collection.#index_name),
+                ) {
+                    return Some(IndexedMut::new(&mut column[index], entity, column_index));
+                }
+            }
+        });
+        quote! {
+            EntityType::#enum_name => {
+                let collection: &mut #collection_name = &mut self.#collection_field_name;
+                let index = collection.index_of(entity)?;
+                #(#indexed_columns)*
+                None
+            }
+        }
+    });
+
+    quote! {
+        impl<T: Clone + Eq + std::hash::Hash + 'static> WorldIndex<T> for #world {
+            fn find_by(&self, value: &T) -> Vec<Entity> {
+                let mut entities = Vec::new();
+                #(#find_calls)*
+                entities
+            }
+
+            fn indexed_mut(&mut self, entity: Entity) -> Option<IndexedMut<'_, T, Entity>> {
+                match entity.entity_type {
+                    #(#indexed_mut_arms)*
+                    _ => None,
+                }
+            }
+        }
+    }
+}
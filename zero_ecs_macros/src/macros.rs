@@ -3,14 +3,22 @@ extern crate proc_macro;
 use macro_magic::import_tokens_attr;
 use proc_macro::TokenStream;
 
+mod commands;
 mod default_queries;
 mod ecs_world_impl;
 mod entity_impl;
+mod events;
 mod helpers;
+mod indexed;
 mod make_query_impl;
+mod migrate;
 mod query_impl;
+mod resources;
+mod sparse;
+mod spatial;
 mod system_for_each_impl;
 mod system_impl;
+mod typed_entity;
 mod world_impl;
 
 #[proc_macro_attribute]
@@ -18,6 +26,14 @@ pub fn entity(attr: TokenStream, item: TokenStream) -> TokenStream {
     entity_impl::entity(attr, item)
 }
 
+#[proc_macro_derive(
+    EntityAttributes,
+    attributes(sparse, indexed, tracked, keep_removed, entity_options)
+)]
+pub fn entity_attributes(_item: TokenStream) -> TokenStream {
+    TokenStream::new()
+}
+
 #[proc_macro]
 pub fn ecs_world(input: TokenStream) -> TokenStream {
     ecs_world_impl::ecs_world(input)
@@ -34,27 +50,30 @@ pub fn expand_world(attr: TokenStream, item: TokenStream) -> TokenStream {
 }
 
 #[import_tokens_attr(zero_ecs::macro_magic)]
+#[with_custom_parsing(world_impl::TagWorldArgs)]
 #[proc_macro_attribute]
 pub fn tag_world(attr: TokenStream, item: TokenStream) -> TokenStream {
-    world_impl::tag_world(attr, item)
+    world_impl::tag_world(attr, item, __custom_tokens)
 }
 
 #[import_tokens_attr(zero_ecs::macro_magic)]
+#[with_custom_parsing(query_impl::QueryArgs)]
 #[proc_macro_attribute]
 pub fn query(attr: TokenStream, item: TokenStream) -> TokenStream {
-    query_impl::query(attr, item)
+    query_impl::query(attr, __custom_tokens, item)
 }
 
 #[import_tokens_attr(zero_ecs::macro_magic)]
+#[with_custom_parsing(query_impl::QueryArgs)]
 #[proc_macro_attribute]
 pub fn system_for_each(attr: TokenStream, item: TokenStream) -> TokenStream {
-    system_for_each_impl::system_for_each(attr, item)
+    system_for_each_impl::system_for_each(attr, __custom_tokens, item)
 }
 
 #[import_tokens_attr(zero_ecs::macro_magic)]
 #[proc_macro_attribute]
 pub fn system(attr: TokenStream, item: TokenStream) -> TokenStream {
-    system_impl::system(attr, item)
+    system_impl::system(attr, __source_path, item)
 }
 
 #[proc_macro]
@@ -2,13 +2,15 @@ use proc_macro::TokenStream;
 use quote::quote;
 use syn::{
     parse::{Parse, ParseStream},
-    parse_macro_input, Ident, Token,
+    parse_macro_input, parse_quote, Ident, Path, Token, Type,
 };
 
+use crate::query_impl::QueryFilter;
+
 /// Represents a component in the query, which can be mutable or immutable
 struct ComponentSpec {
     is_mut: bool,
-    ident: Ident,
+    ty: Type,
 }
 
 impl Parse for ComponentSpec {
@@ -17,60 +19,87 @@ impl Parse for ComponentSpec {
         if is_mut {
             input.parse::<Token![mut]>()?;
         }
-        let ident = input.parse::<Ident>()?;
-        Ok(ComponentSpec { is_mut, ident })
+        let ty = input.parse::<Type>()?;
+        Ok(ComponentSpec { is_mut, ty })
     }
 }
 
 /// Input for the make_query macro
-/// Format: QueryName, [mut] Component1, [mut] Component2, ...
+/// Format: [World:] QueryName, [mut] Component1, [mut] Component2, ... [; Filter1, Filter2, ...]
 struct MakeQueryInput {
+    world: Path,
     query_name: Ident,
     components: Vec<ComponentSpec>,
+    filters: Vec<QueryFilter>,
 }
 
 impl Parse for MakeQueryInput {
     fn parse(input: ParseStream) -> syn::Result<Self> {
+        // the world is optional, `World` by default
+        let fork = input.fork();
+        let world =
+            if fork.parse::<Path>().is_ok() && fork.peek(Token![:]) && !fork.peek(Token![::]) {
+                let world = input.parse::<Path>()?;
+                input.parse::<Token![:]>()?;
+                world
+            } else {
+                parse_quote!(World)
+            };
         let query_name = input.parse::<Ident>()?;
         input.parse::<Token![,]>()?;
 
         let mut components = Vec::new();
         loop {
             components.push(input.parse::<ComponentSpec>()?);
-            if input.is_empty() {
+            if input.is_empty() || input.peek(Token![;]) {
                 break;
             }
             input.parse::<Token![,]>()?;
-            if input.is_empty() {
+            if input.is_empty() || input.peek(Token![;]) {
                 break;
             }
         }
 
+        let mut filters = Vec::new();
+        if input.parse::<Option<Token![;]>>()?.is_some() {
+            while !input.is_empty() {
+                filters.push(input.parse::<QueryFilter>()?);
+                if input.is_empty() {
+                    break;
+                }
+                input.parse::<Token![,]>()?;
+            }
+        }
+
         Ok(MakeQueryInput {
+            world,
             query_name,
             components,
+            filters,
         })
     }
 }
 
 pub fn make_query(input: TokenStream) -> TokenStream {
     let MakeQueryInput {
+        world,
         query_name,
         components,
+        filters,
     } = parse_macro_input!(input as MakeQueryInput);
 
     // Generate the tuple fields for the struct
     let fields = components.iter().map(|comp| {
-        let ident = &comp.ident;
+        let ty = &comp.ty;
         if comp.is_mut {
-            quote! { &'a mut #ident }
+            quote! { &'a mut #ty }
         } else {
-            quote! { &'a #ident }
+            quote! { &'a #ty }
         }
     });
 
     let expanded = quote! {
-        #[query(World)]
+        #[query(#world #(, #filters)*)]
         struct #query_name<'a>(#(#fields),*);
     };
 
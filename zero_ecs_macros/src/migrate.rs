@@ -0,0 +1,104 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{Ident, Member, Type};
+
+use crate::{
+    helpers::{format_collection_name, format_field_name},
+    query_impl::CollectionComponentField,
+};
+
+/// Generates `WorldMigrate` for every pair of entity types. Components of the same type are
+/// moved over from the old entity, the rest are kept from the value passed by the caller.
+/// The old entity is taken and the new one created, so their hooks are called.
+pub fn get_migrations(
+    world: &Ident,
+    collection_component_fields: &[CollectionComponentField],
+) -> TokenStream {
+    let mut entity_names: Vec<&String> = vec![];
+    for field in collection_component_fields {
+        if !entity_names.contains(&&field.collection_name) {
+            entity_names.push(&field.collection_name);
+        }
+    }
+
+    let fields_of = |entity_name: &String| -> Vec<&CollectionComponentField> {
+        collection_component_fields
+            .iter()
+            .filter(|field| &field.collection_name == entity_name)
+            .collect()
+    };
+
+    let migrations = entity_names.iter().flat_map(|from_name| {
+        entity_names
+            .iter()
+            .filter(move |to_name| to_name != &from_name)
+            .map(move |to_name| {
+                let from_ident = format_ident!("{}", from_name);
+                let to_ident = format_ident!("{}", to_name);
+                let from_field_name = format_field_name(from_name);
+                let to_field_name = format_field_name(to_name);
+                let to_collection_name = format_collection_name(to_name);
+
+                let from_fields = fields_of(from_name);
+                let moves: Vec<_> = fields_of(to_name).into_iter().filter_map(|to_field| {
+                    // sparse fields are Option<T>, so they only match other sparse fields
+                    let from_field = from_fields.iter().find(|from_field| {
+                        from_field.key == to_field.key
+                            && from_field.sparse == to_field.sparse
+                    })?;
+                    let from_member = syn::parse_str::<Member>(&from_field.member).expect("entity field");
+                    let to_member = syn::parse_str::<Member>(&to_field.member).expect("entity field");
+                    let to_type = syn::parse_str::<Type>(&to_field.field_type).expect("component type");
+                    let to_type = if to_field.sparse {
+                        quote! { Option<#to_type> }
+                    } else {
+                        quote! { #to_type }
+                    };
+                    // the same name can be a different type, then the value passed is kept
+                    Some(quote! {
+                        if let Some(component) = SameComponent::<#to_type>::component_mut(&mut from.#from_member) {
+                            std::mem::swap(component, &mut to.#to_member);
+                        }
+                    })
+                }).collect();
+                let (to_binding, from_binding) = if moves.is_empty() {
+                    (quote! { to }, quote! { _ })
+                } else {
+                    (quote! { mut to }, quote! { mut from })
+                };
+
+                quote! {
+                    impl WorldMigrate<#from_ident, #to_ident> for #world {
+                        fn migrate(
+                            &mut self,
+                            entity: TypedEntity<#from_ident>,
+                            #to_binding: #to_ident,
+                        ) -> Option<TypedEntity<#to_ident>> {
+                            // taken and created, so the hooks see the old entity destroyed and
+                            // the new one created, and the old one is recorded as removed with the
+                            // new handle. What is left of it is dropped at the end, including the
+                            // values the moved components replaced
+                            let new_entity = self.#to_field_name.next_entity();
+                            let #from_binding = self
+                                .#from_field_name
+                                .take_entity(entity.entity(), Some(new_entity))?;
+
+                            #(#moves)*
+
+                            let created = <#to_collection_name as WorldCreate<#to_ident>>::create(
+                                &mut self.#to_field_name,
+                                to,
+                            );
+                            debug_assert_eq!(created.entity(), new_entity);
+                            self.__hierarchy.replace(entity.entity(), new_entity);
+                            Some(created)
+                        }
+                    }
+                }
+            })
+    });
+
+    quote! {
+        #(#migrations)*
+    }
+}
@@ -1,15 +1,221 @@
 use proc_macro::TokenStream;
-use quote::quote;
+use quote::{quote, ToTokens};
 use std::collections::HashSet;
-use syn::{spanned::Spanned, Error, Fields, ItemStruct, Type};
+use syn::{
+    parse::{Parse, ParseStream},
+    spanned::Spanned,
+    Error, Fields, GenericArgument, Ident, ItemStruct, Path, PathArguments, Token, Type,
+};
 
-use crate::helpers::{format_collection_name, format_field_name};
+use crate::helpers::{
+    component_key, decode_type, format_collection_name, format_field_name, format_tick_names,
+    world_items_path,
+};
+
+/// A filter on the entities a query matches. It doesn't borrow the component.
+#[derive(Debug, Clone)]
+pub enum QueryFilter {
+    /// `Added<T>`, the entity got `T` since the last `clear_trackers`.
+    Added(Type),
+    /// `Changed<T>`, `T` was accessed mutably, or added, since the last `clear_trackers`.
+    Changed(Type),
+    /// `With<T>`, the entity type has a `T` component.
+    With(Type),
+    /// `Without<T>`, the entity type has no `T` component.
+    Without(Type),
+}
+
+impl QueryFilter {
+    pub fn from_type(ty: &Type) -> syn::Result<Self> {
+        let error = || {
+            Error::new(
+                ty.span(),
+                "expected `Added<T>`, `Changed<T>`, `With<T>` or `Without<T>`",
+            )
+        };
+        let Type::Path(type_path) = ty else {
+            return Err(error());
+        };
+        let segment = type_path.path.segments.last().ok_or_else(error)?;
+        let PathArguments::AngleBracketed(args) = &segment.arguments else {
+            return Err(error());
+        };
+        let Some(GenericArgument::Type(component)) = args.args.first() else {
+            return Err(error());
+        };
+        let component = component.clone();
+        if segment.ident == "Added" {
+            Ok(QueryFilter::Added(component))
+        } else if segment.ident == "Changed" {
+            Ok(QueryFilter::Changed(component))
+        } else if segment.ident == "With" {
+            Ok(QueryFilter::With(component))
+        } else if segment.ident == "Without" {
+            Ok(QueryFilter::Without(component))
+        } else {
+            Err(error())
+        }
+    }
+
+    /// Parses a filter, or a tuple of them.
+    pub fn from_types(ty: &Type) -> syn::Result<Vec<Self>> {
+        match ty {
+            Type::Tuple(tuple) => tuple.elems.iter().map(Self::from_type).collect(),
+            Type::Paren(paren) => Ok(vec![Self::from_type(&paren.elem)?]),
+            ty => Ok(vec![Self::from_type(ty)?]),
+        }
+    }
+
+    pub fn component(&self) -> &Type {
+        match self {
+            QueryFilter::Added(ty)
+            | QueryFilter::Changed(ty)
+            | QueryFilter::With(ty)
+            | QueryFilter::Without(ty) => ty,
+        }
+    }
+
+    /// `Added` and `Changed` compare ticks per entity, the others only pick entity types.
+    pub fn is_tick_filter(&self) -> bool {
+        matches!(self, QueryFilter::Added(_) | QueryFilter::Changed(_))
+    }
+}
+
+impl Parse for QueryFilter {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        QueryFilter::from_type(&input.parse()?)
+    }
+}
+
+impl ToTokens for QueryFilter {
+    fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
+        match self {
+            QueryFilter::Added(ty) => quote! { Added<#ty> }.to_tokens(tokens),
+            QueryFilter::Changed(ty) => quote! { Changed<#ty> }.to_tokens(tokens),
+            QueryFilter::With(ty) => quote! { With<#ty> }.to_tokens(tokens),
+            QueryFilter::Without(ty) => quote! { Without<#ty> }.to_tokens(tokens),
+        }
+    }
+}
+
+/// The arguments of `#[query(...)]` and `#[system_for_each(...)]`, the world followed by
+/// filters: `#[query(World, Changed<Position>)]`.
+pub struct QueryArgs {
+    pub world: Path,
+    pub filters: Vec<QueryFilter>,
+}
+
+impl Parse for QueryArgs {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let world = input.parse()?;
+        let mut filters = Vec::new();
+        while !input.is_empty() {
+            input.parse::<Token![,]>()?;
+            if input.is_empty() {
+                break;
+            }
+            filters.push(input.parse()?);
+        }
+        Ok(QueryArgs { world, filters })
+    }
+}
+
+impl ToTokens for QueryArgs {
+    fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
+        self.world.to_tokens(tokens);
+        for filter in &self.filters {
+            quote! { , #filter }.to_tokens(tokens);
+        }
+    }
+}
+
+impl macro_magic::mm_core::ForeignPath for QueryArgs {
+    fn foreign_path(&self) -> &syn::Path {
+        &self.world
+    }
+}
 
 #[derive(Debug)]
 pub struct CollectionComponentField {
     pub collection_name: String,
     pub field_name: String,
+    /// The component type as the world names it, through the alias next to the entity.
     pub field_type: String,
+    /// The component type as the entity writes it.
+    pub written_type: String,
+    /// What the component is matched by, see `component_key`.
+    pub key: String,
+    pub sparse: bool,
+    /// `#[indexed]`, the collection keeps a `ColumnIndex` next to the column.
+    pub indexed: bool,
+    /// `#[tracked]`, the collection keeps the ticks it was added and changed at.
+    pub tracked: bool,
+    /// The field of the entity struct, its position for tuple structs.
+    pub member: String,
+}
+
+/// Parses a tagged world field,
+/// `__twcf__{entity}__{field}__{encoded type}[__sparse|__indexed|__tracked][__unnamed]`,
+/// of type `PhantomData<T>`, where `T` is the component type as the world names it.
+fn parse_tagged_field(name: &str, ty: &Type) -> Option<CollectionComponentField> {
+    let name = name.strip_prefix("__twcf__")?;
+    let mut parts = name.splitn(3, "__");
+    let collection_name = parts.next()?;
+    let field_name = parts.next()?;
+    let encoded_type = parts.next()?;
+    let (encoded_type, unnamed) = match encoded_type.strip_suffix("__unnamed") {
+        Some(encoded_type) => (encoded_type, true),
+        None => (encoded_type, false),
+    };
+    let (encoded_type, sparse) = match encoded_type.strip_suffix("__sparse") {
+        Some(encoded_type) => (encoded_type, true),
+        None => (encoded_type, false),
+    };
+    let (encoded_type, indexed) = match encoded_type.strip_suffix("__indexed") {
+        Some(encoded_type) => (encoded_type, true),
+        None => (encoded_type, false),
+    };
+    let (encoded_type, tracked) = match encoded_type.strip_suffix("__tracked") {
+        Some(encoded_type) => (encoded_type, true),
+        None => (encoded_type, false),
+    };
+    let written_type = decode_type(encoded_type)?;
+    let key = component_key(&syn::parse_str::<Type>(&written_type).ok()?);
+    let Type::Path(phantom) = ty else {
+        return None;
+    };
+    let PathArguments::AngleBracketed(args) = &phantom.path.segments.last()?.arguments else {
+        return None;
+    };
+    let field_type = args.args.first()?.to_token_stream().to_string();
+    // tuple struct columns are named `field_{index}`, see `format_column_name`
+    let member = if unnamed {
+        field_name.strip_prefix("field_")?.to_string()
+    } else {
+        field_name.to_string()
+    };
+    Some(CollectionComponentField {
+        collection_name: collection_name.to_string(),
+        field_name: field_name.to_string(),
+        field_type,
+        written_type,
+        key,
+        sparse,
+        indexed,
+        tracked,
+        member,
+    })
+}
+
+/// Whether the entities write the component `key` as different types. They can be different
+/// types with the same name, so the generated code compares the types they resolve to.
+pub fn is_ambiguous(collection_component_fields: &[CollectionComponentField], key: &str) -> bool {
+    let mut written_types = collection_component_fields
+        .iter()
+        .filter(|field| field.key == key)
+        .map(|field| &field.written_type);
+    let first = written_types.next();
+    written_types.any(|written_type| Some(written_type) != first)
 }
 
 pub fn get_collection_component_fields(
@@ -26,46 +232,31 @@ pub fn get_collection_component_fields(
         .named
         .iter()
         .filter_map(|field| {
-            if let Some(field_name) = &field.ident {
-                if field_name.to_string().starts_with("__twcf__") {
-                    let field_name_str = field_name.to_string();
-                    let field_name_str = field_name_str.replace("__twcf__", "");
-                    let parts: Vec<_> = field_name_str.split("__").collect();
-                    if parts.len() == 3 {
-                        let collection_name = parts[0];
-                        let field_name = parts[1];
-                        let field_type = parts[2];
-                        Some((
-                            collection_name.to_string(),
-                            field_name.to_string(),
-                            field_type.to_string(),
-                        ))
-                    } else {
-                        // print error to build
-                        eprintln!("Error: invalid field name: {}", field_name_str);
-                        None
-                    }
-                } else {
-                    None
-                }
-            } else {
-                None
+            let field_name = field.ident.as_ref()?.to_string();
+            if !field_name.starts_with("__twcf__") {
+                return None;
+            }
+            let parsed = parse_tagged_field(&field_name, &field.ty);
+            if parsed.is_none() {
+                // print error to build
+                eprintln!("Error: invalid field name: {}", field_name);
             }
+            parsed
         })
-        .map(
-            |(collection_name, field_name, field_type)| CollectionComponentField {
-                collection_name,
-                field_name,
-                field_type,
-            },
-        )
         .collect();
 
     collection_component_fields
 }
 
-pub fn query(attr: TokenStream, item: TokenStream) -> TokenStream {
+pub fn query(attr: TokenStream, args: TokenStream, item: TokenStream) -> TokenStream {
     let foreign_struct = syn::parse_macro_input!(attr as ItemStruct);
+    let QueryArgs {
+        world: world_path,
+        filters,
+    } = syn::parse_macro_input!(args as QueryArgs);
+    // the world's types are referred to by path, the world can be in another module
+    let items = world_items_path(&world_path);
+    let world_entities = foreign_struct.ident.clone();
     let collection_component_fields = get_collection_component_fields(foreign_struct);
     let local_struct = syn::parse_macro_input!(item as ItemStruct);
     let Fields::Unnamed(local_fields) = local_struct.fields else {
@@ -77,36 +268,93 @@ pub fn query(attr: TokenStream, item: TokenStream) -> TokenStream {
     let local_struct_name = local_struct.ident;
 
     let local_fields: Vec<_> = local_fields.unnamed.iter().collect();
+    let mutable_local_fields: Vec<bool> = local_fields
+        .iter()
+        .filter_map(|field| match &field.ty {
+            Type::Reference(ty) => Some(ty.mutability.is_some()),
+            _ => None,
+        })
+        .collect();
     let mut any_mutable_local_fields = false;
     let types_to_query: Vec<String> = local_fields
         .iter()
         .filter_map(|field| {
-            // only the type name, ignore all ' and < and stuff
-            // start by casting to a reference
+            // match on the referenced type, ignoring its module path
             if let Type::Reference(ty) = &field.ty {
                 if ty.mutability.is_some() {
                     any_mutable_local_fields = true;
                 }
-
-                // then get the type
-                if let Type::Path(path) = &*ty.elem {
-                    // then get the first segment
-                    if let Some(segment) = path.path.segments.first() {
-                        // then get the ident
-                        let ident = &segment.ident;
-                        Some(ident.to_string())
-                    } else {
-                        None
-                    }
-                } else {
-                    None
-                }
+                Some(component_key(&ty.elem))
             } else {
                 None
             }
         })
         .collect();
 
+    let query_types: Vec<&Type> = local_fields
+        .iter()
+        .filter_map(|field| match &field.ty {
+            Type::Reference(ty) => Some(&*ty.elem),
+            _ => None,
+        })
+        .collect();
+
+    let filter_types: Vec<String> = filters
+        .iter()
+        .map(|filter| component_key(filter.component()))
+        .collect();
+    let tick_filters: Vec<_> = filters
+        .iter()
+        .zip(&filter_types)
+        .filter(|(filter, _)| filter.is_tick_filter())
+        .collect();
+    let with_types: Vec<_> = filters
+        .iter()
+        .zip(&filter_types)
+        .filter(|(filter, _)| matches!(filter, QueryFilter::With(_)))
+        .map(|(_, filter_type)| filter_type)
+        .collect();
+    let without_types: Vec<_> = filters
+        .iter()
+        .zip(&filter_types)
+        .filter(|(filter, _)| matches!(filter, QueryFilter::Without(_)))
+        .map(|(_, filter_type)| filter_type)
+        .collect();
+
+    // catches misspelled components, which would match every or no entity type
+    for (filter, filter_type) in filters.iter().zip(&filter_types) {
+        if !filter.is_tick_filter()
+            && !collection_component_fields
+                .iter()
+                .any(|field| field.key == *filter_type)
+        {
+            return Error::new(
+                filter.component().span(),
+                format!("`{}` isn't a component of any entity", filter_type),
+            )
+            .to_compile_error()
+            .into();
+        }
+    }
+
+    // entities that don't track a filtered component don't match, but one of them has to
+    for (filter, filter_type) in &tick_filters {
+        if !collection_component_fields
+            .iter()
+            .any(|field| field.key == **filter_type && field.tracked)
+        {
+            return Error::new(
+                filter.component().span(),
+                format!(
+                    "`{}` isn't #[tracked] in any entity, it can't be used in `Added` or `Changed`",
+                    filter_type
+                ),
+            )
+            .to_compile_error()
+            .into();
+        }
+    }
+
     let all_collections: HashSet<_> = collection_component_fields
         .iter()
         .map(|field| &field.collection_name)
@@ -116,10 +364,24 @@ pub fn query(attr: TokenStream, item: TokenStream) -> TokenStream {
     let matching_collections: Vec<_> = all_collections
         .iter()
         .filter_map(|collection_name| {
-            let collection_types = collection_component_fields
+            let collection_fields: Vec<_> = collection_component_fields
                 .iter()
                 .filter(|field| field.collection_name == **collection_name)
-                .map(|field| &field.field_type)
+                .collect();
+            let collection_types = collection_fields
+                .iter()
+                .map(|field| &field.key)
+                .collect::<HashSet<_>>();
+            let tracked_types = collection_fields
+                .iter()
+                .filter(|field| field.tracked)
+                .map(|field| &field.key)
+                .collect::<HashSet<_>>();
+            // a sparse component is checked per row, unless it's also queried
+            let dense_types = collection_fields
+                .iter()
+                .filter(|field| !field.sparse || types_to_query.contains(&field.key))
+                .map(|field| &field.key)
                 .collect::<HashSet<_>>();
 
             if types_to_query
@@ -127,6 +389,17 @@ pub fn query(attr: TokenStream, item: TokenStream) -> TokenStream {
                 // entity is special case
                 .filter(|field_name| *field_name != "Entity")
                 .all(|field_name| collection_types.contains(field_name))
+                && tick_filters
+                    .iter()
+                    .all(|(_, field_name)| tracked_types.contains(field_name))
+                && with_types
+                    .iter()
+                    .all(|field_name| collection_types.contains(field_name))
+                // an ambiguous name can be another type, that is checked when the query runs
+                && !without_types.iter().any(|field_name| {
+                    dense_types.contains(field_name)
+                        && !is_ambiguous(&collection_component_fields, field_name)
+                })
             {
                 Some(*collection_name)
             } else {
@@ -145,7 +418,7 @@ pub fn query(attr: TokenStream, item: TokenStream) -> TokenStream {
                     // find from collection_component_fields the one that matches field_type and the collection name. Should only be one
                     let field_name = if field_type != "Entity" { collection_component_fields
                         .iter()
-                        .find(|field| &field.collection_name == collection_name && field.field_type == *field_type)
+                        .find(|field| &field.collection_name == collection_name && field.key == *field_type)
                         .unwrap_or_else(|| panic!("expect_collection_component_fields field_type: {}", field_type))
                         .field_name
                         .clone()
@@ -155,70 +428,259 @@ pub fn query(attr: TokenStream, item: TokenStream) -> TokenStream {
                     quote::format_ident!("{}", field_name)
                 }).collect();
 
+            // indexed components are changed through `indexed_mut`, which keeps the index up to date
+            let indexed_mutable_field = types_to_query
+                .iter()
+                .zip(&mutable_local_fields)
+                .position(|(field_type, mutable)| {
+                    *mutable
+                        && collection_component_fields.iter().any(|field| {
+                            &field.collection_name == collection_name
+                                && field.key == *field_type
+                                && field.indexed
+                        })
+                });
+            if let Some(position) = indexed_mutable_field {
+                let queried_fields: Vec<_> = local_fields
+                    .iter()
+                    .filter(|field| matches!(field.ty, Type::Reference(_)))
+                    .collect();
+                return Error::new(
+                    queried_fields[position].ty.span(),
+                    format!(
+                        "`{}` is #[indexed] in `{}`, it can't be queried mutably, use `indexed_mut` to change it",
+                        types_to_query[position], collection_name
+                    ),
+                )
+                .to_compile_error();
+            }
+
+            let sparse_local_fields: Vec<bool> = types_to_query
+                .iter()
+                .map(|field_type| {
+                    collection_component_fields.iter().any(|field| {
+                        &field.collection_name == collection_name
+                            && field.key == *field_type
+                            && field.sparse
+                    })
+                })
+                .collect();
+            let field_of = |field_type: &String| {
+                collection_component_fields.iter().find(|field| {
+                    &field.collection_name == collection_name && field.key == *field_type
+                })
+            };
+
+            // components written as different types in the entities are compared by the types
+            // their columns resolve to, through the aliases the world defines for them
+            let same_type = |field_type: &String, ty: &Type| {
+                let field = field_of(field_type)?;
+                if !is_ambiguous(&collection_component_fields, field_type) {
+                    return None;
+                }
+                let alias = syn::parse_str::<Type>(&field.field_type).expect("component type");
+                Some((quote! { is_same_component::<#items #alias, #ty>() }, field.sparse))
+            };
+            let casts: Vec<Option<&Type>> = types_to_query
+                .iter()
+                .zip(&query_types)
+                .map(|(field_type, ty)| same_type(field_type, ty).map(|_| *ty))
+                .collect();
+            let mut same_types: Vec<_> = types_to_query
+                .iter()
+                .zip(&query_types)
+                .filter_map(|(field_type, ty)| same_type(field_type, ty))
+                .map(|(same, _)| same)
+                .collect();
+            // sparse components of `Without` are checked per row, only if they're the same type
+            let mut without_same_types = vec![];
+            for (filter, filter_type) in filters.iter().zip(&filter_types) {
+                let Some((same, sparse)) = same_type(filter_type, filter.component()) else {
+                    continue;
+                };
+                match filter {
+                    QueryFilter::Without(_) if sparse => without_same_types.push((filter_type, same)),
+                    QueryFilter::Without(_) => same_types.push(quote! { !#same }),
+                    _ => same_types.push(same),
+                }
+            }
+            let guard = (!same_types.is_empty()).then(|| quote! { #(#same_types)&&* });
+
+            // the ticks the filters compare, and the ticks mutable tracked components set
+            let filter_ticks: Vec<_> = tick_filters
+                .iter()
+                .map(|(filter, filter_type)| {
+                    let field = field_of(filter_type).expect("collection tracks the filtered component");
+                    let (added_name, changed_name) = format_tick_names(&field.field_name);
+                    match filter {
+                        QueryFilter::Added(_) => added_name,
+                        QueryFilter::Changed(_) => changed_name,
+                        QueryFilter::With(_) | QueryFilter::Without(_) => {
+                            unreachable!("only tick filters compare ticks")
+                        }
+                    }
+                })
+                .collect();
+            // sparse components of `With` and `Without`, checked per row
+            let sparse_filters: Vec<_> = with_types
+                .iter()
+                .map(|filter_type| (filter_type, true))
+                .chain(without_types.iter().map(|filter_type| (filter_type, false)))
+                .filter(|(filter_type, _)| !types_to_query.contains(filter_type))
+                .filter_map(|(filter_type, with)| {
+                    let field = field_of(filter_type).filter(|field| field.sparse)?;
+                    let same = without_same_types
+                        .iter()
+                        .find(|(without_type, _)| *without_type == *filter_type)
+                        .map(|(_, same)| same.clone());
+                    Some((quote::format_ident!("{}", field.field_name), with, same))
+                })
+                .collect();
+            let changed_ticks: Vec<_> = types_to_query
+                .iter()
+                .zip(&mutable_local_fields)
+                .filter(|(_, mutable)| **mutable)
+                .filter_map(|(field_type, _)| field_of(field_type))
+                .filter(|field| field.tracked)
+                .map(|field| format_tick_names(&field.field_name).1)
+                .collect();
+
+            if sparse_local_fields.iter().any(|sparse| *sparse)
+                || !filter_ticks.is_empty()
+                || !changed_ticks.is_empty()
+                || !sparse_filters.is_empty()
+            {
+                let fields: Vec<_> = collection_field_names
+                    .iter()
+                    .zip(sparse_local_fields)
+                    .zip(&mutable_local_fields)
+                    .zip(&casts)
+                    .map(|(((name, sparse), mutable), cast)| (name, sparse, *mutable, *cast))
+                    .collect();
+                return get_row_query_code(
+                    &items,
+                    &local_struct_name,
+                    &collection_type_name,
+                    &fields,
+                    &filter_ticks,
+                    &changed_ticks,
+                    &sparse_filters,
+                    guard.as_ref(),
+                );
+            }
+
+            let resolved = |value: &dyn Fn(&Ident) -> proc_macro2::TokenStream, mutable: bool| {
+                collection_field_names
+                    .iter()
+                    .zip(&mutable_local_fields)
+                    .zip(&casts)
+                    .map(|((name, field_mutable), cast)| {
+                        resolve_value(value(name), *cast, mutable && *field_mutable)
+                    })
+                    .collect::<Vec<_>>()
+            };
+            let iter_values = resolved(&|name| quote! { #name }, false);
+            let iter_mut_values = resolved(&|name| quote! { #name }, true);
+            let get_values = resolved(&|name| quote! { self.#name.get(index)? }, false);
+            let get_mut_values = resolved(&|name| quote! { self.#name.get_mut(index)? }, true);
+            // a cast that fails skips the row, so the rows can't just be mapped
+            let to_item = if casts.iter().any(Option::is_some) {
+                quote! { filter_map }
+            } else {
+                quote! { map }
+            };
+            let some_item = |values: &[proc_macro2::TokenStream]| {
+                if casts.iter().any(Option::is_some) {
+                    quote! { Some(#local_struct_name(#(#values),*)) }
+                } else {
+                    quote! { #local_struct_name(#(#values),*) }
+                }
+            };
+            let iter_item = some_item(&iter_values);
+            let iter_mut_item = some_item(&iter_mut_values);
+            let guard = QueryGuard(guard.as_ref());
+            let query_from_body = guard.rows(quote! {
+                izip!(#(self.#collection_field_names.iter()),*)
+                    .#to_item(|(#(#collection_field_names),*)| #iter_item)
+            });
+            let par_query_from_body = guard.par_rows(quote! {
+                izip_par!(#(self.#collection_field_names.par_iter()),*)
+                    .#to_item(|(#(#collection_field_names),*)| #iter_item)
+            });
+            let query_mut_from_body = guard.rows(quote! {
+                izip!(#(self.#collection_field_names.iter_mut()),*)
+                    .#to_item(|(#(#collection_field_names),*)| #iter_mut_item)
+            });
+            let par_query_mut_from_body = guard.par_rows(quote! {
+                izip_par!(#(self.#collection_field_names.par_iter_mut()),*)
+                    .#to_item(|(#(#collection_field_names),*)| #iter_mut_item)
+            });
+            let get_from_body = guard.item(quote! {
+                if let Some(index) = self.index_of(entity) {
+                    Some(#local_struct_name(#(#get_values),*))
+                } else {
+                    None
+                }
+            });
+            let at_body = guard.item(quote! {
+                Some(#local_struct_name(#(#get_values),*))
+            });
+            let get_mut_from_body = guard.item(quote! {
+                if let Some(index) = self.index_of(entity) {
+                    Some(#local_struct_name(#(#get_mut_values),*))
+                } else {
+                    None
+                }
+            });
+            let at_mut_body = guard.item(quote! {
+                Some(#local_struct_name(#(#get_mut_values),*))
+            });
+            let len_body = guard.len(quote! { self.entity.len() });
+
             let query_code = quote! {
-                impl<'a> QueryFrom<'a, #local_struct_name<'a>> for #collection_type_name {
+                impl<'a> #items QueryFrom<'a, #local_struct_name<'a>> for #items #collection_type_name {
                     fn query_from(&'a self) -> impl Iterator<Item = #local_struct_name<'a>> {
-                        izip!(#(self.#collection_field_names.iter()),*)
-                            .map(|(#(#collection_field_names),*)| #local_struct_name(#(#collection_field_names),*))
+                        #query_from_body
                     }
 
                     fn par_query_from(&'a self) -> impl ParallelIterator<Item = #local_struct_name<'a>> {
-                        izip_par!(#(self.#collection_field_names.par_iter()),*)
-                            .map(|(#(#collection_field_names),*)| #local_struct_name(#(#collection_field_names),*))
+                        #par_query_from_body
                     }
 
-                    fn get_from(&'a self, entity: Entity) -> Option<#local_struct_name<'a>> {
-                        if let Some(&Some(index)) = self.index_lookup.get(entity.id) {
-                            Some(#local_struct_name(
-                                #(self.#collection_field_names.get(index)?),*
-                            ))
-                        } else {
-                            None
-                        }
+                    fn get_from(&'a self, entity: #items Entity) -> Option<#local_struct_name<'a>> {
+                        #get_from_body
                     }
 
                     fn at(&'a self, index: usize) -> Option<#local_struct_name<'a>> {
-                        Some(#local_struct_name(
-                            #(self.#collection_field_names.get(index)?),*
-                        ))
+                        #at_body
                     }
                 }
             };
 
             let query_mut_code = quote! {
-                impl<'a> QueryMutFrom<'a, #local_struct_name<'a>> for #collection_type_name {
+                impl<'a> #items QueryMutFrom<'a, #local_struct_name<'a>> for #items #collection_type_name {
                     fn query_mut_from(&'a mut self) -> impl Iterator<Item = #local_struct_name<'a>> {
-                        izip!(#(self.#collection_field_names.iter_mut()),*)
-                            .map(|(#(#collection_field_names),*)| #local_struct_name(#(#collection_field_names),*))
+                        #query_mut_from_body
                     }
 
                     fn par_query_mut_from(&'a mut self) -> impl ParallelIterator<Item = #local_struct_name<'a>> {
-                        izip_par!(#(self.#collection_field_names.par_iter_mut()),*)
-                            .map(|(#(#collection_field_names),*)| #local_struct_name(#(#collection_field_names),*))
+                        #par_query_mut_from_body
                     }
 
-                    fn get_mut_from(&'a mut self, entity: Entity) -> Option<#local_struct_name<'a>> {
-                        if let Some(&Some(index)) = self.index_lookup.get(entity.id) {
-                            Some(#local_struct_name(
-                                #(self.#collection_field_names.get_mut(index)?),*
-                            ))
-                        } else {
-                            None
-                        }
+                    fn get_mut_from(&'a mut self, entity: #items Entity) -> Option<#local_struct_name<'a>> {
+                        #get_mut_from_body
                     }
 
                     fn at_mut(&'a mut self, index: usize) -> Option<#local_struct_name<'a>> {
-                        Some(#local_struct_name(
-                            #(self.#collection_field_names.get_mut(index)?),*
-                        ))
+                        #at_mut_body
                     }
                 }
             };
 
             let len_from_code = quote! {
-                impl<'a> LenFrom<'a, #local_struct_name<'a>> for #collection_type_name {
+                impl<'a> #items LenFrom<'a, #local_struct_name<'a>> for #items #collection_type_name {
                     fn len(&'a self) -> usize {
-                        self.entity.len()
+                        #len_body
                     }
                 }
             };
@@ -238,7 +700,10 @@ pub fn query(attr: TokenStream, item: TokenStream) -> TokenStream {
         })
         .collect();
 
-    let world_query_code = {
+    // implemented for the world's entities, and for the view of its collections
+    // `#[system_for_each]` uses to borrow resources next to them
+    let world_query_code = |impl_generics: proc_macro2::TokenStream,
+                            self_type: proc_macro2::TokenStream| {
         let world_fields: Vec<_> = matching_collections.iter().map(format_field_name).collect();
 
         let query_from_body_parts: Vec<_> = matching_collections
@@ -249,7 +714,7 @@ pub fn query(attr: TokenStream, item: TokenStream) -> TokenStream {
                 let collection_name = format_collection_name(name);
 
                 quote! {
-                    <#collection_name as QueryFrom<'a, #local_struct_name<'a>>>::query_from(& self.#field_name)
+                    <#items #collection_name as #items QueryFrom<'a, #local_struct_name<'a>>>::query_from(& self.#field_name)
                 }
             })
             .collect();
@@ -260,7 +725,7 @@ pub fn query(attr: TokenStream, item: TokenStream) -> TokenStream {
                 let field_name = format_field_name(name);
                 let collection_name = format_collection_name(name);
                 quote! {
-                    <#collection_name as QueryMutFrom<'a, #local_struct_name<'a>>>::query_mut_from(&mut self.#field_name)
+                    <#items #collection_name as #items QueryMutFrom<'a, #local_struct_name<'a>>>::query_mut_from(&mut self.#field_name)
                 }
             })
             .collect();
@@ -271,7 +736,7 @@ pub fn query(attr: TokenStream, item: TokenStream) -> TokenStream {
                 let field_name = format_field_name(name);
                 let collection_name = format_collection_name(name);
                 quote! {
-                    <#collection_name as QueryFrom<'a, #local_struct_name<'a>>>::par_query_from(&self.#field_name)
+                    <#items #collection_name as #items QueryFrom<'a, #local_struct_name<'a>>>::par_query_from(&self.#field_name)
                 }
             })
             .collect();
@@ -281,7 +746,7 @@ pub fn query(attr: TokenStream, item: TokenStream) -> TokenStream {
                 let field_name = format_field_name(name);
                 let collection_name = format_collection_name(name);
                 quote! {
-                    <#collection_name as QueryMutFrom<'a, #local_struct_name<'a>>>::par_query_mut_from(&mut self.#field_name)
+                    <#items #collection_name as #items QueryMutFrom<'a, #local_struct_name<'a>>>::par_query_mut_from(&mut self.#field_name)
                 }
             })
             .collect();
@@ -293,7 +758,7 @@ pub fn query(attr: TokenStream, item: TokenStream) -> TokenStream {
                 let enum_name = quote::format_ident!("{}", name);
 
                 quote! {
-                    EntityType::#enum_name => self.#field_name.get(entity)
+                    #items EntityType::#enum_name => self.#field_name.get(entity)
                 }
             })
             .collect();
@@ -305,17 +770,22 @@ pub fn query(attr: TokenStream, item: TokenStream) -> TokenStream {
                 let enum_name = quote::format_ident!("{}", name);
 
                 quote! {
-                    EntityType::#enum_name => self.#field_name.get_mut(entity)
+                    #items EntityType::#enum_name => self.#field_name.get_mut(entity)
                 }
             })
             .collect();
 
+        let collection_names: Vec<_> = matching_collections
+            .iter()
+            .map(format_collection_name)
+            .collect();
         let at_parts: Vec<_> = world_fields
             .iter()
-            .map(|name| {
+            .zip(&collection_names)
+            .map(|(name, collection_name)| {
                 quote! {
                     {
-                        let len = self.#name.len();
+                        let len = <#items #collection_name as #items LenFrom<'_, #local_struct_name<'_>>>::len(&self.#name);
                         if index < len {
                             return self.#name.at(index);
                         }
@@ -327,10 +797,11 @@ pub fn query(attr: TokenStream, item: TokenStream) -> TokenStream {
 
         let at_mut_parts: Vec<_> = world_fields
             .iter()
-            .map(|name| {
+            .zip(&collection_names)
+            .map(|(name, collection_name)| {
                 quote! {
                     {
-                        let len = self.#name.len();
+                        let len = <#items #collection_name as #items LenFrom<'_, #local_struct_name<'_>>>::len(&self.#name);
                         if index < len {
                             return self.#name.at_mut(index);
                         }
@@ -342,16 +813,17 @@ pub fn query(attr: TokenStream, item: TokenStream) -> TokenStream {
 
         let len_parts: Vec<_> = world_fields
             .iter()
-            .map(|name| {
+            .zip(&collection_names)
+            .map(|(name, collection_name)| {
                 quote! {
-                    self.#name.len()
+                    <#items #collection_name as #items LenFrom<'_, #local_struct_name<'_>>>::len(&self.#name)
                 }
             })
             .collect();
 
         let query_code = quote! {
 
-            impl<'a> QueryFrom<'a, #local_struct_name<'a>> for World {
+            impl<#impl_generics> #items QueryFrom<'a, #local_struct_name<'a>> for #self_type {
                 fn query_from(&'a self) -> impl Iterator<Item = #local_struct_name<'a>> {
                     chain!(
                         #(#query_from_body_parts),*
@@ -364,7 +836,7 @@ pub fn query(attr: TokenStream, item: TokenStream) -> TokenStream {
                     )
                 }
 
-                fn get_from(&'a self, entity: Entity) -> Option<#local_struct_name<'a>> {
+                fn get_from(&'a self, entity: #items Entity) -> Option<#local_struct_name<'a>> {
                     match entity.entity_type {
                         #(#get_from_body_parts,)*
                         _ => None,
@@ -380,7 +852,7 @@ pub fn query(attr: TokenStream, item: TokenStream) -> TokenStream {
         };
 
         let query_mut_code = quote! {
-            impl<'a> QueryMutFrom<'a, #local_struct_name<'a>> for World {
+            impl<#impl_generics> #items QueryMutFrom<'a, #local_struct_name<'a>> for #self_type {
                 fn query_mut_from(&'a mut self) -> impl Iterator<Item = #local_struct_name<'a>> {
                     chain!(
                         #(#query_mut_from_body_parts),*
@@ -393,7 +865,7 @@ pub fn query(attr: TokenStream, item: TokenStream) -> TokenStream {
                     )
                 }
 
-                fn get_mut_from(&'a mut self, entity: Entity) -> Option<#local_struct_name<'a>> {
+                fn get_mut_from(&'a mut self, entity: #items Entity) -> Option<#local_struct_name<'a>> {
                     match entity.entity_type {
                         #(#get_mut_from_body_parts,)*
                         _ => None,
@@ -421,7 +893,7 @@ pub fn query(attr: TokenStream, item: TokenStream) -> TokenStream {
         };
 
         let len_from_code = quote! {
-            impl<'a> LenFrom<'a, #local_struct_name<'a>> for World {
+            impl<#impl_generics> #items LenFrom<'a, #local_struct_name<'a>> for #self_type {
                 fn len(&'a self) -> usize {
                     #sum
                 }
@@ -441,6 +913,8 @@ pub fn query(attr: TokenStream, item: TokenStream) -> TokenStream {
             }
         }
     };
+    let world_code = world_query_code(quote! { 'a }, quote! { #items #world_entities });
+    let entities_code = world_query_code(quote! { 'a, 'w }, quote! { #items __WorldEntities<'w> });
 
     quote! {
 
@@ -449,7 +923,515 @@ pub fn query(attr: TokenStream, item: TokenStream) -> TokenStream {
 
         #(#query_codes)*
 
-        #world_query_code
+        #world_code
+        #entities_code
     }
     .into()
 }
+
+/// A queried value as the type the query asks for, if the component's name is ambiguous. The
+/// row is skipped if the column is another type.
+fn resolve_value(
+    value: proc_macro2::TokenStream,
+    cast: Option<&Type>,
+    mutable: bool,
+) -> proc_macro2::TokenStream {
+    match cast {
+        Some(ty) if mutable => quote! { SameComponent::<#ty>::component_mut(&mut *#value)? },
+        Some(ty) => quote! { SameComponent::<#ty>::component_ref(&*#value)? },
+        None => value,
+    }
+}
+
+fn resolve_values(
+    fields: &[(&Ident, bool, bool)],
+    casts: &[Option<&Type>],
+    mutable: bool,
+    value: impl Fn(&(&Ident, bool, bool)) -> proc_macro2::TokenStream,
+) -> Vec<proc_macro2::TokenStream> {
+    fields
+        .iter()
+        .zip(casts)
+        .map(|(field, cast)| resolve_value(value(field), *cast, mutable && field.2))
+        .collect()
+}
+
+/// Checks the types of the ambiguous components of a collection before it's queried, the
+/// collection has none of the rows if they aren't the types the query asks for.
+struct QueryGuard<'a>(Option<&'a proc_macro2::TokenStream>);
+
+impl QueryGuard<'_> {
+    fn rows(&self, body: proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+        match self.0 {
+            Some(guard) => quote! {
+                let __rows = if #guard { Some({ #body }) } else { None };
+                __rows.into_iter().flatten()
+            },
+            None => body,
+        }
+    }
+
+    fn par_rows(&self, body: proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+        match self.0 {
+            Some(guard) => quote! {
+                let __rows = if #guard { Some({ #body }) } else { None };
+                __rows.into_par_iter().flatten()
+            },
+            None => body,
+        }
+    }
+
+    fn item(&self, body: proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+        match self.0 {
+            Some(guard) => quote! {
+                if !(#guard) {
+                    return None;
+                }
+                #body
+            },
+            None => body,
+        }
+    }
+
+    fn len(&self, body: proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+        match self.0 {
+            Some(guard) => quote! {
+                if !(#guard) {
+                    return 0;
+                }
+                #body
+            },
+            None => body,
+        }
+    }
+}
+
+/// Queries that walk the collection row by row and can skip rows: rows without a sparse
+/// component, and rows that don't pass `Added`/`Changed`, or `With`/`Without` of a sparse
+/// component. Also used to set the changed ticks of mutably queried `#[tracked]` components.
+/// `fields` are the queried columns, with whether they're sparse and mutable, and the type to
+/// cast them to if the name is ambiguous. `sparse_filters` are the sparse columns a row must
+/// have, or not have if false, `Without` only if the check passes. `guard` is checked before any
+/// row is.
+#[allow(clippy::too_many_arguments)]
+fn get_row_query_code(
+    items: &proc_macro2::TokenStream,
+    local_struct_name: &Ident,
+    collection_type_name: &Ident,
+    query_fields: &[(&Ident, bool, bool, Option<&Type>)],
+    filter_ticks: &[Ident],
+    changed_ticks: &[Ident],
+    sparse_filters: &[(Ident, bool, Option<proc_macro2::TokenStream>)],
+    guard: Option<&proc_macro2::TokenStream>,
+) -> proc_macro2::TokenStream {
+    let guard = QueryGuard(guard);
+    let fields: Vec<_> = query_fields
+        .iter()
+        .map(|(name, sparse, mutable, _)| (*name, *sparse, *mutable))
+        .collect();
+    let casts: Vec<_> = query_fields.iter().map(|(.., cast)| *cast).collect();
+    let any_mutable_local_fields = fields.iter().any(|(_, _, mutable)| *mutable);
+
+    // the entity column is always iterated, as __entity, to look up sparse components
+    let dense_names: Vec<_> = fields
+        .iter()
+        .filter(|(name, sparse, _)| !sparse && *name != "entity")
+        .map(|(name, _, _)| *name)
+        .collect();
+    let sparse_mut_names: Vec<_> = fields
+        .iter()
+        .filter(|(_, sparse, mutable)| *sparse && *mutable)
+        .map(|(name, _, _)| *name)
+        .collect();
+    let sparse_mut_iters: Vec<_> = sparse_mut_names
+        .iter()
+        .map(|name| quote::format_ident!("__{}_iter", name))
+        .collect();
+    let sparse_names: Vec<_> = fields
+        .iter()
+        .filter(|(_, sparse, _)| *sparse)
+        .map(|(name, _, _)| *name)
+        .collect();
+
+    // ticks are iterated next to the columns, the changed ones mutably
+    let mut mut_ticks: Vec<(&Ident, bool)> =
+        changed_ticks.iter().map(|tick| (tick, true)).collect();
+    for tick in filter_ticks {
+        if !mut_ticks.iter().any(|(name, _)| *name == tick) {
+            mut_ticks.push((tick, false));
+        }
+    }
+    let mut_tick_names: Vec<_> = mut_ticks.iter().map(|(name, _)| *name).collect();
+    let mut_tick_iters = mut_ticks.iter().map(|(name, mutable)| {
+        if *mutable {
+            quote! { self.#name.iter_mut() }
+        } else {
+            quote! { self.#name.iter() }
+        }
+    });
+
+    // izip! with a single iterator doesn't yield tuples
+    let row_pattern = |names: &[&Ident]| {
+        if names.is_empty() {
+            quote! { __entity }
+        } else {
+            quote! { (__entity, #(#names),*) }
+        }
+    };
+    let ref_row_pattern =
+        row_pattern(&[&dense_names[..], &filter_ticks.iter().collect::<Vec<_>>()].concat());
+    let mut_row_pattern = row_pattern(&[&dense_names[..], &mut_tick_names[..]].concat());
+    let row_filter = (!filter_ticks.is_empty()).then(|| {
+        quote! {
+            if #(*#filter_ticks != __tick)||* {
+                return None;
+            }
+        }
+    });
+    let with_names: Vec<_> = sparse_filters
+        .iter()
+        .filter(|(_, with, _)| *with)
+        .map(|(name, ..)| name)
+        .collect();
+    // a `Without` of an ambiguous name only excludes the rows if it's the same type
+    let without_checks = |column: &dyn Fn(&Ident) -> proc_macro2::TokenStream,
+                          id: proc_macro2::TokenStream| {
+        sparse_filters
+            .iter()
+            .filter(|(_, with, _)| !with)
+            .map(|(name, _, same)| {
+                let column = column(name);
+                match same {
+                    Some(same) => quote! { (#same && #column.contains(#id)) },
+                    None => quote! { #column.contains(#id) },
+                }
+            })
+            .collect::<Vec<_>>()
+    };
+    let without_rows = without_checks(&|name| quote! { #name }, quote! { __entity.id });
+    let without_self_rows = without_checks(&|name| quote! { self.#name }, quote! { __entity.id });
+    let without_entity = without_checks(&|name| quote! { self.#name }, quote! { entity.id });
+    // borrowed before the columns are iterated, the closures can't borrow `self`
+    let sparse_filter_bindings: Vec<_> = sparse_filters
+        .iter()
+        .map(|(name, ..)| quote! { let #name = &self.#name; })
+        .collect();
+    let sparse_row_filter = quote! {
+        #(
+            if !#with_names.contains(__entity.id) {
+                return None;
+            }
+        )*
+        #(
+            if #without_rows {
+                return None;
+            }
+        )*
+    };
+    // whether the row at __index passes, without borrowing the columns
+    let row_matches = quote! {
+        true
+            #(&& self.#sparse_names.contains(__entity.id))*
+            #(&& self.#filter_ticks[__index] == self.change_tick)*
+            #(&& self.#with_names.contains(__entity.id))*
+            #(&& !#without_self_rows)*
+    };
+
+    // without sparse fields or filters every row matches, and the index is the row
+    let rows_skipped =
+        !sparse_names.is_empty() || !filter_ticks.is_empty() || !sparse_filters.is_empty();
+    let at_entity = if rows_skipped {
+        quote! {
+            self.entity
+                .iter()
+                .enumerate()
+                .filter(|&(__index, __entity)| #row_matches)
+                .map(|(_, entity)| *entity)
+                .nth(index)?
+        }
+    } else {
+        quote! { *self.entity.get(index)? }
+    };
+    let at_code = if rows_skipped {
+        quote! { <Self as #items QueryFrom<'a, #local_struct_name<'a>>>::query_from(self).nth(index) }
+    } else {
+        quote! {
+            let entity = *self.entity.get(index)?;
+            <Self as #items QueryFrom<'a, #local_struct_name<'a>>>::get_from(self, entity)
+        }
+    };
+    let len_code = if rows_skipped {
+        quote! {
+            self.entity
+                .iter()
+                .enumerate()
+                .filter(|&(__index, __entity)| #row_matches)
+                .count()
+        }
+    } else {
+        quote! { self.entity.len() }
+    };
+
+    // the parallel queries borrow the columns they use, not the whole collection
+    let par_ref_names: Vec<_> = dense_names
+        .iter()
+        .chain(&sparse_names)
+        .copied()
+        .chain(filter_ticks)
+        .collect();
+    let par_row_filter = (!filter_ticks.is_empty()).then(|| {
+        quote! {
+            if #(#filter_ticks[__index] != __tick)||* {
+                return None;
+            }
+        }
+    });
+    let par_values = resolve_values(&fields, &casts, false, |(name, sparse, _)| {
+        if *name == "entity" {
+            quote! { __entity }
+        } else if *sparse {
+            quote! { #name.get(__entity.id)? }
+        } else {
+            quote! { &#name[__index] }
+        }
+    });
+    let par_tick_chunks = mut_ticks.iter().map(|(name, mutable)| {
+        if *mutable {
+            quote! { self.#name.par_chunks_mut(__chunk_size) }
+        } else {
+            quote! { self.#name.par_chunks(__chunk_size) }
+        }
+    });
+    let chunk_tick_iters = mut_ticks.iter().map(|(name, mutable)| {
+        if *mutable {
+            quote! { #name.iter_mut() }
+        } else {
+            quote! { #name.iter() }
+        }
+    });
+    let par_chunk_pattern = {
+        let names = [&dense_names[..], &mut_tick_names[..], &sparse_mut_names[..]].concat();
+        if names.is_empty() {
+            quote! { __entities }
+        } else {
+            quote! { (__entities, #(#names),*) }
+        }
+    };
+
+    let iter_values = resolve_values(&fields, &casts, false, |(name, sparse, mutable)| {
+        match (sparse, mutable) {
+            (false, _) if *name == "entity" => quote! { __entity },
+            (false, _) => quote! { #name },
+            (true, false) => quote! { self.#name.get(__entity.id)? },
+            (true, true) => quote! { #name? },
+        }
+    });
+    let iter_mut_values = resolve_values(&fields, &casts, true, |(name, sparse, mutable)| {
+        match (sparse, mutable) {
+            (false, _) if *name == "entity" => quote! { __entity },
+            (false, _) => quote! { #name },
+            (true, false) => quote! { #name.get(__entity.id)? },
+            (true, true) => quote! { #name? },
+        }
+    });
+    let sparse_ref_bindings = fields
+        .iter()
+        .filter(|(_, sparse, mutable)| *sparse && !mutable)
+        .map(|(name, _, _)| quote! { let #name = &self.#name; })
+        .collect::<Vec<_>>();
+    let get_values = resolve_values(&fields, &casts, false, |(name, sparse, _)| {
+        if *sparse {
+            quote! { self.#name.get(entity.id)? }
+        } else {
+            quote! { self.#name.get(index)? }
+        }
+    });
+    let get_mut_values = resolve_values(&fields, &casts, true, |(name, sparse, mutable)| {
+        match (sparse, mutable) {
+            (false, _) => quote! { self.#name.get_mut(index)? },
+            (true, false) => quote! { self.#name.get(entity.id)? },
+            (true, true) => quote! { self.#name.get_mut(entity.id)? },
+        }
+    });
+    let get_filter = (!filter_ticks.is_empty()).then(|| {
+        quote! {
+            if #(self.#filter_ticks[index] != self.change_tick)||* {
+                return None;
+            }
+        }
+    });
+    let get_sparse_filter = quote! {
+        #(
+            if !self.#with_names.contains(entity.id) {
+                return None;
+            }
+        )*
+        #(
+            if #without_entity {
+                return None;
+            }
+        )*
+    };
+
+    let query_from_body = guard.rows(quote! {
+        let __tick = self.change_tick;
+        #(#sparse_filter_bindings)*
+        izip!(self.entity.iter(), #(self.#dense_names.iter(),)* #(self.#filter_ticks.iter()),*)
+            .filter_map(move |#ref_row_pattern| {
+                #row_filter
+                #sparse_row_filter
+                Some(#local_struct_name(#(#iter_values),*))
+            })
+    });
+    let par_query_from_body = guard.par_rows(quote! {
+        // each row is looked up on its own, so the rows split like the dense columns
+        let __entities = &self.entity;
+        #(let #par_ref_names = &self.#par_ref_names;)*
+        #(#sparse_filter_bindings)*
+        let __tick = self.change_tick;
+        (0..__entities.len()).into_par_iter().filter_map(move |__index| {
+            let __entity = &__entities[__index];
+            #par_row_filter
+            #sparse_row_filter
+            Some(#local_struct_name(#(#par_values),*))
+        })
+    });
+    let get_from_body = guard.item(quote! {
+        let index = self.index_of(entity)?;
+        #get_filter
+        #get_sparse_filter
+        Some(#local_struct_name(#(#get_values),*))
+    });
+    let query_mut_from_body = guard.rows(quote! {
+        // walk mutable sparse components in the same order as the dense columns
+        #(self.#sparse_mut_names.sort_by_index(&self.index_lookup);)*
+        #(let mut #sparse_mut_iters = self.#sparse_mut_names.iter_mut().peekable();)*
+        #(#sparse_ref_bindings)*
+        #(#sparse_filter_bindings)*
+        let __tick = self.change_tick;
+
+        izip!(self.entity.iter(), #(self.#dense_names.iter_mut(),)* #(#mut_tick_iters),*)
+            .filter_map(move |#mut_row_pattern| {
+                #(
+                    let #sparse_mut_names = #sparse_mut_iters
+                        .next_if(|(id, _)| *id == __entity.id)
+                        .map(|(_, value)| value);
+                )*
+                #row_filter
+                #sparse_row_filter
+                let __item = #local_struct_name(#(#iter_mut_values),*);
+                #(*#changed_ticks = __tick;)*
+                Some(__item)
+            })
+    });
+    let par_query_mut_from_body = guard.par_rows(quote! {
+        // the rows are split into chunks, each walked like query_mut_from
+        let __rows = self.entity.len();
+        let __chunk_size = par_chunk_size(__rows);
+        #(
+            let #sparse_mut_names = self
+                .#sparse_mut_names
+                .chunks_by_index_mut(&self.index_lookup, __rows, __chunk_size);
+        )*
+        #(#sparse_ref_bindings)*
+        #(#sparse_filter_bindings)*
+        let __tick = self.change_tick;
+
+        izip_par!(
+            self.entity.par_chunks(__chunk_size),
+            #(self.#dense_names.par_chunks_mut(__chunk_size),)*
+            #(#par_tick_chunks,)*
+            #(#sparse_mut_names),*
+        )
+        .flat_map_iter(move |#par_chunk_pattern| {
+            #(let mut #sparse_mut_iters = #sparse_mut_names.peekable();)*
+            izip!(__entities.iter(), #(#dense_names.iter_mut(),)* #(#chunk_tick_iters),*)
+                .filter_map(move |#mut_row_pattern| {
+                    #(
+                        let #sparse_mut_names = #sparse_mut_iters
+                            .next_if(|(id, _)| *id == __entity.id)
+                            .map(|(_, value)| value);
+                    )*
+                    #row_filter
+                    #sparse_row_filter
+                    let __item = #local_struct_name(#(#iter_mut_values),*);
+                    #(*#changed_ticks = __tick;)*
+                    Some(__item)
+                })
+        })
+    });
+    let get_mut_from_body = guard.item(quote! {
+        let index = self.index_of(entity)?;
+        #get_filter
+        #get_sparse_filter
+        let __item = #local_struct_name(#(#get_mut_values),*);
+        #(self.#changed_ticks[index] = self.change_tick;)*
+        Some(__item)
+    });
+    let len_body = guard.len(quote! {
+        #len_code
+    });
+
+    let query_code = quote! {
+        impl<'a> #items QueryFrom<'a, #local_struct_name<'a>> for #items #collection_type_name {
+            fn query_from(&'a self) -> impl Iterator<Item = #local_struct_name<'a>> {
+                #query_from_body
+            }
+
+            fn par_query_from(&'a self) -> impl ParallelIterator<Item = #local_struct_name<'a>> {
+                #par_query_from_body
+            }
+
+            fn get_from(&'a self, entity: #items Entity) -> Option<#local_struct_name<'a>> {
+                #get_from_body
+            }
+
+            fn at(&'a self, index: usize) -> Option<#local_struct_name<'a>> {
+                #at_code
+            }
+        }
+    };
+
+    let query_mut_code = quote! {
+        impl<'a> #items QueryMutFrom<'a, #local_struct_name<'a>> for #items #collection_type_name {
+            fn query_mut_from(&'a mut self) -> impl Iterator<Item = #local_struct_name<'a>> {
+                #query_mut_from_body
+            }
+
+            fn par_query_mut_from(&'a mut self) -> impl ParallelIterator<Item = #local_struct_name<'a>> {
+                #par_query_mut_from_body
+            }
+
+            fn get_mut_from(&'a mut self, entity: #items Entity) -> Option<#local_struct_name<'a>> {
+                #get_mut_from_body
+            }
+
+            fn at_mut(&'a mut self, index: usize) -> Option<#local_struct_name<'a>> {
+                // only the row that is returned counts as changed
+                let entity = #at_entity;
+                <Self as #items QueryMutFrom<'a, #local_struct_name<'a>>>::get_mut_from(self, entity)
+            }
+        }
+    };
+
+    let len_from_code = quote! {
+        impl<'a> #items LenFrom<'a, #local_struct_name<'a>> for #items #collection_type_name {
+            fn len(&'a self) -> usize {
+                #len_body
+            }
+        }
+    };
+
+    if any_mutable_local_fields {
+        quote! {
+            #query_mut_code
+            #len_from_code
+        }
+    } else {
+        quote! {
+            #query_code
+            #query_mut_code
+            #len_from_code
+        }
+    }
+}
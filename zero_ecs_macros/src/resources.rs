@@ -0,0 +1,99 @@
+use convert_case::{Case, Casing};
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote, ToTokens};
+use syn::{Fields, Ident, ItemStruct, Type};
+
+use crate::events::first_type_argument;
+
+/// World fields that hold resources are named `__twr__{type}`.
+const RESOURCE_FIELD_PREFIX: &str = "__twr__";
+
+pub struct WorldResource {
+    pub field_name: Ident,
+    pub ty: Type,
+}
+
+pub fn type_string(ty: &Type) -> String {
+    ty.to_token_stream().to_string().replace(' ', "")
+}
+
+pub fn format_resource_field_name(ty: &Type) -> Ident {
+    let name: String = type_string(ty)
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '_' })
+        .collect();
+    let name = name.to_case(Case::Snake);
+    format_ident!("{}{}", RESOURCE_FIELD_PREFIX, name.trim_matches('_'))
+}
+
+/// Finds the resources in the tags of the world's entities, `PhantomData<T>` fields named like
+/// the world fields that hold them. Passed to `#[system]` and `#[system_for_each]`.
+pub fn get_world_resources(world_struct: &ItemStruct) -> Vec<WorldResource> {
+    let Fields::Named(fields) = &world_struct.fields else {
+        return vec![];
+    };
+    fields
+        .named
+        .iter()
+        .filter_map(|field| {
+            let field_name = field.ident.as_ref()?;
+            if !field_name.to_string().starts_with(RESOURCE_FIELD_PREFIX) {
+                return None;
+            }
+            Some(WorldResource {
+                field_name: field_name.clone(),
+                ty: first_type_argument(&field.ty)?.clone(),
+            })
+        })
+        .collect()
+}
+
+pub fn find_resource<'a>(resources: &'a [WorldResource], ty: &Type) -> Option<&'a WorldResource> {
+    let ty = type_string(ty);
+    resources
+        .iter()
+        .find(|resource| type_string(&resource.ty) == ty)
+}
+
+/// Generates `WorldResource` and the world's `resource` and `resource_mut` methods.
+pub fn get_resources(world: &Ident, resources: &[Type]) -> TokenStream {
+    let resource_implementations = resources.iter().map(|ty| {
+        let field_name = format_resource_field_name(ty);
+        quote! {
+            impl WorldResource<#ty> for #world {
+                fn resource(&self) -> &#ty {
+                    &self.#field_name
+                }
+                fn resource_mut(&mut self) -> &mut #ty {
+                    &mut self.#field_name
+                }
+            }
+        }
+    });
+
+    quote! {
+        pub trait WorldResource<T> {
+            fn resource(&self) -> &T;
+            fn resource_mut(&mut self) -> &mut T;
+        }
+
+        #(#resource_implementations)*
+
+        #[allow(dead_code)]
+        impl #world {
+            pub fn resource<T>(&self) -> &T
+            where
+                #world: WorldResource<T>,
+            {
+                WorldResource::<T>::resource(self)
+            }
+
+            pub fn resource_mut<T>(&mut self) -> &mut T
+            where
+                #world: WorldResource<T>,
+            {
+                WorldResource::<T>::resource_mut(self)
+            }
+        }
+    }
+}
@@ -0,0 +1,103 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use std::collections::BTreeMap;
+use syn::{Ident, Type};
+
+use crate::{
+    helpers::{format_collection_name, format_field_name},
+    query_impl::CollectionComponentField,
+};
+
+/// Generates `WorldSparse`, to add and remove `#[sparse]` components per entity. The component
+/// goes to the sparse column of the entity whose type it resolves to.
+pub fn get_sparse_components(
+    world: &Ident,
+    collection_component_fields: &[CollectionComponentField],
+) -> TokenStream {
+    // entity type -> its sparse columns
+    let mut collections: BTreeMap<&String, Vec<&CollectionComponentField>> = BTreeMap::new();
+    for field in collection_component_fields
+        .iter()
+        .filter(|field| field.sparse)
+    {
+        collections
+            .entry(&field.collection_name)
+            .or_default()
+            .push(field);
+    }
+
+    // a world without sparse components doesn't implement it, inserting one doesn't compile
+    if collections.is_empty() {
+        return quote! {};
+    }
+
+    let insert_arms = collections.iter().map(|(collection, fields)| {
+        let enum_name = format_ident!("{}", collection);
+        let collection_field_name = format_field_name(collection);
+        let collection_name = format_collection_name(collection);
+        let insert_columns = fields.iter().map(|field| {
+            let column = format_ident!("{}", field.field_name);
+            let field_type = syn::parse_str::<Type>(&field.field_type).expect("component type");
+            quote! {
+                let component = match SameComponent::<#field_type>::into_component(component) {
+                    Ok(component) => {
+                        collection.#column.insert(entity.id, component);
+                        return true;
+                    }
+                    Err(component) => component,
+                };
+            }
+        });
+        quote! {
+            EntityType::#enum_name => {
+                let collection: &mut #collection_name = &mut self.#collection_field_name;
+                if collection.index_of(entity).is_none() {
+                    return false;
+                }
+                #(#insert_columns)*
+                false
+            }
+        }
+    });
+
+    let remove_arms = collections.iter().map(|(collection, fields)| {
+        let enum_name = format_ident!("{}", collection);
+        let collection_field_name = format_field_name(collection);
+        let collection_name = format_collection_name(collection);
+        let remove_columns = fields.iter().map(|field| {
+            let column = format_ident!("{}", field.field_name);
+            quote! {
+                if let Some(column) = SameComponent::<SparseColumn<T>>::component_mut(&mut collection.#column) {
+                    return column.remove(entity.id);
+                }
+            }
+        });
+        quote! {
+            EntityType::#enum_name => {
+                let collection: &mut #collection_name = &mut self.#collection_field_name;
+                collection.index_of(entity)?;
+                #(#remove_columns)*
+                None
+            }
+        }
+    });
+
+    quote! {
+        impl<T: 'static> WorldSparse<T> for #world {
+            #[allow(unused_variables)]
+            fn insert(&mut self, entity: Entity, component: T) -> bool {
+                match entity.entity_type {
+                    #(#insert_arms)*
+                    _ => false,
+                }
+            }
+
+            fn remove(&mut self, entity: Entity) -> Option<T> {
+                match entity.entity_type {
+                    #(#remove_arms)*
+                    _ => None,
+                }
+            }
+        }
+    }
+}
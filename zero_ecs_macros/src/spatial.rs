@@ -0,0 +1,133 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{Fields, GenericArgument, ItemStruct, PathArguments, Type};
+
+use crate::{
+    helpers::{component_key, format_field_name},
+    query_impl::{is_ambiguous, CollectionComponentField},
+};
+
+/// The component the world keeps a spatial hash of, the `T` of its `__spatial: SpatialHash<T, _>`
+/// field. `None` if the world has no `spatial:`.
+fn spatial_component(world_struct: &ItemStruct) -> Option<&Type> {
+    let Fields::Named(fields) = &world_struct.fields else {
+        return None;
+    };
+    let field = fields.named.iter().find(|field| {
+        field
+            .ident
+            .as_ref()
+            .is_some_and(|ident| ident == "__spatial")
+    })?;
+    let Type::Path(ty) = &field.ty else {
+        return None;
+    };
+    let PathArguments::AngleBracketed(args) = &ty.path.segments.last()?.arguments else {
+        return None;
+    };
+    match args.args.first()? {
+        GenericArgument::Type(ty) => Some(ty),
+        _ => None,
+    }
+}
+
+/// Generates `rebuild_spatial_hash`, `query_radius` and `query_aabb` for worlds with a
+/// `spatial:` component.
+pub fn get_spatial_hash(
+    world_struct: &ItemStruct,
+    collection_component_fields: &[CollectionComponentField],
+) -> TokenStream {
+    let Some(component) = spatial_component(world_struct) else {
+        return quote! {};
+    };
+    let world = &world_struct.ident;
+    let key = component_key(component);
+    let ambiguous = is_ambiguous(collection_component_fields, &key);
+
+    let insert_calls = collection_component_fields
+        .iter()
+        .filter(|field| field.key == key)
+        .map(|field| {
+            let collection_field_name = format_field_name(&field.collection_name);
+            let column = format_ident!("{}", field.field_name);
+            // only the columns that resolve to the spatial component are inserted
+            let component = if ambiguous {
+                quote! {
+                    let Some(component) = SameComponent::<#component>::component_ref(component) else {
+                        continue;
+                    };
+                }
+            } else {
+                quote! {}
+            };
+            if field.sparse {
+                quote! {
+                    for (id, component) in self.#collection_field_name.#column.iter() {
+                        #component
+                        if let Some(index) = self.#collection_field_name.index_lookup[id] {
+                            self.__spatial
+                                .insert(self.#collection_field_name.entity[index], component);
+                        }
+                    }
+                }
+            } else {
+                quote! {
+                    for (entity, component) in self
+                        .#collection_field_name
+                        .entity
+                        .iter()
+                        .zip(&self.#collection_field_name.#column)
+                    {
+                        #component
+                        self.__spatial.insert(*entity, component);
+                    }
+                }
+            }
+        });
+
+    quote! {
+        #[allow(dead_code)]
+        impl #world {
+            /// Puts every entity with the spatial component in the spatial hash, at its current
+            /// position. Call it when positions have changed, usually once per frame.
+            pub fn rebuild_spatial_hash(&mut self, cell_size: f32) {
+                self.__spatial.reset(cell_size);
+                #(#insert_calls)*
+            }
+
+            /// The items of the query for the entities at most `radius` from `center`, as of the
+            /// last `rebuild_spatial_hash`.
+            pub fn query_radius<'a, T: 'a + Send, U>(
+                &'a self,
+                query: Query<T>,
+                center: [f32; 2],
+                radius: f32,
+            ) -> impl Iterator<Item = U> + 'a
+            where
+                #world: QueryFrom<'a, T>,
+                T: Into<U>,
+            {
+                self.__spatial
+                    .query_radius(center, radius)
+                    .filter_map(move |entity| query.get(self, entity).map(|e| e.into()))
+            }
+
+            /// The items of the query for the entities inside the box from `min` to `max`, as of
+            /// the last `rebuild_spatial_hash`.
+            pub fn query_aabb<'a, T: 'a + Send, U>(
+                &'a self,
+                query: Query<T>,
+                min: [f32; 2],
+                max: [f32; 2],
+            ) -> impl Iterator<Item = U> + 'a
+            where
+                #world: QueryFrom<'a, T>,
+                T: Into<U>,
+            {
+                self.__spatial
+                    .query_aabb(min, max)
+                    .filter_map(move |entity| query.get(self, entity).map(|e| e.into()))
+            }
+        }
+    }
+}
@@ -1,16 +1,30 @@
 use proc_macro::TokenStream;
 use quote::{format_ident, quote};
 use std::collections::HashSet;
-use syn::{FnArg, ItemFn, ItemStruct, Pat, PatIdent, PatType, Type};
+use syn::{FnArg, Ident, ItemFn, ItemStruct, Pat, PatIdent, PatType, Type};
 
-use crate::query_impl::get_collection_component_fields;
+use crate::{
+    events::{find_event_arg, get_world_events, EventArg},
+    helpers::{component_key, world_items_path},
+    query_impl::{get_collection_component_fields, QueryArgs},
+    resources::{find_resource, get_world_resources},
+};
 
-pub fn system_for_each(attr: TokenStream, item: TokenStream) -> TokenStream {
+pub fn system_for_each(attr: TokenStream, args: TokenStream, item: TokenStream) -> TokenStream {
     let foreign_struct = syn::parse_macro_input!(attr as ItemStruct);
+    // filters narrow down the entities the function runs for, e.g. only changed ones
+    let QueryArgs {
+        world: world_path,
+        filters,
+    } = syn::parse_macro_input!(args as QueryArgs);
+    let items = world_items_path(&world_path);
+    let world_resources = get_world_resources(&foreign_struct);
+    let world_events = get_world_events(&foreign_struct);
+    let collection_fields = get_collection_fields(&foreign_struct);
     let collection_component_fields = get_collection_component_fields(foreign_struct);
     let component_types: HashSet<_> = collection_component_fields
         .iter()
-        .map(|ccf| &ccf.field_type)
+        .map(|ccf| ccf.key.clone())
         .collect();
     let input_fn = syn::parse_macro_input!(item as ItemFn);
 
@@ -24,6 +38,10 @@ pub fn system_for_each(attr: TokenStream, item: TokenStream) -> TokenStream {
     let mut any_mutable_arguments = false;
     let mut resource_args = Vec::new();
     let mut resource_params = Vec::new();
+    // (name, field in the world, mutable)
+    let mut world_resource_args = Vec::new();
+    // (name, events field in the world, event type, writer)
+    let mut event_args = Vec::new();
     let mut all_args = Vec::new();
 
     for arg in &fn_sig.inputs {
@@ -43,24 +61,41 @@ pub fn system_for_each(attr: TokenStream, item: TokenStream) -> TokenStream {
                 if let Type::Reference(ty) = &**ty {
                     let is_mutable = ty.mutability.is_some();
 
-                    let Type::Path(type_path) = &*ty.elem else {
-                        panic!("failed to get elem path #[system_for_each]");
-                    };
+                    if let Some(event_arg) = find_event_arg(&world_events, &ty.elem) {
+                        let (event, writer) = match event_arg {
+                            EventArg::Reader(event) => (event, false),
+                            EventArg::Writer(event) => (event, true),
+                        };
+                        event_args.push((
+                            arg_ident.clone(),
+                            event.field_name.clone(),
+                            event.ty.clone(),
+                            writer,
+                        ));
+                        all_args.push(arg_ident.clone());
+                        continue;
+                    }
 
-                    let Some(type_path) = type_path.path.get_ident() else {
-                        panic!("failed to get type path #[system_for_each]")
-                    };
+                    if let Some(resource) = find_resource(&world_resources, &ty.elem) {
+                        world_resource_args.push((
+                            arg_ident.clone(),
+                            resource.field_name.clone(),
+                            is_mutable,
+                        ));
+                        all_args.push(arg_ident.clone());
+                        continue;
+                    }
 
-                    let type_path_str = type_path.to_string();
-                    let is_component = component_types.iter().any(|ty| **ty == type_path_str);
+                    let elem = &ty.elem;
+                    let is_component = component_types.contains(&component_key(elem));
 
                     if is_component {
                         call_args.push(arg_ident.clone());
                         if is_mutable {
                             any_mutable_arguments = true;
-                            query_fields.push(quote! {&'a mut #type_path});
+                            query_fields.push(quote! {&'a mut #elem});
                         } else {
-                            query_fields.push(quote! {&'a #type_path});
+                            query_fields.push(quote! {&'a #elem});
                         }
                     } else {
                         resource_args.push(arg_ident.clone());
@@ -79,27 +114,65 @@ pub fn system_for_each(attr: TokenStream, item: TokenStream) -> TokenStream {
     };
 
     let ext_name = format_ident!("__ext_{}", fn_name);
-    let mut_code = quote! {
-        self.with_query_mut(Query::<QueryObject>::new())
-            .iter_mut()
-            .for_each(|QueryObject(#(#call_args),*)| {
-                #fn_call
-            });
+    // the query runs on a view of the collections, so the other fields of the world, resources
+    // and events, can be borrowed next to it
+    let code = if any_mutable_arguments {
+        quote! {
+            let mut __entities = #items __WorldEntities {
+                #( #collection_fields: &mut self.__entities.#collection_fields, )*
+                __phantom: PhantomData,
+            };
+            <#items __WorldEntities<'_> as #items QueryMutFrom<'_, QueryObject<'_>>>::query_mut_from(&mut __entities)
+                .for_each(|QueryObject(#(#call_args),*)| {
+                    #fn_call
+                });
+        }
+    } else {
+        quote! {
+            let __entities = #items __WorldEntities {
+                #( #collection_fields: &mut self.__entities.#collection_fields, )*
+                __phantom: PhantomData,
+            };
+            <#items __WorldEntities<'_> as #items QueryFrom<'_, QueryObject<'_>>>::query_from(&__entities)
+                .for_each(|QueryObject(#(#call_args),*)| {
+                    #fn_call
+                });
+        }
     };
 
-    let ref_code = quote! {
-        self.with_query(Query::<QueryObject>::new())
+    let resources_setup_code = world_resource_args
+        .iter()
+        .map(|(name, field_name, mutable)| {
+            if *mutable {
+                quote! { let #name = &mut self.#field_name; }
+            } else {
+                quote! { let #name = &self.#field_name; }
+            }
+        });
+
+    // events are sent when all entities are done
+    let events_setup_code = event_args.iter().map(|(name, field_name, ty, writer)| {
+        let value_name = format_ident!("__events_{}", name);
+        if *writer {
+            quote! {
+                let mut #value_name = EventWriter::<#ty>::new();
+                let #name = &mut #value_name;
+            }
+        } else {
+            quote! {
+                let #value_name = EventReader::new(self.#field_name.read());
+                let #name = &#value_name;
+            }
+        }
+    });
+    let events_restore_code =
+        event_args
             .iter()
-            .for_each(|QueryObject(#(#call_args),*)| {
-                #fn_call
+            .filter(|(_, _, _, writer)| *writer)
+            .map(|(name, field_name, _, _)| {
+                let value_name = format_ident!("__events_{}", name);
+                quote! { self.#field_name.send_batch(#value_name); }
             });
-    };
-
-    let code = if any_mutable_arguments {
-        mut_code
-    } else {
-        ref_code
-    };
 
     let resource_args_params = if resource_args.is_empty() {
         quote! {}
@@ -114,15 +187,36 @@ pub fn system_for_each(attr: TokenStream, item: TokenStream) -> TokenStream {
         }
 
         #[ext(name = #ext_name)]
-        pub impl World {
+        pub impl #world_path {
             fn #fn_name(&mut self, #resource_args_params) {
-                #[query(World)]
+                #[query(#world_path #(, #filters)*)]
                 struct QueryObject<'a>(#(#query_fields),*);
 
+                #(#resources_setup_code)*
+                #(#events_setup_code)*
+
                 #code
+
+                #(#events_restore_code)*
             }
         }
     };
 
     expanded.into()
 }
+
+/// The fields of the world that hold entity collections, `__{entity}: __{Entity}Collection`.
+fn get_collection_fields(world_struct: &ItemStruct) -> Vec<Ident> {
+    world_struct
+        .fields
+        .iter()
+        .filter(|field| match &field.ty {
+            Type::Path(type_path) => type_path.path.segments.last().is_some_and(|segment| {
+                let name = segment.ident.to_string();
+                name.starts_with("__") && name.ends_with("Collection")
+            }),
+            _ => false,
+        })
+        .filter_map(|field| field.ident.clone())
+        .collect()
+}
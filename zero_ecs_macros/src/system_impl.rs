@@ -2,11 +2,25 @@ use intehan_util_dump::dump;
 use proc_macro::TokenStream;
 use quote::{format_ident, quote};
 use syn::{
-    parse_macro_input, FnArg, GenericArgument, Ident, ItemFn, Pat, PatIdent, PatType,
-    PathArguments, Type,
+    parse_macro_input, FnArg, GenericArgument, Ident, ItemFn, ItemStruct, Pat, PatIdent, PatType,
+    Path, PathArguments, Type,
 };
 
-pub fn system(_attr: TokenStream, item: TokenStream) -> TokenStream {
+use crate::{
+    events::{find_event_arg, get_world_events, EventArg, WorldEvent},
+    helpers::{
+        format_entity_name, format_field_name, format_world_entities_name, world_items_path,
+    },
+    query_impl::QueryFilter,
+    resources::{find_resource, get_world_resources, WorldResource},
+};
+
+pub fn system(attr: TokenStream, world_path: TokenStream, item: TokenStream) -> TokenStream {
+    let world_struct = parse_macro_input!(attr as ItemStruct);
+    let world_path = parse_macro_input!(world_path as Path);
+    let items = world_items_path(&world_path);
+    let world_resources = get_world_resources(&world_struct);
+    let world_events = get_world_events(&world_struct);
     let input_fn = parse_macro_input!(item as ItemFn);
 
     let fn_vis = &input_fn.vis;
@@ -14,7 +28,14 @@ pub fn system(_attr: TokenStream, item: TokenStream) -> TokenStream {
     let fn_name = &fn_sig.ident;
     let fn_block = &input_fn.block;
 
-    let system_args: Vec<SystemArg> = collect_fn_sig(&fn_sig);
+    // the world argument is written with the name of the world, its tokens are its entities'
+    let world_name = &world_path
+        .segments
+        .last()
+        .expect("the world path should have a last segment")
+        .ident;
+    let mut system_args: Vec<SystemArg> =
+        collect_fn_sig(&fn_sig, world_name, &world_resources, &world_events);
     assert_eq!(
         system_args
             .iter()
@@ -24,6 +45,22 @@ pub fn system(_attr: TokenStream, item: TokenStream) -> TokenStream {
         "#[system] must have exactly ONE &World or &mut World argument"
     );
 
+    let world_mutable = system_args
+        .iter()
+        .any(|arg| matches!(arg, SystemArg::World(ArgWorld { mutable: true, .. })));
+    // resources and events are fields of the world next to its entities, a system that borrows
+    // a resource or reads events gets the entities and those are borrowed apart from them
+    let entities_only = system_args
+        .iter()
+        .any(|arg| matches!(arg, SystemArg::WorldResource(_) | SystemArg::EventReader(_)));
+    for arg in &mut system_args {
+        match arg {
+            SystemArg::World(world) => world.entities_only = entities_only,
+            SystemArg::Removed(removed) => removed.shared = !world_mutable,
+            _ => {}
+        }
+    }
+
     let query_args: Vec<_> = system_args
         .iter()
         .filter_map(|arg| {
@@ -45,25 +82,26 @@ pub fn system(_attr: TokenStream, item: TokenStream) -> TokenStream {
                 .fields
                 .iter()
                 .map(|field| {
-                    let ty_ident = format_ident!("{}", field.ty);
+                    let ty = &field.ty;
 
                     if field.mutable {
                         quote! {
-                            &'a mut #ty_ident
+                            &'a mut #ty
                         }
                     } else {
                         quote! {
-                            &'a #ty_ident
+                            &'a #ty
                         }
                     }
                 })
                 .collect();
 
+            let filters = &arg.filters;
             let code = quote! {
-                #[query(World)]
+                #[query(#world_path #(, #filters)*)]
                 struct #struct_name<'a>(#(#components),*);
 
-                let #arg_name = Query::<#struct_name>::new();
+                let #arg_name = #items Query::<#struct_name>::new();
             };
 
             code
@@ -72,18 +110,76 @@ pub fn system(_attr: TokenStream, item: TokenStream) -> TokenStream {
 
     let out_fn_args: Vec<_> = system_args
         .iter()
-        .filter_map(|arg| arg.get_arg_code())
+        .filter_map(|arg| arg.get_arg_code(&world_path, &items))
         .collect();
     let resource_fn_args: Vec<_> = system_args
         .iter()
         .filter(|arg| matches!(arg, SystemArg::Resource(_)))
-        .filter_map(|arg| arg.get_arg_code())
+        .filter_map(|arg| arg.get_arg_code(&world_path, &items))
         .collect();
     let call_fn_code: Vec<_> = system_args
         .iter()
         .filter_map(|arg| arg.get_call_code())
         .collect();
 
+    let commands_args: Vec<_> = system_args
+        .iter()
+        .filter_map(|arg| {
+            if let SystemArg::Commands(commands) = arg {
+                Some(commands)
+            } else {
+                None
+            }
+        })
+        .collect();
+    let commands_setup_code = commands_args.iter().map(|arg| {
+        let name = &arg.name_ident;
+        if arg.sync {
+            quote! { let #name = #items SyncCommands::new(); }
+        } else {
+            quote! { let mut #name = #items Commands::new(); }
+        }
+    });
+    let commands_apply_code = commands_args.iter().map(|arg| {
+        let name = &arg.name_ident;
+        quote! { self.apply(#name); }
+    });
+
+    let resource_args: Vec<_> = system_args
+        .iter()
+        .filter_map(|arg| {
+            if let SystemArg::WorldResource(resource) = arg {
+                Some(resource)
+            } else {
+                None
+            }
+        })
+        .collect();
+    let resources_setup_code = resource_args.iter().map(|arg| arg.get_setup_code());
+
+    let removed_args: Vec<_> = system_args
+        .iter()
+        .filter_map(|arg| {
+            if let SystemArg::Removed(removed) = arg {
+                Some(removed)
+            } else {
+                None
+            }
+        })
+        .collect();
+    let removed_setup_code = removed_args.iter().map(|arg| arg.get_setup_code());
+    let removed_restore_code = removed_args.iter().map(|arg| arg.get_restore_code());
+
+    let events_setup_code = system_args.iter().filter_map(|arg| match arg {
+        SystemArg::EventReader(reader) => Some(reader.get_setup_code()),
+        SystemArg::EventWriter(writer) => Some(writer.get_setup_code()),
+        _ => None,
+    });
+    let events_restore_code = system_args.iter().filter_map(|arg| match arg {
+        SystemArg::EventWriter(writer) => Some(writer.get_restore_code()),
+        _ => None,
+    });
+
     let ext_name = format_ident!("__ext_{}", fn_name);
     let expanded = quote! {
         #fn_vis fn #fn_name(#(#out_fn_args),*) {
@@ -94,9 +190,16 @@ pub fn system(_attr: TokenStream, item: TokenStream) -> TokenStream {
 
 
         #[ext(name = #ext_name)]
-        pub impl World {
+        pub impl #world_path {
             fn #fn_name(&mut self, #(#resource_fn_args),*) {
+                #(#resources_setup_code)*
+                #(#removed_setup_code)*
+                #(#events_setup_code)*
+                #(#commands_setup_code)*
                 #fn_name(#(#call_fn_code),*);
+                #(#removed_restore_code)*
+                #(#events_restore_code)*
+                #(#commands_apply_code)*
             }
         }
     };
@@ -107,7 +210,7 @@ pub fn system(_attr: TokenStream, item: TokenStream) -> TokenStream {
 #[derive(Debug)]
 struct ArgQueryField {
     mutable: bool,
-    ty: String,
+    ty: Type,
 }
 
 #[derive(Debug)]
@@ -115,13 +218,15 @@ struct ArgQuery {
     name: String,
     name_ident: Ident,
     fields: Vec<ArgQueryField>,
+    /// From the second argument, `Query<(&A, &mut B), Changed<A>>`.
+    filters: Vec<QueryFilter>,
 }
 
 #[derive(Debug)]
 struct ArgResource {
     name_ident: Ident,
     mutable: bool,
-    ty: String,
+    ty: Type,
     by_ref: bool,
 }
 
@@ -129,6 +234,106 @@ struct ArgResource {
 struct ArgWorld {
     name_ident: Ident,
     mutable: bool,
+    /// Passed the world's entities, without its resources and events.
+    entities_only: bool,
+}
+
+#[derive(Debug)]
+struct ArgCommands {
+    name_ident: Ident,
+    sync: bool,
+}
+
+/// A resource stored in the world, borrowed by the system instead of passed by the caller.
+#[derive(Debug)]
+struct ArgWorldResource {
+    name_ident: Ident,
+    mutable: bool,
+    ty: Type,
+    field_name: Ident,
+}
+
+impl ArgWorldResource {
+    fn get_setup_code(&self) -> proc_macro2::TokenStream {
+        let name = &self.name_ident;
+        let field_name = &self.field_name;
+        if self.mutable {
+            quote! { let #name = &mut self.#field_name; }
+        } else {
+            quote! { let #name = &self.#field_name; }
+        }
+    }
+}
+
+/// `Removed<T>`, the entities of type `T` destroyed since the last `clear_trackers`.
+#[derive(Debug)]
+struct ArgRemoved {
+    name_ident: Ident,
+    ty: Type,
+    field_name: Ident,
+    shared: bool,
+}
+
+impl ArgRemoved {
+    fn get_setup_code(&self) -> proc_macro2::TokenStream {
+        let name = &self.name_ident;
+        let field_name = &self.field_name;
+        if self.shared {
+            quote! { let #name = self.__entities.#field_name.removed.as_slice(); }
+        } else {
+            // taken out of the world while the system runs, so the world can be borrowed too
+            quote! { let #name = std::mem::take(&mut self.__entities.#field_name.removed); }
+        }
+    }
+
+    fn get_restore_code(&self) -> proc_macro2::TokenStream {
+        let name = &self.name_ident;
+        let field_name = &self.field_name;
+        if self.shared {
+            quote! {}
+        } else {
+            // entities destroyed by the system come after the ones it was given
+            quote! { self.__entities.#field_name.removed.splice(0..0, #name); }
+        }
+    }
+}
+
+/// `&EventReader<T>`, the events of type `T` sent before the last `update_events`.
+#[derive(Debug)]
+struct ArgEventReader {
+    name_ident: Ident,
+    ty: Type,
+    field_name: Ident,
+}
+
+impl ArgEventReader {
+    fn get_setup_code(&self) -> proc_macro2::TokenStream {
+        let name = &self.name_ident;
+        let field_name = &self.field_name;
+        quote! { let #name = EventReader::new(self.#field_name.read()); }
+    }
+}
+
+/// `&mut EventWriter<T>`, sends events of type `T` when the system returns.
+#[derive(Debug)]
+struct ArgEventWriter {
+    name_ident: Ident,
+    ty: Type,
+    field_name: Ident,
+}
+
+impl ArgEventWriter {
+    fn get_setup_code(&self) -> proc_macro2::TokenStream {
+        let name = &self.name_ident;
+        let ty = &self.ty;
+        quote! { let mut #name = EventWriter::<#ty>::new(); }
+    }
+
+    fn get_restore_code(&self) -> proc_macro2::TokenStream {
+        let name = &self.name_ident;
+        let field_name = &self.field_name;
+        quote! { self.#field_name.send_batch(#name); }
+    }
 }
 
 #[derive(Debug)]
@@ -136,22 +341,37 @@ enum SystemArg {
     World(ArgWorld),
     Query(ArgQuery),
     Resource(ArgResource),
+    WorldResource(ArgWorldResource),
+    Commands(ArgCommands),
+    Removed(ArgRemoved),
+    EventReader(ArgEventReader),
+    EventWriter(ArgEventWriter),
 }
 
 impl SystemArg {
-    fn get_arg_code(&self) -> Option<proc_macro2::TokenStream> {
+    fn get_arg_code(
+        &self,
+        world_path: &Path,
+        items: &proc_macro2::TokenStream,
+    ) -> Option<proc_macro2::TokenStream> {
         match self {
             SystemArg::World(arg_world) => {
                 let name = &arg_world.name_ident;
+                let world = if arg_world.entities_only {
+                    let entities = format_world_entities_name(&world_path.segments.last()?.ident);
+                    quote! { #items #entities }
+                } else {
+                    quote! { #world_path }
+                };
                 Some(if arg_world.mutable {
-                    quote! { #name: &mut World }
+                    quote! { #name: &mut #world }
                 } else {
-                    quote! { #name: &World }
+                    quote! { #name: &#world }
                 })
             }
             SystemArg::Resource(arg_resource) => {
                 let name = &arg_resource.name_ident;
-                let ty = format_ident!("{}", arg_resource.ty);
+                let ty = &arg_resource.ty;
                 if arg_resource.by_ref {
                     Some(if arg_resource.mutable {
                         quote! { #name: &mut #ty }
@@ -166,6 +386,39 @@ impl SystemArg {
                     })
                 }
             }
+            SystemArg::WorldResource(arg_resource) => {
+                let name = &arg_resource.name_ident;
+                let ty = &arg_resource.ty;
+                Some(if arg_resource.mutable {
+                    quote! { #name: &mut #ty }
+                } else {
+                    quote! { #name: &#ty }
+                })
+            }
+            SystemArg::Commands(arg_commands) => {
+                let name = &arg_commands.name_ident;
+                Some(if arg_commands.sync {
+                    quote! { #name: &#items SyncCommands }
+                } else {
+                    quote! { #name: &mut #items Commands }
+                })
+            }
+            SystemArg::Removed(arg_removed) => {
+                let name = &arg_removed.name_ident;
+                let ty = &arg_removed.ty;
+                let entities = format_world_entities_name(&world_path.segments.last()?.ident);
+                Some(quote! { #name: &[<#items #entities as #items WorldRemoved<#ty>>::Removed] })
+            }
+            SystemArg::EventReader(arg_reader) => {
+                let name = &arg_reader.name_ident;
+                let ty = &arg_reader.ty;
+                Some(quote! { #name: &EventReader<'_, #ty> })
+            }
+            SystemArg::EventWriter(arg_writer) => {
+                let name = &arg_writer.name_ident;
+                let ty = &arg_writer.ty;
+                Some(quote! { #name: &mut EventWriter<#ty> })
+            }
             _ => None,
         }
     }
@@ -173,17 +426,56 @@ impl SystemArg {
     // for when calling fn (self, foo, bar)
     fn get_call_code(&self) -> Option<proc_macro2::TokenStream> {
         match self {
-            SystemArg::World(_) => Some(quote! { self }),
+            SystemArg::World(arg_world) => {
+                Some(match (arg_world.entities_only, arg_world.mutable) {
+                    (true, true) => quote! { &mut self.__entities },
+                    (true, false) => quote! { &self.__entities },
+                    (false, _) => quote! { self },
+                })
+            }
             SystemArg::Resource(arg_resource) => {
                 let name = &arg_resource.name_ident;
                 Some(quote! { #name })
             }
+            SystemArg::WorldResource(arg_resource) => {
+                let name = &arg_resource.name_ident;
+                Some(quote! { #name })
+            }
+            SystemArg::Commands(arg_commands) => {
+                let name = &arg_commands.name_ident;
+                Some(if arg_commands.sync {
+                    quote! { &#name }
+                } else {
+                    quote! { &mut #name }
+                })
+            }
+            SystemArg::Removed(arg_removed) => {
+                let name = &arg_removed.name_ident;
+                Some(if arg_removed.shared {
+                    quote! { #name }
+                } else {
+                    quote! { &#name }
+                })
+            }
+            SystemArg::EventReader(arg_reader) => {
+                let name = &arg_reader.name_ident;
+                Some(quote! { &#name })
+            }
+            SystemArg::EventWriter(arg_writer) => {
+                let name = &arg_writer.name_ident;
+                Some(quote! { &mut #name })
+            }
             _ => None,
         }
     }
 }
 
-fn collect_fn_sig(fn_sig: &&syn::Signature) -> Vec<SystemArg> {
+fn collect_fn_sig(
+    fn_sig: &&syn::Signature,
+    world: &Ident,
+    world_resources: &[WorldResource],
+    world_events: &[WorldEvent],
+) -> Vec<SystemArg> {
     let mut system_args = vec![];
 
     for arg in &fn_sig.inputs {
@@ -206,26 +498,53 @@ fn collect_fn_sig(fn_sig: &&syn::Signature) -> Vec<SystemArg> {
                         let Type::Path(type_path) = &*ty.elem else {
                             panic!("Reference: failed to get elem path #[system]");
                         };
-                        let is_world = type_path
+                        let last_ident = &type_path
                             .path
                             .segments
                             .last()
-                            .map(|s| s.ident == "World")
-                            .expect("path should have one last segment");
-                        let Some(type_path) = type_path.path.get_ident() else {
-                            panic!("Reference: failed to get type path #[system]")
-                        };
+                            .expect("path should have one last segment")
+                            .ident;
+                        let is_world = last_ident == world;
+                        let is_commands = last_ident == "Commands" || last_ident == "SyncCommands";
 
                         if is_world {
                             let arg = ArgWorld {
                                 mutable: is_mutable,
                                 name_ident: arg_ident,
+                                entities_only: false,
                             };
                             system_args.push(SystemArg::World(arg));
+                        } else if is_commands {
+                            let arg = ArgCommands {
+                                name_ident: arg_ident,
+                                sync: last_ident == "SyncCommands",
+                            };
+                            system_args.push(SystemArg::Commands(arg));
+                        } else if let Some(event_arg) = find_event_arg(world_events, &ty.elem) {
+                            system_args.push(match event_arg {
+                                EventArg::Reader(event) => SystemArg::EventReader(ArgEventReader {
+                                    name_ident: arg_ident,
+                                    ty: event.ty.clone(),
+                                    field_name: event.field_name.clone(),
+                                }),
+                                EventArg::Writer(event) => SystemArg::EventWriter(ArgEventWriter {
+                                    name_ident: arg_ident,
+                                    ty: event.ty.clone(),
+                                    field_name: event.field_name.clone(),
+                                }),
+                            });
+                        } else if let Some(resource) = find_resource(world_resources, &ty.elem) {
+                            let arg = ArgWorldResource {
+                                name_ident: arg_ident,
+                                mutable: is_mutable,
+                                ty: (*ty.elem).clone(),
+                                field_name: resource.field_name.clone(),
+                            };
+                            system_args.push(SystemArg::WorldResource(arg));
                         } else {
                             let arg = ArgResource {
                                 mutable: is_mutable,
-                                ty: type_path.to_string(),
+                                ty: (*ty.elem).clone(),
                                 by_ref: true,
                                 name_ident: arg_ident,
                             };
@@ -249,6 +568,14 @@ fn collect_fn_sig(fn_sig: &&syn::Signature) -> Vec<SystemArg> {
                                 .args
                                 .first()
                                 .expect("#[system] args args should not be empty");
+                            let filters = match args.args.iter().nth(1) {
+                                Some(GenericArgument::Type(ty)) => QueryFilter::from_types(ty)
+                                    .unwrap_or_else(|err| panic!("#[system] {}", err)),
+                                Some(_) => panic!(
+                                    "#[system] Expected filters as the second argument of Query"
+                                ),
+                                None => vec![],
+                            };
 
                             match arg {
                                 GenericArgument::Type(Type::Tuple(tuple)) => {
@@ -259,15 +586,10 @@ fn collect_fn_sig(fn_sig: &&syn::Signature) -> Vec<SystemArg> {
                                             panic!("#[system] Expected a reference type inside the tuple, but found something else");
                                         };
                                         let is_mutable = elem.mutability.is_some();
-                                        let Type::Path(elem_path) = &*elem.elem else {
-                                            panic!("#[system] Expected a path type inside the reference, but found something else");
-                                        };
-                                        let elem_ident =
-                                            &elem_path.path.segments.first().unwrap().ident;
 
                                         arg_query_fields.push(ArgQueryField {
                                             mutable: is_mutable,
-                                            ty: elem_ident.to_string(),
+                                            ty: (*elem.elem).clone(),
                                         });
                                     }
 
@@ -275,23 +597,20 @@ fn collect_fn_sig(fn_sig: &&syn::Signature) -> Vec<SystemArg> {
                                         name: arg_ident.to_string(),
                                         name_ident: arg_ident,
                                         fields: arg_query_fields,
+                                        filters,
                                     };
                                     system_args.push(SystemArg::Query(arg_query));
                                 }
                                 GenericArgument::Type(Type::Reference(elem)) => {
                                     let is_mutable = elem.mutability.is_some();
-                                    let Type::Path(elem_path) = &*elem.elem else {
-                                        panic!("#[system] Expected a path type inside the reference, but found something else");
-                                    };
-                                    let elem_ident =
-                                        &elem_path.path.segments.first().unwrap().ident;
                                     let arg_query = ArgQuery {
                                         name: arg_ident.to_string(),
                                         name_ident: arg_ident,
                                         fields: vec![ArgQueryField {
                                             mutable: is_mutable,
-                                            ty: elem_ident.to_string(),
+                                            ty: (*elem.elem).clone(),
                                         }],
+                                        filters,
                                     };
                                     system_args.push(SystemArg::Query(arg_query))
                                 }
@@ -301,18 +620,14 @@ fn collect_fn_sig(fn_sig: &&syn::Signature) -> Vec<SystemArg> {
                                     };
 
                                     let is_mutable = elem.mutability.is_some();
-                                    let Type::Path(elem_path) = &*elem.elem else {
-                                        panic!("#[system] Expected a path type inside the reference, but found something else");
-                                    };
-                                    let elem_ident =
-                                        &elem_path.path.segments.first().unwrap().ident;
                                     let arg_query = ArgQuery {
                                         name: arg_ident.to_string(),
                                         name_ident: arg_ident,
                                         fields: vec![ArgQueryField {
                                             mutable: is_mutable,
-                                            ty: elem_ident.to_string(),
+                                            ty: (*elem.elem).clone(),
                                         }],
+                                        filters,
                                     };
                                     system_args.push(SystemArg::Query(arg_query))
                                 }
@@ -326,13 +641,27 @@ fn collect_fn_sig(fn_sig: &&syn::Signature) -> Vec<SystemArg> {
                                     );
                                 }
                             }
-                        } else {
-                            let Some(ty_ident) = ty.path.get_ident() else {
-                                panic!("#[system] failed tog get_ident ty.path");
+                        } else if outer_ident == "Removed" {
+                            let Some(GenericArgument::Type(Type::Path(entity_type))) =
+                                (match &segment.arguments {
+                                    PathArguments::AngleBracketed(args) => args.args.first(),
+                                    _ => None,
+                                })
+                            else {
+                                panic!("#[system] Expected an entity type in Removed");
                             };
+                            // the collection is named after the entity type as `ecs_world!` names it
+                            let field_name = format_field_name(&format_entity_name(entity_type));
+                            system_args.push(SystemArg::Removed(ArgRemoved {
+                                name_ident: arg_ident,
+                                ty: Type::Path(entity_type.clone()),
+                                field_name,
+                                shared: false,
+                            }));
+                        } else {
                             let arg = ArgResource {
                                 mutable: false,
-                                ty: ty_ident.to_string(),
+                                ty: Type::Path(ty.clone()),
                                 by_ref: false,
                                 name_ident: arg_ident,
                             };
@@ -0,0 +1,153 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::Ident;
+
+use crate::helpers::{format_collection_name, format_field_name};
+
+/// Generates `TypedEntity<T>`, the handle `create` returns, and the traits that let the
+/// world's methods take either a `TypedEntity<T>` or a plain `Entity`.
+pub fn get_typed_entity(world: &Ident, types: &[&Ident]) -> TokenStream {
+    let handle_implementations = types.iter().map(|ty| {
+        let collection_field_name = format_field_name(ty);
+        let collection_name = format_collection_name(ty);
+        quote! {
+            impl EntityHandle<#ty> for Entity {
+                fn typed(self) -> Option<TypedEntity<#ty>> {
+                    if self.entity_type == EntityType::#ty {
+                        Some(TypedEntity::new(self))
+                    } else {
+                        None
+                    }
+                }
+            }
+
+            impl<'a, T: 'a + Send> EntityGet<'a, T> for TypedEntity<#ty>
+            where
+                #collection_name: QueryFrom<'a, T>,
+            {
+                fn get_in(self, world: &'a #world) -> Option<T> {
+                    QueryFrom::<T>::get_from(&world.#collection_field_name, self.entity)
+                }
+            }
+
+            impl<'a, T: 'a + Send> EntityGetMut<'a, T> for TypedEntity<#ty>
+            where
+                #collection_name: QueryMutFrom<'a, T>,
+            {
+                fn get_mut_in(self, world: &'a mut #world) -> Option<T> {
+                    QueryMutFrom::<T>::get_mut_from(&mut world.#collection_field_name, self.entity)
+                }
+            }
+        }
+    });
+
+    quote! {
+        /// Handle to an entity of type `T`, returned by `create`. Converts into `Entity`.
+        /// Methods that take one know which collection to look in, and passing a handle of
+        /// the wrong type is a compile error.
+        pub struct TypedEntity<T> {
+            entity: Entity,
+            phantom: PhantomData<fn() -> T>,
+        }
+
+        #[allow(dead_code)]
+        impl<T> TypedEntity<T> {
+            fn new(entity: Entity) -> Self {
+                TypedEntity {
+                    entity,
+                    phantom: PhantomData,
+                }
+            }
+
+            pub fn entity(self) -> Entity {
+                self.entity
+            }
+
+            pub fn id(self) -> usize {
+                self.entity.id
+            }
+
+            pub fn generation(self) -> u32 {
+                self.entity.generation
+            }
+        }
+
+        impl<T> Copy for TypedEntity<T> {}
+        impl<T> Clone for TypedEntity<T> {
+            fn clone(&self) -> Self {
+                *self
+            }
+        }
+        impl<T> PartialEq for TypedEntity<T> {
+            fn eq(&self, other: &Self) -> bool {
+                self.entity == other.entity
+            }
+        }
+        impl<T> Eq for TypedEntity<T> {}
+        impl<T> PartialEq<Entity> for TypedEntity<T> {
+            fn eq(&self, other: &Entity) -> bool {
+                self.entity == *other
+            }
+        }
+        impl<T> PartialEq<TypedEntity<T>> for Entity {
+            fn eq(&self, other: &TypedEntity<T>) -> bool {
+                *self == other.entity
+            }
+        }
+        impl<T> std::hash::Hash for TypedEntity<T> {
+            fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+                self.entity.hash(state);
+            }
+        }
+        impl<T> std::fmt::Debug for TypedEntity<T> {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                f.debug_tuple("TypedEntity").field(&self.entity).finish()
+            }
+        }
+        impl<T> From<TypedEntity<T>> for Entity {
+            fn from(entity: TypedEntity<T>) -> Self {
+                entity.entity
+            }
+        }
+
+        /// A handle to an entity of type `T`. `Entity` checks its type at runtime,
+        /// `TypedEntity<T>` doesn't have to.
+        pub trait EntityHandle<T>: Copy + Into<Entity> {
+            fn typed(self) -> Option<TypedEntity<T>>;
+        }
+
+        impl<T> EntityHandle<T> for TypedEntity<T> {
+            fn typed(self) -> Option<TypedEntity<T>> {
+                Some(self)
+            }
+        }
+
+        /// Handles that can be used to get the query `T` of an entity.
+        pub trait EntityGet<'a, T> {
+            fn get_in(self, world: &'a #world) -> Option<T>;
+        }
+        pub trait EntityGetMut<'a, T> {
+            fn get_mut_in(self, world: &'a mut #world) -> Option<T>;
+        }
+
+        impl<'a, T: 'a + Send> EntityGet<'a, T> for Entity
+        where
+            #world: QueryFrom<'a, T>,
+        {
+            fn get_in(self, world: &'a #world) -> Option<T> {
+                QueryFrom::<T>::get_from(world, self)
+            }
+        }
+
+        impl<'a, T: 'a + Send> EntityGetMut<'a, T> for Entity
+        where
+            #world: QueryMutFrom<'a, T>,
+        {
+            fn get_mut_in(self, world: &'a mut #world) -> Option<T> {
+                QueryMutFrom::<T>::get_mut_from(world, self)
+            }
+        }
+
+        #(#handle_implementations)*
+    }
+}
@@ -1,15 +1,72 @@
+use std::collections::HashMap;
+
 use proc_macro::TokenStream;
-use quote::{format_ident, quote};
-use syn::{spanned::Spanned, Error, Fields, ItemStruct};
+use quote::{format_ident, quote, ToTokens};
+use syn::{
+    parse::{Parse, ParseStream},
+    parse_quote,
+    spanned::Spanned,
+    Error, Fields, GenericArgument, Generics, ItemStruct, PathArguments, Type, TypePath,
+};
+
+use crate::{
+    commands::get_component_edits,
+    ecs_world_impl::StructList,
+    entity_impl::{component_type, get_collection, tracks_removed},
+    helpers::{
+        column_type_params, component_key, encode_type, format_column_name,
+        format_column_type_name, format_entity_name, format_field_name, format_removed_name,
+        format_world_column_type_name, is_indexed, is_sparse, is_tracked, parent_path,
+        substitute_idents,
+    },
+    indexed::get_indexed_components,
+    migrate::get_migrations,
+    query_impl::get_collection_component_fields,
+    resources::type_string,
+    sparse::get_sparse_components,
+    spatial::get_spatial_hash,
+};
+
+/// The argument of `#[tag_world(...)]`, an entity type as listed in `ecs_world!`. Its tokens
+/// are imported without the generic arguments, those are applied to the imported struct.
+pub struct TagWorldArgs {
+    entity_type: TypePath,
+    foreign_path: syn::Path,
+}
+
+impl Parse for TagWorldArgs {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let entity_type: TypePath = input.parse()?;
+        let mut foreign_path = entity_type.path.clone();
+        if let Some(last_segment) = foreign_path.segments.last_mut() {
+            last_segment.arguments = PathArguments::None;
+        }
+        Ok(TagWorldArgs {
+            entity_type,
+            foreign_path,
+        })
+    }
+}
 
-use crate::ecs_world_impl::StructList;
+impl ToTokens for TagWorldArgs {
+    fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
+        self.entity_type.to_tokens(tokens);
+    }
+}
+
+impl macro_magic::mm_core::ForeignPath for TagWorldArgs {
+    fn foreign_path(&self) -> &syn::Path {
+        &self.foreign_path
+    }
+}
 
 pub fn expand_world(attr: TokenStream, item: TokenStream) -> TokenStream {
     let types = syn::parse_macro_input!(attr as StructList);
 
     let mut types: Vec<_> = types.0.iter().collect();
     let Some(next_type) = types.pop() else {
-        return item;
+        // every entity has been tagged, generate what needs to know all components
+        return finish_world(item);
     };
 
     let local_struct = syn::parse_macro_input!(item as ItemStruct);
@@ -40,8 +97,139 @@ pub fn expand_world(attr: TokenStream, item: TokenStream) -> TokenStream {
     .into()
 }
 
-pub fn tag_world(attr: TokenStream, item: TokenStream) -> TokenStream {
-    let foreign_struct = syn::parse_macro_input!(attr as ItemStruct);
+fn finish_world(item: TokenStream) -> TokenStream {
+    let world_struct = syn::parse_macro_input!(item as ItemStruct);
+    let collection_component_fields = get_collection_component_fields(world_struct.clone());
+
+    let component_edits = get_component_edits(&world_struct.ident, &collection_component_fields);
+    let migrations = get_migrations(&world_struct.ident, &collection_component_fields);
+    let sparse_components =
+        get_sparse_components(&world_struct.ident, &collection_component_fields);
+    let indexed_components =
+        get_indexed_components(&world_struct.ident, &collection_component_fields);
+    let spatial_hash = get_spatial_hash(&world_struct, &collection_component_fields);
+
+    quote! {
+        #world_struct
+
+        #component_edits
+
+        #migrations
+
+        #sparse_components
+
+        #indexed_components
+
+        #spatial_hash
+    }
+    .into()
+}
+
+/// Adds the tag fields of one entity type to the world, and generates its storage next to
+/// the world.
+pub fn tag_world(attr: TokenStream, item: TokenStream, custom_tokens: TokenStream) -> TokenStream {
+    let mut foreign_struct = syn::parse_macro_input!(attr as ItemStruct);
+    let entity_type = syn::parse_macro_input!(custom_tokens as TypePath);
+    let entity_name = format_entity_name(&entity_type);
+
+    // instantiate generic entities with the arguments given in `ecs_world!`
+    let generic_arguments: Vec<_> = match &entity_type.path.segments.last().map(|s| &s.arguments) {
+        Some(PathArguments::AngleBracketed(args)) => args
+            .args
+            .iter()
+            .filter_map(|arg| match arg {
+                GenericArgument::Type(ty) => Some(ty.to_token_stream()),
+                _ => None,
+            })
+            .collect(),
+        _ => vec![],
+    };
+    let substitutions: Vec<_> = foreign_struct
+        .generics
+        .type_params()
+        .map(|param| param.ident.clone())
+        .zip(generic_arguments)
+        .collect();
+
+    // the component types are named through the aliases `#[entity]` defines next to the
+    // entity, they might not be in scope here. Components are still matched by the type
+    // written in the entity.
+    let entity_module = parent_path(&entity_type.path);
+    let columns: Vec<_> = foreign_struct
+        .fields
+        .iter()
+        .enumerate()
+        .map(|(index, field)| {
+            let column = format_column_name(index, field);
+            let component_type = component_type(field);
+            let alias = format_column_type_name(&foreign_struct.ident, &column);
+            let alias_arguments = column_type_params(&foreign_struct.generics, component_type)
+                .into_iter()
+                .filter_map(|param| {
+                    substitutions
+                        .iter()
+                        .find(|(from, _)| *from == param)
+                        .map(|(_, to)| to.clone())
+                });
+            let world_type: Type = parse_quote!(#entity_module #alias<#(#alias_arguments),*>);
+            let written_type = substitute_idents(component_type.to_token_stream(), &substitutions);
+            // sparse, indexed and tracked fields are told apart by a suffix on their tag, and
+            // fields of tuple structs by another one, migrations move them by position
+            let kind = if is_sparse(field) {
+                "__sparse"
+            } else if is_indexed(field) {
+                "__indexed"
+            } else if is_tracked(field) {
+                "__tracked"
+            } else {
+                ""
+            };
+            let unnamed = if field.ident.is_none() {
+                "__unnamed"
+            } else {
+                ""
+            };
+            let suffix = format!("{}{}", kind, unnamed);
+            (column, written_type, world_type, suffix)
+        })
+        .collect();
+
+    // `#[entity]` checks the types as written, a generic entity can still get the same
+    // component twice through its arguments
+    let mut component_columns = HashMap::new();
+    for (column, written_type, _, _) in &columns {
+        if let Some(other_column) = component_columns.insert(component_key(written_type), column) {
+            return Error::new(
+                entity_type.span(),
+                format!(
+                    "`{}` has the same type as `{}` in `{}`, an entity can only have one component of each type",
+                    column,
+                    other_column,
+                    type_string(&Type::Path(entity_type.clone()))
+                ),
+            )
+            .to_compile_error()
+            .into();
+        }
+    }
+
+    for (field, (_, _, world_type, suffix)) in foreign_struct.fields.iter_mut().zip(&columns) {
+        field.ty = if suffix.starts_with("__sparse") {
+            parse_quote!(Option<#world_type>)
+        } else {
+            world_type.clone()
+        };
+    }
+    foreign_struct.generics = Generics::default();
+
+    let collection = match get_collection(&foreign_struct, &entity_name) {
+        Ok(collection) => collection,
+        Err(err) => return err.to_compile_error().into(),
+    };
+    let track_removed = match tracks_removed(&foreign_struct) {
+        Ok(track_removed) => track_removed,
+        Err(err) => return err.to_compile_error().into(),
+    };
 
     let local_struct = syn::parse_macro_input!(item as ItemStruct);
     let Fields::Named(local_fields) = local_struct.fields else {
@@ -58,34 +246,51 @@ pub fn tag_world(attr: TokenStream, item: TokenStream) -> TokenStream {
     let ident = local_struct.ident;
     let vis = local_struct.vis;
 
-    let Fields::Named(foreign_fields) = foreign_struct.fields else {
-        return Error::new(
-            foreign_struct.fields.span(),
-            "unnamed fields are not supported",
-        )
-        .to_compile_error()
-        .into();
-    };
-
-    let foreign_struct_ident = foreign_struct.ident;
-
-    let foreign_fields = foreign_fields.named.iter().map(|field| {
-        let field_name = &field.ident;
-        let field_type = &field.ty;
-        let field_type_str = quote::ToTokens::to_token_stream(field_type).to_string();
-        let field_type_str = field_type_str.replace(' ', ""); // Remove spaces if necessary
+    let foreign_fields = columns.iter().map(|(column, written_type, _, suffix)| {
+        // the type can have paths and generic arguments, so it's encoded to fit in the name
+        let field_type_str = encode_type(&written_type.to_string());
         let field_name = format_ident!(
-            "__twcf__{}__{}__{}",
-            foreign_struct_ident,
-            field_name.clone().expect("foreign fields field clone"),
-            field_type_str
+            "__twcf__{}__{}__{}{}",
+            entity_name,
+            column,
+            field_type_str,
+            suffix
         );
+        let alias = format_world_column_type_name(&entity_name, column);
         quote! {
-            #field_name: std::marker::PhantomData<()>
+            #field_name: std::marker::PhantomData<#alias>
+        }
+    });
+    let column_types = columns.iter().map(|(column, _, world_type, _)| {
+        let alias = format_world_column_type_name(&entity_name, column);
+        quote! {
+            #[doc(hidden)]
+            #[allow(non_camel_case_types)]
+            pub type #alias = #world_type;
+        }
+    });
+
+    let removed = track_removed.then(|| {
+        let field_name = format_field_name(&entity_name);
+        let removed_name = format_removed_name(&entity_name);
+        quote! {
+            impl WorldRemoved<#entity_name> for #ident {
+                type Removed = #removed_name;
+
+                fn removed(&self) -> &[#removed_name] {
+                    &self.#field_name.removed
+                }
+            }
         }
     });
 
     quote! {
+        #collection
+
+        #removed
+
+        #(#column_types)*
+
         #(#attrs)
         *
         #vis struct #ident<#generics> {
@@ -4,5 +4,80 @@ mod test_simple;
 #[cfg(test)]
 mod test_systems_comprehensive;
 
+#[cfg(test)]
+mod test_generational;
+
+#[cfg(test)]
+mod test_take;
+
+#[cfg(test)]
+mod test_commands;
+
+#[cfg(test)]
+mod test_migrate;
+
+#[cfg(test)]
+mod test_sparse;
+
+#[cfg(test)]
+mod test_batch;
+
+#[cfg(test)]
+mod test_clear;
+
+#[cfg(test)]
+mod test_sort;
+
+#[cfg(test)]
+mod test_ordered;
+
+#[cfg(test)]
+mod test_typed;
+
+#[cfg(test)]
+mod test_resources;
+
+#[cfg(test)]
+mod test_generic;
+
+#[cfg(test)]
+mod test_tuple;
+
+#[cfg(test)]
+mod test_type_paths;
+
+#[cfg(test)]
+mod test_same_name;
+
+#[cfg(test)]
+mod test_named_worlds;
+
+#[cfg(test)]
+mod test_cross_crate;
+
+#[cfg(test)]
+mod test_hierarchy;
+
+#[cfg(test)]
+mod test_indexed;
+
+#[cfg(test)]
+mod test_spatial;
+
+#[cfg(test)]
+mod test_changed;
+
+#[cfg(test)]
+mod test_removed;
+
+#[cfg(test)]
+mod test_events;
+
+#[cfg(test)]
+mod test_hooks;
+
+#[cfg(test)]
+mod test_with_without;
+
 #[cfg(test)]
 mod complex_tests;
@@ -0,0 +1,101 @@
+use zero_ecs::*;
+
+#[derive(Debug, Default, PartialEq)]
+pub struct Position(i32);
+
+#[derive(Debug, Default, PartialEq)]
+pub struct Name(&'static str);
+
+#[entity]
+#[derive(Default)]
+pub struct EnemyEntity {
+    position: Position,
+    #[sparse]
+    name: Option<Name>,
+}
+
+ecs_world!(EnemyEntity);
+
+make_query!(QueryPosition, Position);
+
+#[test]
+fn create_batch_returns_handles_in_order() {
+    let mut world = World::default();
+
+    let entities = world.create_batch((0..1000).map(|i| EnemyEntity {
+        position: Position(i),
+        name: if i % 2 == 0 { Some(Name("even")) } else { None },
+    }));
+
+    assert_eq!(entities.len(), 1000);
+    assert_eq!(world.__enemy_entity.len(), 1000);
+    assert!(world.__enemy_entity.position.capacity() >= 1000);
+
+    for (i, entity) in entities.iter().enumerate() {
+        let QueryPosition(position) = world.get(*entity).unwrap();
+        assert_eq!(position.0, i as i32);
+    }
+    assert_eq!(world.__enemy_entity.name.len(), 500);
+}
+
+#[test]
+fn create_batch_reserves_from_the_upper_bound() {
+    let mut world = World::default();
+    world.create(EnemyEntity::default());
+
+    // a filter has a lower bound of 0
+    let entities = world.create_batch((0..300).filter(|i| i % 3 == 0).map(|i| EnemyEntity {
+        position: Position(i),
+        name: None,
+    }));
+    assert!(world.__enemy_entity.position.capacity() >= 301);
+
+    assert_eq!(entities.len(), 100);
+    assert_eq!(entities[0].id(), 1);
+    for (i, entity) in entities.iter().enumerate() {
+        let QueryPosition(position) = world.get(*entity).unwrap();
+        assert_eq!(position.0, i as i32 * 3);
+    }
+    world.destroy(entities[0]);
+    assert!(world.is_alive(entities[1]));
+    assert_eq!(world.__enemy_entity.len(), 100);
+}
+
+#[test]
+fn create_batch_reuses_freed_ids() {
+    let mut world = World::default();
+
+    let first = world.create_batch(vec![
+        EnemyEntity {
+            position: Position(1),
+            name: None,
+        },
+        EnemyEntity {
+            position: Position(2),
+            name: None,
+        },
+    ]);
+    world.destroy(first[0]);
+
+    let second = world.create_batch(vec![EnemyEntity {
+        position: Position(3),
+        name: None,
+    }]);
+    assert_eq!(second[0].id(), first[0].id());
+    assert_ne!(second[0], first[0]);
+    assert_eq!(world.__enemy_entity.len(), 2);
+}
+
+#[test]
+fn reserve_grows_every_dense_column() {
+    let mut world = World::default();
+
+    world.reserve::<EnemyEntity>(500);
+
+    let collection = &world.__enemy_entity;
+    assert!(collection.entity.capacity() >= 500);
+    assert!(collection.position.capacity() >= 500);
+    assert!(collection.index_lookup.capacity() >= 500);
+    assert!(collection.generations.capacity() >= 500);
+    assert_eq!(collection.len(), 0);
+}
@@ -0,0 +1,282 @@
+use zero_ecs::*;
+
+#[derive(Debug, Default, PartialEq)]
+pub struct Position(i32);
+
+#[derive(Debug, Default, PartialEq)]
+pub struct Velocity(i32);
+
+#[derive(Debug, Default, PartialEq)]
+pub struct Bounds(i32);
+
+#[derive(Debug, PartialEq)]
+pub struct Shield(i32);
+
+#[entity]
+#[derive(Default)]
+pub struct ShipEntity {
+    #[tracked]
+    position: Position,
+    velocity: Velocity,
+    bounds: Bounds,
+    #[sparse]
+    shield: Option<Shield>,
+}
+
+#[entity]
+#[derive(Default)]
+pub struct RockEntity {
+    position: Position,
+}
+
+ecs_world!(ShipEntity, RockEntity);
+
+make_query!(QueryPositionMut, mut Position);
+make_query!(QueryVelocityMut, mut Velocity);
+make_query!(QueryEntity, Entity);
+make_query!(QueryChanged, Entity; Changed<Position>);
+make_query!(QueryAdded, Entity; Added<Position>);
+make_query!(QueryChangedShield, Entity, Shield; Changed<Position>);
+
+#[system_for_each(World)]
+fn apply_velocity(position: &mut Position, velocity: &Velocity) {
+    position.0 += velocity.0;
+}
+
+#[system_for_each(World, Changed<Position>)]
+fn update_bounds(position: &Position, bounds: &mut Bounds) {
+    bounds.0 = position.0 + 1;
+}
+
+#[system(World)]
+fn count_changed(world: &World, positions: Query<&Position, Changed<Position>>, count: &mut usize) {
+    *count = world.with_query(positions).len();
+}
+
+fn changed(world: &World) -> Vec<Entity> {
+    world
+        .with_query(Query::<QueryChanged>::new())
+        .iter()
+        .map(|QueryChanged(entity)| *entity)
+        .collect()
+}
+
+fn added(world: &World) -> Vec<Entity> {
+    world
+        .with_query(Query::<QueryAdded>::new())
+        .iter()
+        .map(|QueryAdded(entity)| *entity)
+        .collect()
+}
+
+#[test]
+fn created_entities_are_added_and_changed_until_trackers_are_cleared() {
+    let mut world = World::default();
+    let a = world.create(ShipEntity::default()).entity();
+    let b = world.create(ShipEntity::default()).entity();
+    world.create(RockEntity::default());
+
+    // rocks don't track their position, so they never match
+    assert_eq!(added(&world), vec![a, b]);
+    assert_eq!(changed(&world), vec![a, b]);
+
+    world.clear_trackers();
+    assert!(added(&world).is_empty());
+    assert!(changed(&world).is_empty());
+
+    let c = world.create(ShipEntity::default()).entity();
+    assert_eq!(added(&world), vec![c]);
+    assert_eq!(changed(&world), vec![c]);
+}
+
+#[test]
+fn mutable_access_marks_components_changed() {
+    let mut world = World::default();
+    let a = world.create(ShipEntity::default()).entity();
+    let b = world.create(ShipEntity::default()).entity();
+    let c = world.create(ShipEntity::default()).entity();
+    world.clear_trackers();
+
+    // reading, or changing an untracked component, doesn't count
+    world
+        .with_query(Query::<QueryEntity>::new())
+        .iter()
+        .for_each(|_: QueryEntity| {});
+    world
+        .with_query_mut(Query::<QueryVelocityMut>::new())
+        .iter_mut()
+        .for_each(|QueryVelocityMut(velocity)| velocity.0 += 1);
+    assert!(changed(&world).is_empty());
+
+    {
+        let mut query = world.with_query_mut(Query::<QueryPositionMut>::new());
+        let QueryPositionMut(position) = query.get_mut(b).unwrap();
+        position.0 = 5;
+    }
+    assert_eq!(changed(&world), vec![b]);
+
+    // only the returned row counts, not the ones before it
+    {
+        let mut query = world.with_query_mut(Query::<QueryPositionMut>::new());
+        let QueryPositionMut(position) = query.at_mut(2).unwrap();
+        position.0 = 5;
+    }
+    assert_eq!(changed(&world), vec![b, c]);
+
+    world.clear_trackers();
+    world
+        .with_query_mut(Query::<QueryPositionMut>::new())
+        .iter_mut()
+        .for_each(|_: QueryPositionMut| {});
+    assert_eq!(changed(&world), vec![a, b, c]);
+}
+
+#[test]
+fn system_for_each_can_run_only_for_changed_components() {
+    let mut world = World::default();
+    world.create(ShipEntity::default());
+    let moving = world
+        .create(ShipEntity {
+            velocity: Velocity(2),
+            ..Default::default()
+        })
+        .entity();
+    world.update_bounds();
+    assert_eq!(world.__ship_entity.bounds, vec![Bounds(1), Bounds(1)]);
+    world.clear_trackers();
+
+    {
+        let mut query = world.with_query_mut(Query::<QueryPositionMut>::new());
+        let QueryPositionMut(position) = query.get_mut(moving).unwrap();
+        position.0 = 10;
+    }
+    // changed by hand, to see that the bounds of the other ship are left alone
+    world.__ship_entity.bounds[0] = Bounds(-1);
+    world.update_bounds();
+
+    assert_eq!(world.__ship_entity.bounds, vec![Bounds(-1), Bounds(11)]);
+}
+
+#[test]
+fn systems_accept_filters() {
+    let mut world = World::default();
+    world.create(ShipEntity {
+        velocity: Velocity(1),
+        ..Default::default()
+    });
+    world.create(ShipEntity {
+        velocity: Velocity(1),
+        ..Default::default()
+    });
+    world.create(RockEntity::default());
+
+    let mut count = 0;
+    world.count_changed(&mut count);
+    assert_eq!(count, 2);
+
+    world.clear_trackers();
+    world.count_changed(&mut count);
+    assert_eq!(count, 0);
+
+    world.apply_velocity();
+    world.count_changed(&mut count);
+    assert_eq!(count, 2);
+}
+
+#[test]
+fn filters_combine_with_sparse_components() {
+    let mut world = World::default();
+    let shielded = world
+        .create(ShipEntity {
+            shield: Some(Shield(3)),
+            ..Default::default()
+        })
+        .entity();
+    let other = world
+        .create(ShipEntity {
+            shield: Some(Shield(1)),
+            ..Default::default()
+        })
+        .entity();
+    world.create(ShipEntity::default());
+    world.clear_trackers();
+
+    for entity in [shielded, other] {
+        let mut query = world.with_query_mut(Query::<QueryPositionMut>::new());
+        let QueryPositionMut(position) = query.get_mut(entity).unwrap();
+        position.0 = 1;
+    }
+    world.destroy(other);
+
+    let query = world.with_query(Query::<QueryChangedShield>::new());
+    assert_eq!(query.len(), 1);
+    let entities: Vec<Entity> = query
+        .iter()
+        .map(|QueryChangedShield(entity, _)| *entity)
+        .collect();
+    assert_eq!(entities, vec![shielded]);
+    let QueryChangedShield(_, shield) = query.at(0).unwrap();
+    assert_eq!(*shield, Shield(3));
+}
+
+#[test]
+fn ticks_stay_with_their_entity_when_others_are_destroyed() {
+    let mut world = World::default();
+    let a = world.create(ShipEntity::default()).entity();
+    let b = world.create(ShipEntity::default()).entity();
+    let c = world.create(ShipEntity::default()).entity();
+    world.clear_trackers();
+
+    {
+        let mut query = world.with_query_mut(Query::<QueryPositionMut>::new());
+        let QueryPositionMut(position) = query.get_mut(c).unwrap();
+        position.0 = 1;
+    }
+    // c is swapped into a's place
+    world.destroy(a);
+    assert_eq!(changed(&world), vec![c]);
+    assert!(world.is_alive(b));
+
+    world.clear_trackers();
+    let mut commands = Commands::new();
+    commands.set(b, Position(4));
+    world.apply(commands);
+    assert_eq!(changed(&world), vec![b]);
+}
+
+#[test]
+fn parallel_queries_track_changes() {
+    let mut world = World::default();
+    let ships: Vec<Entity> = (0..500)
+        .map(|i| {
+            world
+                .create(ShipEntity {
+                    shield: (i % 2 == 0).then_some(Shield(i)),
+                    ..Default::default()
+                })
+                .entity()
+        })
+        .collect();
+    world.clear_trackers();
+
+    world
+        .with_query_mut(Query::<QueryPositionMut>::new())
+        .par_iter_mut()
+        .for_each(|QueryPositionMut(position)| position.0 += 1);
+    assert_eq!(changed(&world), ships);
+
+    world.clear_trackers();
+    for entity in ships.iter().step_by(3) {
+        let mut query = world.with_query_mut(Query::<QueryPositionMut>::new());
+        let QueryPositionMut(position) = query.get_mut(*entity).unwrap();
+        position.0 = 2;
+    }
+
+    let query = world.with_query(Query::<QueryChangedShield>::new());
+    let entities: Vec<Entity> = query
+        .par_iter()
+        .map(|QueryChangedShield(entity, _)| *entity)
+        .collect();
+    let expected: Vec<Entity> = ships.iter().copied().step_by(6).collect();
+    assert_eq!(entities, expected);
+}
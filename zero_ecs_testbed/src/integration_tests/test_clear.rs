@@ -0,0 +1,98 @@
+use zero_ecs::*;
+
+#[derive(Default)]
+pub struct Position(i32);
+
+#[derive(Default)]
+pub struct Shield;
+
+#[entity]
+#[derive(Default)]
+pub struct EnemyEntity {
+    position: Position,
+    #[sparse]
+    shield: Option<Shield>,
+}
+
+#[entity]
+#[derive(Default)]
+pub struct PlayerEntity {
+    position: Position,
+}
+
+ecs_world!(EnemyEntity, PlayerEntity);
+
+make_query!(QueryPosition, Position);
+
+#[test]
+fn len_counts_all_and_per_type() {
+    let mut world = World::default();
+    assert!(world.is_empty());
+
+    for _ in 0..3 {
+        world.create(EnemyEntity::default());
+    }
+    let player = world.create(PlayerEntity::default());
+
+    assert_eq!(world.len(), 4);
+    assert_eq!(world.len_of(EntityType::EnemyEntity), 3);
+    assert_eq!(world.len_of(EntityType::PlayerEntity), 1);
+
+    world.destroy(player);
+    assert_eq!(world.len(), 3);
+    assert_eq!(world.len_of(EntityType::PlayerEntity), 0);
+}
+
+#[test]
+fn is_alive_follows_destroy() {
+    let mut world = World::default();
+
+    let enemy = world.create(EnemyEntity::default());
+    assert!(world.is_alive(enemy));
+
+    world.destroy(enemy);
+    assert!(!world.is_alive(enemy));
+
+    let reused = world.create(EnemyEntity::default());
+    assert_eq!(reused.id(), enemy.id());
+    assert!(world.is_alive(reused));
+    assert!(!world.is_alive(enemy));
+}
+
+#[test]
+fn clear_makes_old_handles_stale() {
+    let mut world = World::default();
+
+    let enemy = world.create(EnemyEntity {
+        shield: Some(Shield),
+        ..Default::default()
+    });
+    let player = world.create(PlayerEntity::default());
+
+    world.clear();
+    assert!(world.is_empty());
+    assert!(!world.is_alive(enemy));
+    assert!(!world.is_alive(player));
+    assert!(world.__enemy_entity.shield.is_empty());
+
+    let new_enemy = world.create(EnemyEntity {
+        position: Position(7),
+        ..Default::default()
+    });
+    assert!(world.get::<QueryPosition>(enemy).is_none());
+    let QueryPosition(position) = world.get(new_enemy).unwrap();
+    assert_eq!(position.0, 7);
+}
+
+#[test]
+fn clear_type_leaves_other_types() {
+    let mut world = World::default();
+
+    let enemy = world.create(EnemyEntity::default());
+    let player = world.create(PlayerEntity::default());
+
+    world.clear_type(EntityType::EnemyEntity);
+    assert!(!world.is_alive(enemy));
+    assert!(world.is_alive(player));
+    assert_eq!(world.len(), 1);
+}
@@ -0,0 +1,143 @@
+use zero_ecs::*;
+
+#[derive(Debug, PartialEq)]
+pub struct Health(i32);
+
+#[derive(Debug, PartialEq)]
+pub struct Position(i32);
+
+pub struct Bullet;
+
+#[entity]
+pub struct EnemyEntity {
+    health: Health,
+    position: Position,
+}
+
+#[entity]
+pub struct BulletEntity {
+    position: Position,
+    bullet: Bullet,
+}
+
+ecs_world!(EnemyEntity, BulletEntity);
+
+make_query!(QueryHealth, Health);
+
+make_query!(QueryPosition, Position);
+
+#[system(World)]
+fn kill_dead_enemies(
+    world: &mut World,
+    enemies: Query<(&Entity, &mut Health, &Position)>,
+    commands: &mut Commands,
+) {
+    world
+        .with_query_mut(enemies)
+        .iter_mut()
+        .for_each(|(entity, health, position)| {
+            health.0 -= 1;
+            if health.0 <= 0 {
+                commands.destroy(*entity);
+                commands.create(BulletEntity {
+                    position: Position(position.0),
+                    bullet: Bullet,
+                });
+            }
+        });
+}
+
+#[system(World)]
+fn par_heal_enemies(
+    world: &mut World,
+    enemies: Query<(&Entity, &Health)>,
+    commands: &SyncCommands,
+) {
+    world.with_query_mut(enemies).par_iter_mut().for_each(
+        |(entity, health): (&Entity, &Health)| {
+            commands.set(*entity, Health(health.0 + 10));
+        },
+    );
+}
+
+#[test]
+fn commands_are_applied_after_the_system() {
+    let mut world = World::default();
+
+    world.create(EnemyEntity {
+        health: Health(1),
+        position: Position(7),
+    });
+    world.create(EnemyEntity {
+        health: Health(5),
+        position: Position(8),
+    });
+
+    world.kill_dead_enemies();
+
+    let health = world.with_query(Query::<QueryHealth>::new());
+    assert_eq!(health.len(), 1);
+    let QueryHealth(health) = health.at(0).unwrap();
+    assert_eq!(health.0, 4);
+
+    // one enemy and one bullet
+    let mut positions: Vec<i32> = world
+        .with_query(Query::<QueryPosition>::new())
+        .iter()
+        .map(|QueryPosition(p)| p.0)
+        .collect();
+    positions.sort();
+    assert_eq!(positions, vec![7, 8]);
+}
+
+#[test]
+fn sync_commands_work_from_parallel_iteration() {
+    let mut world = World::default();
+
+    for i in 0..100 {
+        world.create(EnemyEntity {
+            health: Health(i),
+            position: Position(i),
+        });
+    }
+
+    world.par_heal_enemies();
+
+    world
+        .with_query(Query::<QueryHealth>::new())
+        .iter()
+        .enumerate()
+        .for_each(|(i, QueryHealth(health))| {
+            assert_eq!(health.0, i as i32 + 10);
+        });
+}
+
+#[test]
+fn commands_are_applied_in_order() {
+    let mut world = World::default();
+
+    let enemy = world.create(EnemyEntity {
+        health: Health(1),
+        position: Position(0),
+    });
+
+    let mut commands = Commands::new();
+    commands.set(enemy, Health(50));
+    commands.set(enemy, Position(3));
+    commands.destroy(enemy);
+    // setting a component on a destroyed entity is ignored
+    commands.set(enemy, Health(60));
+    commands.create(EnemyEntity {
+        health: Health(2),
+        position: Position(1),
+    });
+    assert_eq!(commands.len(), 5);
+
+    world.apply(commands);
+
+    let health = world.with_query(Query::<QueryHealth>::new());
+    assert_eq!(health.len(), 1);
+    assert!(health.get::<QueryHealth>(enemy).is_none());
+    let QueryHealth(health) = health.at(0).unwrap();
+    assert_eq!(health.0, 2);
+}
@@ -0,0 +1,88 @@
+use zero_ecs::*;
+use zero_ecs_testbed_shared::components::{Arrow, Position, Shield, Velocity};
+use zero_ecs_testbed_shared::entities::Projectile;
+use zero_ecs_testbed_shared::world::{core_world, CoreWorld};
+
+// only the entities' module is named, their components are not imported in it
+mod local {
+    use zero_ecs::*;
+
+    #[entity]
+    pub struct Tower {
+        pub position: zero_ecs_testbed_shared::components::Position,
+    }
+
+    ecs_world!(zero_ecs_testbed_shared::entities::Unit, Tower);
+}
+
+#[system_for_each(zero_ecs_testbed_shared::world::CoreWorld)]
+fn advance(position: &mut Position, velocity: &Velocity) {
+    position.0 += velocity.0;
+}
+
+#[system(zero_ecs_testbed_shared::world::CoreWorld)]
+fn fire(world: &mut CoreWorld, shooters: Query<(&Position, &Shield)>, commands: &mut Commands) {
+    world.with_query(shooters).iter().for_each(|(position, _)| {
+        commands.create(Projectile {
+            position: *position,
+            payload: Arrow(1),
+        });
+    });
+}
+
+#[system_for_each(local::World)]
+fn advance_local(position: &mut Position, velocity: &Velocity) {
+    position.0 += velocity.0;
+}
+
+make_query!(local::World: LocalPositions, Position);
+
+#[test]
+fn entities_from_another_crate_can_be_listed_in_a_world() {
+    let mut world = local::World::default();
+
+    let unit = world.create(zero_ecs_testbed_shared::entities::Unit {
+        position: Position(0),
+        velocity: Velocity(2),
+        shield: Some(Shield(1)),
+    });
+    world.create(local::Tower {
+        position: Position(5),
+    });
+
+    world.advance_local();
+    let LocalPositions(position) = world.get(unit).unwrap();
+    assert_eq!(*position, Position(2));
+    assert_eq!(
+        world
+            .with_query(local::Query::<LocalPositions>::new())
+            .len(),
+        2
+    );
+
+    assert!(world.remove::<Shield>(unit).is_some());
+    assert_eq!(world.len_of(local::EntityType::Unit), 1);
+}
+
+#[test]
+fn systems_can_run_on_a_world_from_another_crate() {
+    let mut world = CoreWorld::default();
+
+    world.create(zero_ecs_testbed_shared::entities::Unit {
+        position: Position(1),
+        velocity: Velocity(1),
+        shield: Some(Shield(3)),
+    });
+    world.create(zero_ecs_testbed_shared::entities::Unit {
+        position: Position(4),
+        velocity: Velocity(-1),
+        shield: None,
+    });
+
+    world.advance();
+    world.fire();
+    assert_eq!(world.len_of(core_world::EntityType::ProjectileArrow), 1);
+
+    let positions: Vec<_> = world.__unit.position.clone();
+    assert_eq!(positions, vec![Position(2), Position(3)]);
+}
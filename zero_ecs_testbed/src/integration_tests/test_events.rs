@@ -0,0 +1,161 @@
+use zero_ecs::*;
+
+#[derive(Debug, Default)]
+pub struct Position(i32);
+
+#[derive(Debug, Default)]
+pub struct Health(i32);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Collision(Entity, i32);
+
+#[derive(Debug, PartialEq)]
+pub struct Damage(i32);
+
+#[derive(Default)]
+pub struct Log(Vec<i32>);
+
+#[entity]
+#[derive(Default)]
+pub struct ShipEntity {
+    position: Position,
+    health: Health,
+}
+
+ecs_world!(ShipEntity; resources: Log; events: Collision, Damage);
+
+#[system(World)]
+fn detect_collisions(
+    world: &World,
+    positions: Query<(&Entity, &Position)>,
+    collisions: &mut EventWriter<Collision>,
+) {
+    world
+        .with_query(positions)
+        .iter()
+        .filter(|(_, position)| position.0 < 0)
+        .for_each(|(entity, position)| collisions.send(Collision(*entity, position.0)));
+}
+
+#[system(World)]
+fn apply_collisions(
+    world: &mut World,
+    collisions: &EventReader<Collision>,
+    health: Query<&mut Health>,
+    damage: &mut EventWriter<Damage>,
+) {
+    for Collision(entity, amount) in collisions {
+        let mut query = world.with_query_mut(health);
+        let health: Option<&mut Health> = query.get_mut(*entity);
+        if let Some(health) = health {
+            health.0 += amount;
+            damage.send(Damage(*amount));
+        }
+    }
+}
+
+#[system(World)]
+fn log_damage(world: &mut World, damage: &EventReader<Damage>, log: &mut Log) {
+    // the events are borrowed next to the entities, a ship is created for every one read
+    for Damage(amount) in damage {
+        log.0.push(*amount);
+        world.create(ShipEntity {
+            position: Position(*amount),
+            health: Health(0),
+        });
+    }
+}
+
+#[system_for_each(World)]
+fn move_ships(position: &mut Position, damage: &EventReader<Damage>) {
+    position.0 += damage.len() as i32;
+}
+
+#[system_for_each(World)]
+fn report_ships(position: &Position, health: &Health, damage: &mut EventWriter<Damage>) {
+    if position.0 < 0 {
+        damage.send(Damage(health.0));
+    }
+}
+
+#[test]
+fn events_can_be_read_after_update() {
+    let mut world = World::default();
+    world.send_event(Damage(1));
+    assert!(world.read_events::<Damage>().is_empty());
+
+    world.update_events();
+    world.send_event(Damage(2));
+    let read: Vec<&Damage> = world.read_events::<Damage>().iter().collect();
+    assert_eq!(read, vec![&Damage(1)]);
+
+    // read events are dropped on the next update
+    world.update_events();
+    let read: Vec<&Damage> = world.read_events::<Damage>().iter().collect();
+    assert_eq!(read, vec![&Damage(2)]);
+
+    world.update_events();
+    assert!(world.read_events::<Damage>().is_empty());
+}
+
+#[test]
+fn systems_send_and_read_events() {
+    let mut world = World::default();
+    let a = world
+        .create(ShipEntity {
+            position: Position(-3),
+            health: Health(10),
+        })
+        .entity();
+    world.create(ShipEntity {
+        position: Position(4),
+        health: Health(10),
+    });
+
+    world.detect_collisions();
+    world.apply_collisions();
+    assert!(world.read_events::<Collision>().is_empty());
+
+    world.update_events();
+    let collisions: Vec<Collision> = world.read_events::<Collision>().iter().cloned().collect();
+    assert_eq!(collisions, vec![Collision(a, -3)]);
+
+    world.apply_collisions();
+    let health: Vec<i32> = world.__ship_entity.health.iter().map(|h| h.0).collect();
+    assert_eq!(health, vec![7, 10]);
+
+    world.update_events();
+    world.log_damage();
+    assert_eq!(world.resource::<Log>().0, vec![-3]);
+    assert_eq!(world.len(), 3);
+    // reading doesn't take the events out of the world
+    let damage: Vec<&Damage> = world.read_events::<Damage>().iter().collect();
+    assert_eq!(damage, vec![&Damage(-3)]);
+}
+
+#[test]
+fn system_for_each_sends_and_reads_events() {
+    let mut world = World::default();
+    world.create(ShipEntity {
+        position: Position(-1),
+        health: Health(10),
+    });
+    world.create(ShipEntity {
+        position: Position(1),
+        health: Health(10),
+    });
+    world.create(ShipEntity {
+        position: Position(-2),
+        health: Health(10),
+    });
+
+    world.report_ships();
+    world.update_events();
+    assert_eq!(world.read_events::<Damage>().len(), 2);
+
+    // every ship reads both events
+    world.move_ships();
+    let positions: Vec<i32> = world.__ship_entity.position.iter().map(|p| p.0).collect();
+    assert_eq!(positions, vec![1, 3, 0]);
+    assert_eq!(world.read_events::<Damage>().len(), 2);
+}
@@ -0,0 +1,91 @@
+use zero_ecs::*;
+
+pub struct Bullet(usize);
+
+#[entity]
+pub struct BulletEntity {
+    bullet: Bullet,
+}
+
+ecs_world!(BulletEntity);
+
+make_query!(QueryBullet, Bullet);
+
+make_query!(QueryBulletMut, mut Bullet);
+
+#[test]
+fn destroyed_ids_are_recycled() {
+    let mut world = World::default();
+
+    for frame in 0..100 {
+        let a = world.create(BulletEntity {
+            bullet: Bullet(frame),
+        });
+        let b = world.create(BulletEntity {
+            bullet: Bullet(frame),
+        });
+        world.destroy(a);
+        world.destroy(b);
+    }
+
+    assert_eq!(world.__bullet_entity.index_lookup.len(), 2);
+    assert_eq!(world.__bullet_entity.len(), 0);
+}
+
+#[test]
+fn recycled_id_gets_new_generation() {
+    let mut world = World::default();
+
+    let old = world.create(BulletEntity { bullet: Bullet(1) });
+    world.destroy(old);
+    let new = world.create(BulletEntity { bullet: Bullet(2) });
+
+    assert_eq!(old.id(), new.id());
+    assert_ne!(old.generation(), new.generation());
+    assert_ne!(old, new);
+}
+
+#[test]
+fn stale_handles_are_rejected() {
+    let mut world = World::default();
+
+    let old = world.create(BulletEntity { bullet: Bullet(1) });
+    world.destroy(old);
+    let new = world.create(BulletEntity { bullet: Bullet(2) });
+
+    assert!(world
+        .with_query(Query::<QueryBullet>::new())
+        .get::<QueryBullet>(old)
+        .is_none());
+    assert!(world
+        .with_query_mut(Query::<QueryBulletMut>::new())
+        .get_mut::<QueryBulletMut>(old)
+        .is_none());
+
+    // destroying the stale handle must not remove the new entity
+    world.destroy(old);
+
+    let query = world.with_query(Query::<QueryBullet>::new());
+    assert_eq!(query.len(), 1);
+    let QueryBullet(bullet) = query.get(new).unwrap();
+    assert_eq!(bullet.0, 2);
+}
+
+#[test]
+fn swap_remove_keeps_handles_valid() {
+    let mut world = World::default();
+
+    let a = world.create(BulletEntity { bullet: Bullet(1) });
+    let b = world.create(BulletEntity { bullet: Bullet(2) });
+    let c = world.create(BulletEntity { bullet: Bullet(3) });
+
+    world.destroy(a);
+    let d = world.create(BulletEntity { bullet: Bullet(4) });
+
+    let query = world.with_query(Query::<QueryBullet>::new());
+    assert!(query.get::<QueryBullet>(a).is_none());
+    for (entity, expected) in [(b, 2), (c, 3), (d, 4)] {
+        let QueryBullet(bullet) = query.get(entity).unwrap();
+        assert_eq!(bullet.0, expected);
+    }
+}
@@ -0,0 +1,113 @@
+use zero_ecs::*;
+
+pub trait Payload {
+    fn damage(&self) -> i32;
+}
+
+#[derive(Default, Debug, PartialEq)]
+pub struct Position(i32);
+
+#[derive(Debug, PartialEq)]
+pub struct Arrow(i32);
+
+#[derive(Debug, PartialEq)]
+pub struct Fireball {
+    radius: i32,
+}
+
+impl Payload for Arrow {
+    fn damage(&self) -> i32 {
+        self.0
+    }
+}
+
+impl Payload for Fireball {
+    fn damage(&self) -> i32 {
+        self.radius * 10
+    }
+}
+
+#[entity]
+pub struct Projectile<T: Payload> {
+    position: Position,
+    payload: T,
+}
+
+#[entity]
+pub struct Tower {
+    position: Position,
+}
+
+ecs_world!(Projectile<Arrow>, Projectile<Fireball>, Tower);
+
+make_query!(QueryPosition, Position);
+make_query!(QueryArrow, Arrow);
+make_query!(QueryFireball, Fireball);
+
+#[system_for_each(World)]
+fn move_projectile(position: &mut Position) {
+    position.0 += 1;
+}
+
+#[test]
+fn each_instantiation_gets_its_own_collection() {
+    let mut world = World::default();
+
+    let arrow = world.create(Projectile {
+        position: Position(0),
+        payload: Arrow(3),
+    });
+    let fireball = world.create(Projectile {
+        position: Position(10),
+        payload: Fireball { radius: 2 },
+    });
+    world.create(Tower {
+        position: Position(20),
+    });
+
+    assert_eq!(arrow.entity().entity_type, EntityType::ProjectileArrow);
+    assert_eq!(
+        fireball.entity().entity_type,
+        EntityType::ProjectileFireball
+    );
+    assert_eq!(world.len_of(EntityType::ProjectileArrow), 1);
+    assert_eq!(world.len_of(EntityType::ProjectileFireball), 1);
+    assert_eq!(world.__projectile_arrow.payload[0], Arrow(3));
+    assert_eq!(world.__projectile_fireball.payload[0].damage(), 20);
+
+    world.move_projectile();
+    let QueryPosition(position) = world.get(fireball).unwrap();
+    assert_eq!(*position, Position(11));
+    assert_eq!(world.with_query(Query::<QueryPosition>::new()).len(), 3);
+
+    // the payload is only a component of its own instantiation
+    assert_eq!(world.with_query(Query::<QueryArrow>::new()).len(), 1);
+    let QueryArrow(payload) = world.get(arrow).unwrap();
+    assert_eq!(payload.damage(), 3);
+    assert!(world.get::<QueryFireball>(arrow.entity()).is_none());
+}
+
+#[test]
+fn generic_entities_can_be_taken_and_migrated() {
+    let mut world = World::default();
+
+    let arrow: TypedEntity<ProjectileArrow> = world.create(Projectile {
+        position: Position(5),
+        payload: Arrow(1),
+    });
+    let fireball = world
+        .migrate(
+            arrow,
+            Projectile {
+                position: Position(0),
+                payload: Fireball { radius: 1 },
+            },
+        )
+        .unwrap();
+    let QueryPosition(position) = world.get(fireball).unwrap();
+    assert_eq!(*position, Position(5));
+
+    let taken: Projectile<Fireball> = world.take(fireball).unwrap();
+    assert_eq!(taken.payload, Fireball { radius: 1 });
+    assert!(world.is_empty());
+}
@@ -0,0 +1,196 @@
+use zero_ecs::*;
+
+#[derive(Debug, PartialEq)]
+pub struct Name(&'static str);
+
+#[derive(Debug, PartialEq)]
+pub struct Fuel(i32);
+
+#[entity]
+pub struct ShipEntity {
+    name: Name,
+}
+
+#[entity]
+pub struct TurretEntity {
+    name: Name,
+}
+
+#[entity]
+pub struct WreckEntity {
+    name: Name,
+    fuel: Fuel,
+}
+
+ecs_world!(ShipEntity, TurretEntity, WreckEntity);
+
+make_query!(QueryName, Name);
+
+#[system(World)]
+fn scrap_ships(world: &mut World, ships: Query<(&Entity, &Name)>, commands: &mut Commands) {
+    world
+        .with_query(ships)
+        .iter()
+        .filter(|(entity, _)| entity.entity_type == EntityType::ShipEntity)
+        .for_each(|(entity, _)| commands.destroy_recursive(*entity));
+}
+
+fn turret(world: &mut World, name: &'static str) -> TypedEntity<TurretEntity> {
+    world.create(TurretEntity { name: Name(name) })
+}
+
+#[test]
+fn children_of_any_type_can_be_added_and_moved() {
+    let mut world = World::default();
+
+    let ship = world.create(ShipEntity { name: Name("ship") });
+    let left = turret(&mut world, "left");
+    let right = turret(&mut world, "right");
+
+    assert!(world.set_parent(left, ship));
+    assert!(world.set_parent(right, ship));
+    assert_eq!(world.children(ship), &[left.entity(), right.entity()]);
+    assert_eq!(world.parent(left), Some(ship.entity()));
+    assert_eq!(world.parent(ship), None);
+
+    // moving a child removes it from its old parent
+    assert!(world.set_parent(right, left));
+    assert_eq!(world.children(ship), &[left.entity()]);
+    assert_eq!(world.children(left), &[right.entity()]);
+
+    assert_eq!(world.remove_parent(right), Some(left.entity()));
+    assert!(world.children(left).is_empty());
+}
+
+#[test]
+fn set_parent_rejects_cycles_and_stale_handles() {
+    let mut world = World::default();
+
+    let ship = world.create(ShipEntity { name: Name("ship") });
+    let turret_a = turret(&mut world, "a");
+    let turret_b = turret(&mut world, "b");
+    assert!(world.set_parent(turret_a, ship));
+    assert!(world.set_parent(turret_b, turret_a));
+
+    assert!(!world.set_parent(ship, turret_b));
+    assert!(!world.set_parent(ship, ship));
+    assert_eq!(world.parent(ship), None);
+
+    world.destroy(turret_b);
+    assert!(!world.set_parent(turret_b, ship));
+    assert!(!world.set_parent(ship, turret_b));
+}
+
+#[test]
+fn destroy_detaches_the_entity_and_orphans_its_children() {
+    let mut world = World::default();
+
+    let ship = world.create(ShipEntity { name: Name("ship") });
+    let turrets: Vec<_> = ["a", "b", "c"]
+        .into_iter()
+        .map(|name| turret(&mut world, name))
+        .collect();
+    let mount = turret(&mut world, "mount");
+    for turret in &turrets {
+        world.set_parent(*turret, ship);
+    }
+    world.set_parent(mount, turrets[2]);
+
+    // the first turret is swap-removed, the last one moves into its place
+    world.destroy(turrets[0]);
+    assert_eq!(
+        world.children(ship),
+        &[turrets[1].entity(), turrets[2].entity()]
+    );
+    assert_eq!(world.children(turrets[2]), &[mount.entity()]);
+    let QueryName(name) = world.get(world.children(turrets[2])[0]).unwrap();
+    assert_eq!(*name, Name("mount"));
+
+    world.destroy(ship);
+    assert_eq!(world.parent(turrets[1]), None);
+    assert!(world.is_alive(turrets[1]));
+
+    // a new entity in the destroyed ship's slot doesn't get its children
+    let new_ship = world.create(ShipEntity { name: Name("new") });
+    assert_eq!(new_ship.id(), ship.id());
+    assert!(world.children(new_ship).is_empty());
+}
+
+#[test]
+fn destroy_recursive_destroys_all_descendants() {
+    let mut world = World::default();
+
+    let ship = world.create(ShipEntity { name: Name("ship") });
+    let other_ship = world.create(ShipEntity {
+        name: Name("other"),
+    });
+    let turret_a = turret(&mut world, "a");
+    let turret_b = turret(&mut world, "b");
+    let wreck = world.create(WreckEntity {
+        name: Name("wreck"),
+        fuel: Fuel(3),
+    });
+    world.set_parent(turret_a, ship);
+    world.set_parent(turret_b, turret_a);
+    world.set_parent(wreck, turret_b);
+    world.set_parent(ship, other_ship);
+
+    world.destroy_recursive(ship);
+    assert_eq!(world.len(), 1);
+    assert!(world.is_alive(other_ship));
+    assert!(world.children(other_ship).is_empty());
+}
+
+#[test]
+fn take_clear_and_migrate_keep_the_hierarchy_consistent() {
+    let mut world = World::default();
+
+    let ship = world.create(ShipEntity { name: Name("ship") });
+    let turret_a = turret(&mut world, "a");
+    let turret_b = turret(&mut world, "b");
+    world.set_parent(turret_a, ship);
+    world.set_parent(turret_b, turret_a);
+
+    // the migrated entity keeps its parent and children
+    let wreck = world
+        .migrate(
+            turret_a,
+            WreckEntity {
+                name: Name(""),
+                fuel: Fuel(1),
+            },
+        )
+        .unwrap();
+    assert_eq!(world.children(ship), &[wreck.entity()]);
+    assert_eq!(world.parent(turret_b), Some(wreck.entity()));
+
+    let taken = world.take(turret_b).unwrap();
+    assert_eq!(taken.name, Name("b"));
+    assert!(world.children(wreck).is_empty());
+
+    world.clear_type(EntityType::WreckEntity);
+    assert!(world.children(ship).is_empty());
+
+    let turret_c = turret(&mut world, "c");
+    world.set_parent(turret_c, ship);
+    world.clear();
+    assert_eq!(world.parent(turret_c), None);
+}
+
+#[test]
+fn commands_can_set_parents_and_destroy_recursively() {
+    let mut world = World::default();
+
+    let ship = world.create(ShipEntity { name: Name("ship") });
+    let turret_a = turret(&mut world, "a");
+    let loose = turret(&mut world, "loose");
+
+    let mut commands = Commands::default();
+    commands.set_parent(turret_a, ship);
+    world.apply(commands);
+    assert_eq!(world.parent(turret_a), Some(ship.entity()));
+
+    world.scrap_ships();
+    assert_eq!(world.len(), 1);
+    assert!(world.is_alive(loose));
+}
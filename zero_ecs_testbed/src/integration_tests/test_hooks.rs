@@ -0,0 +1,156 @@
+use std::cell::RefCell;
+use zero_ecs::*;
+
+#[derive(Debug, Default)]
+pub struct BodyHandle(u32);
+
+#[entity(on_create = register_body, on_destroy = free_body)]
+#[derive(Default)]
+pub struct ShipEntity {
+    body: BodyHandle,
+}
+
+#[entity(track_removed, on_destroy = free_rock)]
+#[derive(Default)]
+pub struct RockEntity {
+    body: BodyHandle,
+}
+
+#[entity]
+#[derive(Default)]
+pub struct WreckEntity {
+    body: BodyHandle,
+}
+
+ecs_world!(ShipEntity, RockEntity, WreckEntity);
+
+thread_local! {
+    // what the hooks saw, in order
+    static EVENTS: RefCell<Vec<(&'static str, Entity, u32)>> = const { RefCell::new(Vec::new()) };
+}
+
+fn register_body(entity: TypedEntity<ShipEntity>, ship: &ShipEntity) {
+    EVENTS.with(|events| {
+        events
+            .borrow_mut()
+            .push(("create", entity.entity(), ship.body.0))
+    });
+}
+
+fn free_body(entity: TypedEntity<ShipEntity>, ship: &ShipEntity) {
+    EVENTS.with(|events| {
+        events
+            .borrow_mut()
+            .push(("destroy", entity.entity(), ship.body.0))
+    });
+}
+
+fn free_rock(entity: TypedEntity<RockEntity>, rock: &RockEntity) {
+    EVENTS.with(|events| {
+        events
+            .borrow_mut()
+            .push(("rock", entity.entity(), rock.body.0))
+    });
+}
+
+fn take_events() -> Vec<(&'static str, Entity, u32)> {
+    EVENTS.with(|events| events.take())
+}
+
+#[test]
+fn hooks_get_the_handle_and_components() {
+    let mut world = World::default();
+    let a = world.create(ShipEntity {
+        body: BodyHandle(1),
+    });
+    let b = world.create(ShipEntity {
+        body: BodyHandle(2),
+    });
+    assert_eq!(
+        take_events(),
+        vec![("create", a.entity(), 1), ("create", b.entity(), 2)]
+    );
+
+    world.destroy(a);
+    // a stale handle doesn't call the hook again
+    world.destroy(a);
+    assert_eq!(take_events(), vec![("destroy", a.entity(), 1)]);
+
+    // the freed id is reused, with the handle the hook was given
+    let c = world.create(ShipEntity {
+        body: BodyHandle(3),
+    });
+    assert_eq!(take_events(), vec![("create", c.entity(), 3)]);
+    assert!(world.is_alive(c));
+    assert!(world.is_alive(b));
+}
+
+#[test]
+fn batches_commands_and_clear_call_hooks() {
+    let mut world = World::default();
+    let created = world.create_batch([
+        ShipEntity {
+            body: BodyHandle(1),
+        },
+        ShipEntity {
+            body: BodyHandle(2),
+        },
+    ]);
+    let mut commands = Commands::new();
+    commands.create(ShipEntity {
+        body: BodyHandle(3),
+    });
+    commands.destroy(created[0]);
+    world.apply(commands);
+
+    let events = take_events();
+    assert_eq!(events.len(), 4);
+    assert_eq!(events[3], ("destroy", created[0].entity(), 1));
+
+    world.clear();
+    let mut freed: Vec<u32> = take_events()
+        .into_iter()
+        .map(|(kind, _, body)| {
+            assert_eq!(kind, "destroy");
+            body
+        })
+        .collect();
+    freed.sort();
+    assert_eq!(freed, vec![2, 3]);
+}
+
+#[test]
+fn take_and_migrate_call_hooks() {
+    let mut world = World::default();
+    let a = world.create(ShipEntity {
+        body: BodyHandle(1),
+    });
+    let b = world.create(ShipEntity {
+        body: BodyHandle(2),
+    });
+    take_events();
+
+    assert!(world.take(a).is_some());
+    assert_eq!(take_events(), vec![("destroy", a.entity(), 1)]);
+
+    // the old type's on_destroy sees the components before they are moved
+    let wreck = world.migrate(b, WreckEntity::default()).unwrap();
+    assert_eq!(take_events(), vec![("destroy", b.entity(), 2)]);
+
+    // and the new type's on_create sees them after, with the new handle
+    let ship_again = world.migrate(wreck, ShipEntity::default()).unwrap();
+    assert_eq!(take_events(), vec![("create", ship_again.entity(), 2)]);
+    assert!(world.is_alive(ship_again));
+}
+
+#[test]
+fn destroy_hooks_run_with_removal_tracking() {
+    let mut world = World::default();
+    let rock = world.create(RockEntity {
+        body: BodyHandle(9),
+    });
+    world.destroy(rock);
+
+    assert_eq!(take_events(), vec![("rock", rock.entity(), 9)]);
+    assert_eq!(world.removed::<RockEntity>().len(), 1);
+}
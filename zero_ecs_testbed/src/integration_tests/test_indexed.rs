@@ -0,0 +1,184 @@
+use zero_ecs::*;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Name(&'static str);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Team(u32);
+
+#[derive(Debug, PartialEq)]
+pub struct Health(i32);
+
+#[entity]
+pub struct EnemyEntity {
+    #[indexed]
+    name: Name,
+    #[indexed]
+    team: Team,
+    health: Health,
+}
+
+#[entity]
+pub struct NpcEntity {
+    #[indexed]
+    name: Name,
+    team: Team,
+}
+
+ecs_world!(EnemyEntity, NpcEntity);
+
+make_query!(QueryNames, Name);
+make_query!(QueryHealth, Health);
+
+#[system_for_each(World)]
+fn damage(health: &mut Health, team: &Team) {
+    health.0 -= team.0 as i32;
+}
+
+#[test]
+fn find_by_looks_up_entities_of_every_type() {
+    let mut world = World::default();
+
+    let goblin = world.create(EnemyEntity {
+        name: Name("goblin"),
+        team: Team(3),
+        health: Health(10),
+    });
+    let orc = world.create(EnemyEntity {
+        name: Name("orc"),
+        team: Team(3),
+        health: Health(10),
+    });
+    world.create(EnemyEntity {
+        name: Name("troll"),
+        team: Team(1),
+        health: Health(10),
+    });
+    let smith = world.create(NpcEntity {
+        name: Name("goblin"),
+        team: Team(3),
+    });
+
+    assert_eq!(
+        world.find_by(&Name("goblin")),
+        vec![goblin.entity(), smith.entity()]
+    );
+    // the npc's team isn't indexed
+    assert_eq!(world.find_by(&Team(3)), vec![goblin.entity(), orc.entity()]);
+    assert!(world.find_by(&Name("dragon")).is_empty());
+
+    // queries can still read indexed components
+    assert_eq!(world.with_query(Query::<QueryNames>::new()).len(), 4);
+    world.damage();
+    let QueryHealth(health) = world.get(orc).unwrap();
+    assert_eq!(*health, Health(7));
+}
+
+#[test]
+fn destroy_take_and_clear_remove_entities_from_the_index() {
+    let mut world = World::default();
+
+    let goblin = world.create(EnemyEntity {
+        name: Name("goblin"),
+        team: Team(3),
+        health: Health(10),
+    });
+    let orc = world.create(EnemyEntity {
+        name: Name("orc"),
+        team: Team(3),
+        health: Health(10),
+    });
+    let troll = world.create(EnemyEntity {
+        name: Name("troll"),
+        team: Team(3),
+        health: Health(10),
+    });
+
+    world.destroy(goblin);
+    assert!(world.find_by(&Name("goblin")).is_empty());
+    // the troll was swap-removed into the goblin's place
+    assert_eq!(world.find_by(&Name("troll")), vec![troll.entity()]);
+    let team = world.find_by(&Team(3));
+    assert_eq!(team.len(), 2);
+    assert!(team.contains(&orc.entity()) && team.contains(&troll.entity()));
+
+    let taken = world.take(orc).unwrap();
+    assert_eq!(taken.name, Name("orc"));
+    assert_eq!(world.find_by(&Team(3)), vec![troll.entity()]);
+
+    world.clear();
+    assert!(world.find_by(&Team(3)).is_empty());
+}
+
+#[test]
+fn indexed_mut_and_set_update_the_index() {
+    let mut world = World::default();
+
+    let goblin = world.create(EnemyEntity {
+        name: Name("goblin"),
+        team: Team(3),
+        health: Health(10),
+    });
+    let smith = world.create(NpcEntity {
+        name: Name("smith"),
+        team: Team(0),
+    });
+
+    {
+        let mut team = world.indexed_mut::<Team>(goblin).unwrap();
+        team.0 = 4;
+    }
+    assert!(world.find_by(&Team(3)).is_empty());
+    assert_eq!(world.find_by(&Team(4)), vec![goblin.entity()]);
+
+    // the npc's team isn't indexed
+    assert!(world.indexed_mut::<Team>(smith).is_none());
+
+    assert!(world.set(smith, Name("baker")));
+    assert!(world.find_by(&Name("smith")).is_empty());
+    assert_eq!(world.find_by(&Name("baker")), vec![smith.entity()]);
+
+    let mut commands = Commands::default();
+    commands.set(goblin, Name("hobgoblin"));
+    world.apply(commands);
+    assert_eq!(world.find_by(&Name("hobgoblin")), vec![goblin.entity()]);
+    assert!(world.find_by(&Name("goblin")).is_empty());
+}
+
+#[test]
+fn migrate_moves_the_entity_between_indexes() {
+    let mut world = World::default();
+
+    let goblin = world.create(EnemyEntity {
+        name: Name("goblin"),
+        team: Team(3),
+        health: Health(10),
+    });
+    let npc = world
+        .migrate(
+            goblin,
+            NpcEntity {
+                name: Name("unused"),
+                team: Team(0),
+            },
+        )
+        .unwrap();
+
+    assert_eq!(world.find_by(&Name("goblin")), vec![npc.entity()]);
+    assert!(world.find_by(&Name("unused")).is_empty());
+    assert!(world.find_by(&Team(3)).is_empty());
+
+    let enemy = world
+        .migrate(
+            npc,
+            EnemyEntity {
+                name: Name("unused"),
+                team: Team(5),
+                health: Health(1),
+            },
+        )
+        .unwrap();
+    assert_eq!(world.find_by(&Name("goblin")), vec![enemy.entity()]);
+    assert_eq!(world.find_by(&Team(3)), vec![enemy.entity()]);
+    assert!(world.find_by(&Team(5)).is_empty());
+}
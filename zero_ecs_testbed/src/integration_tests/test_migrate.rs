@@ -0,0 +1,103 @@
+use zero_ecs::*;
+
+#[derive(Debug, Default, PartialEq)]
+pub struct Position(i32, i32);
+
+#[derive(Debug, Default, PartialEq)]
+pub struct Health(i32);
+
+#[derive(Default)]
+pub struct EnemyComponent;
+
+#[derive(Debug, Default, PartialEq)]
+pub struct CorpseTimer(f32);
+
+#[entity]
+#[derive(Default)]
+pub struct EnemyEntity {
+    position: Position,
+    health: Health,
+    enemy_component: EnemyComponent,
+}
+
+#[entity]
+#[derive(Default)]
+pub struct DeadEnemyEntity {
+    position: Position,
+    corpse_timer: CorpseTimer,
+}
+
+ecs_world!(EnemyEntity, DeadEnemyEntity);
+
+make_query!(QueryPosition, Position);
+
+make_query!(QueryHealth, Health);
+
+make_query!(QueryPositionCorpseTimer, Position, CorpseTimer);
+
+#[test]
+fn migrate_moves_shared_components() {
+    let mut world = World::default();
+
+    let enemy = world.create(EnemyEntity {
+        position: Position(3, 4),
+        health: Health(0),
+        ..Default::default()
+    });
+    let other = world.create(EnemyEntity {
+        position: Position(1, 1),
+        health: Health(10),
+        ..Default::default()
+    });
+
+    let dead = world
+        .migrate::<EnemyEntity, DeadEnemyEntity>(
+            enemy,
+            DeadEnemyEntity {
+                corpse_timer: CorpseTimer(5.0),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+    assert_eq!(dead.entity().entity_type, EntityType::DeadEnemyEntity);
+    assert!(world
+        .with_query(Query::<QueryPosition>::new())
+        .get::<QueryPosition>(enemy)
+        .is_none());
+
+    let query = world.with_query(Query::<QueryPositionCorpseTimer>::new());
+    assert_eq!(query.len(), 1);
+    let QueryPositionCorpseTimer(position, corpse_timer) = query.get(dead).unwrap();
+    assert_eq!(*position, Position(3, 4));
+    assert_eq!(*corpse_timer, CorpseTimer(5.0));
+
+    let query = world.with_query(Query::<QueryHealth>::new());
+    assert_eq!(query.len(), 1);
+    let QueryHealth(health) = query.get(other).unwrap();
+    assert_eq!(*health, Health(10));
+}
+
+#[test]
+fn migrate_rejects_stale_and_wrong_type_handles() {
+    let mut world = World::default();
+
+    let enemy = world.create(EnemyEntity::default());
+    let dead = world
+        .migrate::<EnemyEntity, DeadEnemyEntity>(enemy, DeadEnemyEntity::default())
+        .unwrap();
+
+    assert!(world
+        .migrate::<EnemyEntity, DeadEnemyEntity>(enemy, DeadEnemyEntity::default())
+        .is_none());
+    assert!(world
+        .migrate::<EnemyEntity, DeadEnemyEntity>(dead.entity(), DeadEnemyEntity::default())
+        .is_none());
+    assert_eq!(world.with_query(Query::<QueryPosition>::new()).len(), 1);
+
+    let revived = world
+        .migrate::<DeadEnemyEntity, EnemyEntity>(dead, EnemyEntity::default())
+        .unwrap();
+    assert_eq!(revived.entity().entity_type, EntityType::EnemyEntity);
+    assert_eq!(world.with_query(Query::<QueryPosition>::new()).len(), 1);
+}
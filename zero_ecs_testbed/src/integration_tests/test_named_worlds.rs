@@ -0,0 +1,107 @@
+use zero_ecs::*;
+
+#[derive(Default, Debug, PartialEq)]
+pub struct Position(i32);
+
+#[derive(Default, Debug, PartialEq)]
+pub struct Health(i32);
+
+#[derive(Default, Debug, PartialEq)]
+pub struct Label(&'static str);
+
+#[derive(Default)]
+pub struct Tick(u32);
+
+#[entity]
+pub struct Unit {
+    position: Position,
+    health: Health,
+}
+
+#[entity]
+pub struct Building {
+    position: Position,
+}
+
+#[entity]
+pub struct Button {
+    position: Position,
+    label: Label,
+}
+
+ecs_world!(SimWorld: Unit, Building; resources: Tick);
+ecs_world!(UiWorld: Button, Unit);
+
+make_query!(SimWorld: SimPositions, Position);
+make_query!(UiWorld: UiPositions, Position);
+make_query!(UiWorld: UiLabels, Label);
+
+#[system_for_each(SimWorld)]
+fn fall(position: &mut Position, tick: &mut Tick) {
+    position.0 -= 1;
+    tick.0 += 1;
+}
+
+#[system(UiWorld)]
+fn relabel(
+    world: &mut UiWorld,
+    buttons: Query<(&mut Label, &Position)>,
+    commands: &mut Commands,
+    new_unit: Position,
+) {
+    world
+        .with_query_mut(buttons)
+        .iter_mut()
+        .for_each(|(label, position)| {
+            if position.0 > 0 {
+                *label = Label("right");
+            }
+        });
+    commands.create(Unit {
+        position: new_unit,
+        health: Health(1),
+    });
+}
+
+#[test]
+fn named_worlds_live_side_by_side() {
+    let mut sim = SimWorld::default();
+    let mut ui = UiWorld::default();
+
+    let unit: sim_world::TypedEntity<Unit> = sim.create(Unit {
+        position: Position(10),
+        health: Health(3),
+    });
+    sim.create(Building {
+        position: Position(20),
+    });
+    let button = ui.create(Button {
+        position: Position(5),
+        label: Label("left"),
+    });
+
+    assert_eq!(unit.entity().entity_type, sim_world::EntityType::Unit);
+    assert_eq!(button.entity().entity_type, ui_world::EntityType::Button);
+    assert_eq!(sim.len(), 2);
+    assert_eq!(ui.len(), 1);
+
+    sim.fall();
+    assert_eq!(sim.resource::<Tick>().0, 2);
+    let SimPositions(position) = sim.get(unit).unwrap();
+    assert_eq!(*position, Position(9));
+    assert_eq!(
+        sim.with_query(sim_world::Query::<SimPositions>::new())
+            .len(),
+        2
+    );
+    assert_eq!(
+        ui.with_query(ui_world::Query::<UiPositions>::new()).len(),
+        1
+    );
+
+    ui.relabel(Position(7));
+    let UiLabels(label) = ui.get(button).unwrap();
+    assert_eq!(*label, Label("right"));
+    assert_eq!(ui.len_of(ui_world::EntityType::Unit), 1);
+    assert_eq!(sim.len_of(sim_world::EntityType::Unit), 1);
+}
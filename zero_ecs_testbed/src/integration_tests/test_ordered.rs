@@ -0,0 +1,91 @@
+use zero_ecs::*;
+
+pub struct Name(&'static str);
+
+pub struct Slot(usize);
+
+#[entity(ordered)]
+pub struct ListItemEntity {
+    name: Name,
+    slot: Slot,
+}
+
+#[entity]
+pub struct ParticleEntity {
+    name: Name,
+}
+
+ecs_world!(ListItemEntity, ParticleEntity);
+
+make_query!(QueryName, Name);
+
+fn list_item_names(world: &World) -> Vec<&'static str> {
+    world
+        .__list_item_entity
+        .name
+        .iter()
+        .map(|name| name.0)
+        .collect()
+}
+
+fn particle_names(world: &World) -> Vec<&'static str> {
+    world
+        .__particle_entity
+        .name
+        .iter()
+        .map(|name| name.0)
+        .collect()
+}
+
+#[test]
+fn ordered_destroy_keeps_creation_order() {
+    let mut world = World::default();
+
+    let entities: Vec<_> = ["a", "b", "c", "d", "e"]
+        .into_iter()
+        .enumerate()
+        .map(|(i, name)| {
+            world.create(ListItemEntity {
+                name: Name(name),
+                slot: Slot(i),
+            })
+        })
+        .collect();
+
+    world.destroy(entities[1]);
+    world.destroy(entities[3]);
+    assert_eq!(list_item_names(&world), vec!["a", "c", "e"]);
+
+    for (i, entity) in entities.iter().enumerate() {
+        let alive = i != 1 && i != 3;
+        assert_eq!(world.is_alive(*entity), alive);
+        if alive {
+            let index = world.__list_item_entity.index_of(*entity).unwrap();
+            assert_eq!(world.__list_item_entity.entity[index], *entity);
+            assert_eq!(world.__list_item_entity.slot[index].0, i);
+        }
+    }
+
+    let taken = world.take::<ListItemEntity>(entities[0]).unwrap();
+    assert_eq!(taken.name.0, "a");
+    assert_eq!(list_item_names(&world), vec!["c", "e"]);
+    let QueryName(name) = world.get(entities[4]).unwrap();
+    assert_eq!(name.0, "e");
+}
+
+#[test]
+fn unordered_destroy_swaps_in_the_last_entity() {
+    let mut world = World::default();
+
+    let entities: Vec<_> = ["a", "b", "c", "d"]
+        .into_iter()
+        .map(|name| world.create(ParticleEntity { name: Name(name) }))
+        .collect();
+
+    world.destroy(entities[0]);
+    assert_eq!(particle_names(&world), vec!["d", "b", "c"]);
+
+    let QueryName(name) = world.get(entities[3]).unwrap();
+    assert_eq!(name.0, "d");
+    assert_eq!(world.__particle_entity.index_of(entities[3]), Some(0));
+}
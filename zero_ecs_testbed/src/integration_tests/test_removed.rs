@@ -0,0 +1,234 @@
+use zero_ecs::*;
+
+#[derive(Debug, Default, PartialEq)]
+pub struct Health(i32);
+
+#[derive(Debug, Default, PartialEq)]
+pub struct BodyHandle(u32);
+
+#[derive(Debug, PartialEq)]
+pub struct Emitter(u32);
+
+#[entity(track_removed)]
+#[derive(Default)]
+pub struct EnemyEntity {
+    health: Health,
+    #[keep_removed]
+    body: BodyHandle,
+    #[sparse]
+    #[keep_removed]
+    emitter: Option<Emitter>,
+}
+
+#[entity(track_removed)]
+#[derive(Default)]
+pub struct BulletEntity {
+    health: Health,
+}
+
+#[entity]
+#[derive(Default)]
+pub struct PlayerEntity {
+    health: Health,
+}
+
+ecs_world!(EnemyEntity, BulletEntity, PlayerEntity);
+
+#[system(World)]
+fn free_bodies(world: &World, removed: Removed<EnemyEntity>, freed: &mut Vec<u32>) {
+    assert!(!world.is_empty());
+    freed.extend(
+        removed
+            .iter()
+            .filter_map(|enemy| enemy.body.as_ref())
+            .map(|body| body.0),
+    );
+}
+
+#[system(World)]
+fn destroy_dead(
+    world: &mut World,
+    removed: Removed<EnemyEntity>,
+    dead: Query<(&Entity, &Health)>,
+    seen: &mut usize,
+) {
+    *seen = removed.len();
+    let dead: Vec<Entity> = world
+        .with_query(dead)
+        .iter()
+        .filter(|(_, health)| health.0 <= 0)
+        .map(|(entity, _)| *entity)
+        .collect();
+    for entity in dead {
+        world.destroy(entity);
+    }
+}
+
+#[test]
+fn destroyed_entities_are_recorded_with_kept_components() {
+    let mut world = World::default();
+    let a = world
+        .create(EnemyEntity {
+            health: Health(10),
+            body: BodyHandle(1),
+            emitter: Some(Emitter(7)),
+        })
+        .entity();
+    let b = world
+        .create(EnemyEntity {
+            health: Health(10),
+            body: BodyHandle(2),
+            emitter: None,
+        })
+        .entity();
+    world.create(EnemyEntity {
+        health: Health(10),
+        body: BodyHandle(3),
+        emitter: None,
+    });
+
+    world.destroy(b);
+    world.destroy(a);
+    // destroying a stale handle records nothing
+    world.destroy(a);
+
+    let removed = world.removed::<EnemyEntity>();
+    assert_eq!(removed.len(), 2);
+    assert_eq!(removed[0].entity, b);
+    assert_eq!(removed[0].migrated_to, None);
+    assert_eq!(removed[0].body, Some(BodyHandle(2)));
+    assert_eq!(removed[0].emitter, None);
+    assert_eq!(removed[1].entity, a);
+    assert_eq!(removed[1].body, Some(BodyHandle(1)));
+    assert_eq!(removed[1].emitter, Some(Emitter(7)));
+
+    world.clear_trackers();
+    assert!(world.removed::<EnemyEntity>().is_empty());
+}
+
+#[test]
+fn taken_and_migrated_entities_are_removed_without_kept_components() {
+    let mut world = World::default();
+    let a = world.create(EnemyEntity {
+        body: BodyHandle(1),
+        ..Default::default()
+    });
+    let b = world.create(EnemyEntity::default());
+    let bullet = world.create(BulletEntity::default());
+
+    // the kept components go to the caller instead
+    assert_eq!(world.take(a).unwrap().body, BodyHandle(1));
+    let player = world.migrate(b, PlayerEntity::default()).unwrap();
+
+    let removed = world.removed::<EnemyEntity>();
+    assert_eq!(removed.len(), 2);
+    assert_eq!(
+        (removed[0].entity, removed[0].migrated_to),
+        (a.entity(), None)
+    );
+    assert_eq!(removed[0].body, None);
+    assert_eq!(
+        (removed[1].entity, removed[1].migrated_to),
+        (b.entity(), Some(player.entity()))
+    );
+    assert_eq!(removed[1].body, None);
+
+    world.destroy(bullet);
+    let removed: Vec<Entity> = world
+        .removed::<BulletEntity>()
+        .iter()
+        .map(|bullet| bullet.entity)
+        .collect();
+    assert_eq!(removed, vec![bullet.entity()]);
+}
+
+#[test]
+fn clear_and_commands_record_every_destroyed_entity() {
+    let mut world = World::default();
+    let a = world
+        .create(EnemyEntity {
+            health: Health(10),
+            body: BodyHandle(1),
+            emitter: None,
+        })
+        .entity();
+    let b = world
+        .create(EnemyEntity {
+            health: Health(10),
+            body: BodyHandle(2),
+            emitter: None,
+        })
+        .entity();
+    let c = world
+        .create(EnemyEntity {
+            health: Health(10),
+            body: BodyHandle(3),
+            emitter: None,
+        })
+        .entity();
+    world.create(PlayerEntity::default());
+
+    let mut commands = Commands::new();
+    commands.destroy(b);
+    world.apply(commands);
+    world.clear_type(EntityType::EnemyEntity);
+
+    let mut removed: Vec<Entity> = world
+        .removed::<EnemyEntity>()
+        .iter()
+        .map(|enemy| enemy.entity)
+        .collect();
+    assert_eq!(removed.remove(0), b);
+    removed.sort_by_key(|entity| entity.id);
+    assert_eq!(removed, vec![a, c]);
+
+    world.clear_trackers();
+    world.create(EnemyEntity {
+        health: Health(10),
+        body: BodyHandle(4),
+        emitter: None,
+    });
+    world.clear();
+    assert_eq!(world.removed::<EnemyEntity>().len(), 1);
+    assert_eq!(world.removed::<EnemyEntity>()[0].body, Some(BodyHandle(4)));
+}
+
+#[test]
+fn systems_read_removed_entities() {
+    let mut world = World::default();
+    let a = world
+        .create(EnemyEntity {
+            health: Health(10),
+            body: BodyHandle(1),
+            emitter: None,
+        })
+        .entity();
+    world.create(EnemyEntity {
+        health: Health(10),
+        body: BodyHandle(2),
+        emitter: None,
+    });
+    let c = world
+        .create(EnemyEntity {
+            health: Health(0),
+            body: BodyHandle(3),
+            emitter: None,
+        })
+        .entity();
+    world.destroy(a);
+
+    let mut freed = vec![];
+    world.free_bodies(&mut freed);
+    assert_eq!(freed, vec![1]);
+
+    // the system gets what was removed before it ran, and what it destroys is added after
+    let mut seen = 0;
+    world.destroy_dead(&mut seen);
+    assert_eq!(seen, 1);
+    let removed: Vec<Entity> = world
+        .removed::<EnemyEntity>()
+        .iter()
+        .map(|enemy| enemy.entity)
+        .collect();
+    assert_eq!(removed, vec![a, c]);
+}
@@ -0,0 +1,146 @@
+use zero_ecs::*;
+
+#[derive(Default)]
+pub struct Position(f32);
+
+#[derive(Default)]
+pub struct Velocity(f32);
+
+#[derive(Default)]
+pub struct DeltaTime(f32);
+
+#[derive(Default)]
+pub struct Score(usize);
+
+#[entity]
+#[derive(Default)]
+pub struct EnemyEntity {
+    position: Position,
+    velocity: Velocity,
+}
+
+ecs_world!(EnemyEntity; resources: DeltaTime, Score);
+
+#[system_for_each(World)]
+fn apply_velocity(position: &mut Position, velocity: &Velocity, delta_time: &DeltaTime) {
+    position.0 += velocity.0 * delta_time.0;
+}
+
+#[system_for_each(World)]
+fn count_enemies(_position: &Position, score: &mut Score) {
+    score.0 += 1;
+}
+
+#[system(World)]
+fn score_far_enemies(world: &mut World, positions: Query<&Position>, score: &mut Score) {
+    // the system gets the world's entities, the resource is borrowed next to them
+    let far = world
+        .with_query(positions)
+        .iter()
+        .filter(|position: &&Position| position.0 > 5.0)
+        .count();
+    score.0 += far;
+}
+
+#[system(World)]
+fn sum_positions(world: &World, positions: Query<&Position>, delta_time: &DeltaTime, bonus: usize) {
+    assert_eq!(delta_time.0, 2.0);
+    let sum: f32 = world
+        .with_query(positions)
+        .iter()
+        .map(|position: &Position| position.0)
+        .sum();
+    assert_eq!(sum as usize + bonus, 16);
+}
+
+#[system(World)]
+fn destroy_slow(
+    world: &mut World,
+    velocities: Query<(&Entity, &Velocity)>,
+    commands: &mut Commands,
+    score: &mut Score,
+) {
+    let mut destroyed = 0;
+    world.with_query_mut(velocities).iter_mut().for_each(
+        |(entity, velocity): (&Entity, &Velocity)| {
+            if velocity.0 < 2.0 {
+                commands.destroy(*entity);
+                destroyed += 1;
+            }
+        },
+    );
+    score.0 += destroyed * 100;
+}
+
+#[system_for_each(World)]
+fn score_and_move(position: &mut Position, delta_time: &DeltaTime, score: &mut Score) {
+    // borrowed next to the query, not taken out of the world
+    position.0 += delta_time.0;
+    score.0 += 1;
+}
+
+fn create_world() -> World {
+    let mut world = World::default();
+    world.create(EnemyEntity {
+        velocity: Velocity(1.0),
+        ..Default::default()
+    });
+    world.create(EnemyEntity {
+        velocity: Velocity(3.0),
+        ..Default::default()
+    });
+    *world.resource_mut::<DeltaTime>() = DeltaTime(2.0);
+    world
+}
+
+#[test]
+fn system_for_each_borrows_resources_from_the_world() {
+    let mut world = create_world();
+
+    world.apply_velocity();
+    world.count_enemies();
+
+    let mut positions: Vec<_> = world
+        .__enemy_entity
+        .position
+        .iter()
+        .map(|position| position.0)
+        .collect();
+    positions.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    assert_eq!(positions, vec![2.0, 6.0]);
+    assert_eq!(world.resource::<Score>().0, 2);
+}
+
+#[test]
+fn system_borrows_resources_alongside_queries() {
+    let mut world = create_world();
+
+    world.apply_velocity();
+    world.score_far_enemies();
+    assert_eq!(world.resource::<Score>().0, 1);
+
+    world.sum_positions(8);
+
+    world.destroy_slow();
+    assert_eq!(world.resource::<Score>().0, 101);
+    assert_eq!(world.len(), 1);
+}
+
+#[test]
+fn system_for_each_borrows_resources_next_to_mutable_queries() {
+    let mut world = create_world();
+    world.resource_mut::<Score>().0 = 10;
+
+    world.score_and_move();
+    world.score_and_move();
+
+    assert_eq!(world.resource::<Score>().0, 14);
+    assert_eq!(world.resource::<DeltaTime>().0, 2.0);
+    let positions: Vec<_> = world
+        .__enemy_entity
+        .position
+        .iter()
+        .map(|position| position.0)
+        .collect();
+    assert_eq!(positions, vec![4.0, 4.0]);
+}
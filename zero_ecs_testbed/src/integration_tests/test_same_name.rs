@@ -0,0 +1,162 @@
+use zero_ecs::*;
+
+pub mod physics {
+    #[derive(Default, Debug, PartialEq, Clone, Copy)]
+    pub struct Velocity(pub i32);
+
+    #[derive(Default, Debug, PartialEq)]
+    pub struct Frozen;
+}
+
+pub mod render {
+    #[derive(Default, Debug, PartialEq, Clone, Copy)]
+    pub struct Velocity(pub f32);
+
+    #[derive(Default, Debug, PartialEq)]
+    pub struct Frozen;
+}
+
+#[derive(Default, Debug, PartialEq)]
+pub struct Position(i32);
+
+#[entity]
+pub struct Ship {
+    position: Position,
+    velocity: physics::Velocity,
+    #[sparse]
+    frozen: Option<physics::Frozen>,
+}
+
+#[entity]
+pub struct Sprite {
+    position: Position,
+    velocity: render::Velocity,
+    #[sparse]
+    frozen: Option<render::Frozen>,
+}
+
+ecs_world!(Ship, Sprite);
+
+make_query!(QueryPhysics, Entity, physics::Velocity);
+make_query!(QueryRender, Entity, render::Velocity);
+make_query!(QueryPhysicsMut, mut physics::Velocity);
+make_query!(QueryMoving, Entity, Position; Without<physics::Frozen>);
+make_query!(QueryRenderFrozen, Entity; With<render::Frozen>);
+
+#[system_for_each(World)]
+fn drift(position: &mut Position, velocity: &render::Velocity) {
+    position.0 += velocity.0 as i32;
+}
+
+fn ship(velocity: i32) -> Ship {
+    Ship {
+        position: Position(0),
+        velocity: physics::Velocity(velocity),
+        frozen: None,
+    }
+}
+
+fn sprite(velocity: f32) -> Sprite {
+    Sprite {
+        position: Position(0),
+        velocity: render::Velocity(velocity),
+        frozen: None,
+    }
+}
+
+#[test]
+fn types_with_the_same_name_are_different_components() {
+    let mut world = World::default();
+
+    let ship: Entity = world.create(ship(3)).into();
+    let sprite: Entity = world.create(sprite(1.5)).into();
+
+    let physics: Vec<_> = world
+        .with_query(Query::<QueryPhysics>::new())
+        .iter()
+        .map(|QueryPhysics(entity, velocity)| (*entity, *velocity))
+        .collect();
+    assert_eq!(physics, vec![(ship, physics::Velocity(3))]);
+    let render: Vec<_> = world
+        .with_query(Query::<QueryRender>::new())
+        .iter()
+        .map(|QueryRender(entity, velocity)| (*entity, *velocity))
+        .collect();
+    assert_eq!(render, vec![(sprite, render::Velocity(1.5))]);
+    assert_eq!(world.with_query(Query::<QueryRender>::new()).len(), 1);
+
+    assert!(world.get::<QueryPhysics>(sprite).is_none());
+    world
+        .with_query_mut(Query::<QueryPhysicsMut>::new())
+        .iter_mut()
+        .for_each(|QueryPhysicsMut(velocity)| velocity.0 += 1);
+    let QueryPhysics(_, velocity) = world.get(ship).unwrap();
+    assert_eq!(*velocity, physics::Velocity(4));
+
+    world.drift();
+    let QueryMoving(_, position) = world.get(ship).unwrap();
+    assert_eq!(*position, Position(0));
+    let QueryMoving(_, position) = world.get(sprite).unwrap();
+    assert_eq!(*position, Position(1));
+}
+
+#[test]
+fn types_with_the_same_name_can_be_set_inserted_and_filtered() {
+    let mut world = World::default();
+
+    let ship: Entity = world.create(ship(3)).into();
+    let sprite: Entity = world.create(sprite(1.5)).into();
+
+    assert!(world.set(ship, physics::Velocity(5)));
+    assert!(!world.set(ship, render::Velocity(5.0)));
+    assert!(world.set(sprite, render::Velocity(2.5)));
+    let mut commands = Commands::new();
+    commands.set(ship, physics::Velocity(6));
+    commands.set(sprite, physics::Velocity(6));
+    world.apply(commands);
+    let QueryPhysics(_, velocity) = world.get(ship).unwrap();
+    assert_eq!(*velocity, physics::Velocity(6));
+    let QueryRender(_, velocity) = world.get(sprite).unwrap();
+    assert_eq!(*velocity, render::Velocity(2.5));
+
+    // a render::Frozen sprite still moves as far as physics is concerned
+    assert!(!world.insert(ship, render::Frozen));
+    assert!(world.insert(sprite, render::Frozen));
+    let moving: Vec<_> = world
+        .with_query(Query::<QueryMoving>::new())
+        .iter()
+        .map(|QueryMoving(entity, _)| *entity)
+        .collect();
+    assert_eq!(moving.len(), 2);
+    assert!(moving.contains(&ship) && moving.contains(&sprite));
+    assert!(world.insert(ship, physics::Frozen));
+    let moving: Vec<_> = world
+        .with_query(Query::<QueryMoving>::new())
+        .iter()
+        .map(|QueryMoving(entity, _)| *entity)
+        .collect();
+    assert_eq!(moving, vec![sprite]);
+    let frozen: Vec<_> = world
+        .with_query(Query::<QueryRenderFrozen>::new())
+        .iter()
+        .map(|QueryRenderFrozen(entity)| *entity)
+        .collect();
+    assert_eq!(frozen, vec![sprite]);
+
+    assert_eq!(world.remove::<render::Frozen>(ship), None);
+    assert_eq!(world.remove::<physics::Frozen>(ship), Some(physics::Frozen));
+}
+
+#[test]
+fn migrating_keeps_the_passed_component_of_the_same_name() {
+    let mut world = World::default();
+
+    let ship = world.create(ship(3));
+    world.set(ship, Position(7));
+    let sprite = world.migrate(ship, sprite(1.5)).unwrap();
+
+    let QueryRender(_, velocity) = world.get(sprite).unwrap();
+    assert_eq!(*velocity, render::Velocity(1.5));
+    let QueryMoving(_, position) = world.get(sprite).unwrap();
+    assert_eq!(*position, Position(7));
+}
@@ -23,7 +23,12 @@ fn assert_value(world: &World, values: Query<&Value>, expected: usize) {
 }
 
 #[system(World)]
-fn assert_value_for(world: &World, values: Query<&Value>, expected: usize, entity: Entity) {
+fn assert_value_for(
+    world: &World,
+    values: Query<&Value>,
+    expected: usize,
+    entity: TypedEntity<ValueEntity>,
+) {
     let values = world.with_query(values);
     let value: &Value = values.get(entity).unwrap();
 
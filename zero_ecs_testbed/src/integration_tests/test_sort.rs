@@ -0,0 +1,111 @@
+use zero_ecs::*;
+
+#[derive(Default)]
+pub struct Layer(i32);
+
+#[derive(Default)]
+pub struct Name(&'static str);
+
+#[derive(Default)]
+pub struct Glow(i32);
+
+#[entity]
+#[derive(Default)]
+pub struct EnemyEntity {
+    layer: Layer,
+    name: Name,
+    #[sparse]
+    glow: Option<Glow>,
+}
+
+#[entity]
+#[derive(Default)]
+pub struct PlayerEntity {
+    layer: Layer,
+}
+
+ecs_world!(EnemyEntity, PlayerEntity);
+
+make_query!(QueryName, Name);
+make_query!(QueryNameGlow, Name, Glow);
+
+fn enemy(layer: i32, name: &'static str) -> EnemyEntity {
+    EnemyEntity {
+        layer: Layer(layer),
+        name: Name(name),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn sort_by_key_reorders_iteration() {
+    let mut world = World::default();
+
+    world.create(enemy(3, "c"));
+    world.create(enemy(1, "a"));
+    world.create(enemy(2, "b"));
+    world.create(enemy(1, "a2"));
+
+    world.sort_by_key::<EnemyEntity, _>(|enemy| enemy.layer.0);
+
+    let names: Vec<_> = world
+        .with_query(Query::<QueryName>::new())
+        .iter()
+        .map(|QueryName(name)| name.0)
+        .collect();
+    // stable, "a" was created before "a2"
+    assert_eq!(names, vec!["a", "a2", "b", "c"]);
+}
+
+#[test]
+fn sort_by_key_keeps_handles_valid() {
+    let mut world = World::default();
+
+    let entities: Vec<_> = (0..20)
+        .map(|i| world.create(enemy((i * 7) % 20, "")))
+        .collect();
+    world.destroy(entities[5]);
+    let glowing = entities[11];
+    world.insert(glowing, Glow(4));
+
+    world.sort_by_key::<EnemyEntity, i32>(|enemy| -enemy.layer.0);
+
+    for (i, entity) in entities.iter().enumerate() {
+        let layer = world.__enemy_entity.index_of(*entity).map(|index| {
+            assert_eq!(world.__enemy_entity.entity[index], *entity);
+            world.__enemy_entity.layer[index].0
+        });
+        if i == 5 {
+            assert_eq!(layer, None);
+        } else {
+            assert_eq!(layer, Some((i as i32 * 7) % 20));
+        }
+    }
+
+    let layers: Vec<_> = world.__enemy_entity.layer.iter().map(|l| l.0).collect();
+    let mut sorted = layers.clone();
+    sorted.sort_by_key(|layer| -layer);
+    assert_eq!(layers, sorted);
+
+    assert_eq!(world.with_query(Query::<QueryNameGlow>::new()).len(), 1);
+    let QueryNameGlow(_, glow) = world.get(glowing).unwrap();
+    assert_eq!(glow.0, 4);
+}
+
+#[test]
+fn sort_by_key_sees_sparse_components() {
+    let mut world = World::default();
+
+    let plain = world.create(enemy(0, "plain"));
+    let glowing = world.create(EnemyEntity {
+        glow: Some(Glow(1)),
+        ..enemy(0, "glowing")
+    });
+    world.create(PlayerEntity::default());
+
+    world.sort_by_key::<EnemyEntity, _>(|enemy| enemy.glow.is_none());
+
+    assert_eq!(world.__enemy_entity.index_of(glowing), Some(0));
+    assert_eq!(world.__enemy_entity.index_of(plain), Some(1));
+    assert_eq!(world.__enemy_entity.entity[0], glowing);
+}
@@ -0,0 +1,254 @@
+use zero_ecs::*;
+
+#[derive(Debug, Default, PartialEq)]
+pub struct Health(i32);
+
+#[derive(Debug, PartialEq)]
+pub struct Burning(i32);
+
+#[derive(Debug, PartialEq)]
+pub struct Frozen;
+
+#[entity]
+#[derive(Default)]
+pub struct EnemyEntity {
+    health: Health,
+    #[sparse]
+    burning: Option<Burning>,
+    #[sparse]
+    frozen: Option<Frozen>,
+}
+
+#[entity]
+#[derive(Default)]
+pub struct DeadEnemyEntity {
+    #[sparse]
+    burning: Option<Burning>,
+}
+
+ecs_world!(EnemyEntity, DeadEnemyEntity);
+
+make_query!(QueryHealth, Health);
+
+make_query!(QueryHealthBurning, Health, Burning);
+
+make_query!(QueryEntityBurning, Entity, Burning);
+
+make_query!(QueryHealthMutBurning, mut Health, Burning);
+
+make_query!(QueryBurningMutFrozen, mut Burning, Frozen);
+
+#[system_for_each(World)]
+fn burn(health: &mut Health, burning: &Burning) {
+    health.0 -= burning.0;
+}
+
+#[system_for_each(World)]
+fn cool_down(burning: &mut Burning) {
+    burning.0 -= 1;
+}
+
+fn create_enemies(world: &mut World) -> Vec<Entity> {
+    (0..6)
+        .map(|i| {
+            world
+                .create(EnemyEntity {
+                    health: Health(100),
+                    burning: (i % 2 == 0).then_some(Burning(i)),
+                    frozen: (i % 3 == 0).then_some(Frozen),
+                })
+                .entity()
+        })
+        .collect()
+}
+
+#[test]
+fn queries_only_match_entities_with_the_sparse_component() {
+    let mut world = World::default();
+    let enemies = create_enemies(&mut world);
+
+    assert_eq!(world.with_query(Query::<QueryHealth>::new()).len(), 6);
+
+    let query = world.with_query(Query::<QueryHealthBurning>::new());
+    assert_eq!(query.len(), 3);
+    let burning: Vec<i32> = query.iter().map(|QueryHealthBurning(_, b)| b.0).collect();
+    assert_eq!(burning, vec![0, 2, 4]);
+
+    assert!(query.get::<QueryHealthBurning>(enemies[1]).is_none());
+    let QueryHealthBurning(_, burning) = query.get(enemies[2]).unwrap();
+    assert_eq!(*burning, Burning(2));
+    let QueryHealthBurning(_, burning) = query.at(1).unwrap();
+    assert_eq!(*burning, Burning(2));
+
+    // both sparse components: only enemy 0
+    let mut query = world.with_query_mut(Query::<QueryBurningMutFrozen>::new());
+    assert_eq!(query.len(), 1);
+}
+
+#[test]
+fn systems_mutate_dense_and_sparse_components() {
+    let mut world = World::default();
+    let enemies = create_enemies(&mut world);
+
+    world.burn();
+    world.cool_down();
+
+    let query = world.with_query(Query::<QueryHealth>::new());
+    let health: Vec<i32> = enemies
+        .iter()
+        .map(|e| query.get::<QueryHealth>(*e).unwrap().0 .0)
+        .collect();
+    assert_eq!(health, vec![100, 100, 98, 100, 96, 100]);
+
+    let query = world.with_query(Query::<QueryEntityBurning>::new());
+    let mut burning: Vec<(Entity, i32)> = query
+        .iter()
+        .map(|QueryEntityBurning(e, b)| (*e, b.0))
+        .collect();
+    burning.sort_by_key(|(_, b)| *b);
+    assert_eq!(
+        burning,
+        vec![(enemies[0], -1), (enemies[2], 1), (enemies[4], 3)]
+    );
+}
+
+#[test]
+fn mutable_sparse_iteration_follows_swap_removes() {
+    let mut world = World::default();
+    let enemies = create_enemies(&mut world);
+
+    // moves the last enemy into the first slot, out of order with the sparse storage
+    world.destroy(enemies[0]);
+    world.insert(enemies[5], Burning(50));
+
+    world
+        .with_query_mut(Query::<QueryHealthMutBurning>::new())
+        .iter_mut()
+        .for_each(|QueryHealthMutBurning(health, burning)| {
+            health.0 = burning.0;
+        });
+
+    let query = world.with_query(Query::<QueryHealth>::new());
+    for (entity, expected) in [(enemies[2], 2), (enemies[4], 4), (enemies[5], 50)] {
+        let QueryHealth(health) = query.get(entity).unwrap();
+        assert_eq!(health.0, expected);
+    }
+
+    let count = world
+        .with_query_mut(Query::<QueryHealthMutBurning>::new())
+        .par_iter_mut()
+        .map(|QueryHealthMutBurning(health, _)| health.0)
+        .count();
+    assert_eq!(count, 3);
+}
+
+#[test]
+fn insert_remove_and_take_sparse_components() {
+    let mut world = World::default();
+    let enemies = create_enemies(&mut world);
+
+    assert!(world.insert(enemies[1], Burning(7)));
+    assert_eq!(world.remove::<Burning>(enemies[0]), Some(Burning(0)));
+    assert_eq!(world.remove::<Burning>(enemies[0]), None);
+    assert!(world.set(enemies[3], Burning(9)));
+
+    let query = world.with_query(Query::<QueryHealthBurning>::new());
+    assert_eq!(query.len(), 4);
+
+    let taken = world.take::<EnemyEntity>(enemies[2]).unwrap();
+    assert_eq!(taken.burning, Some(Burning(2)));
+    assert_eq!(taken.frozen, None);
+    assert!(!world.insert(enemies[2], Burning(1)));
+
+    let taken = world.take::<EnemyEntity>(enemies[3]).unwrap();
+    assert_eq!(taken.burning, Some(Burning(9)));
+    assert_eq!(taken.frozen, Some(Frozen));
+
+    assert_eq!(
+        world.with_query(Query::<QueryHealthBurning>::new()).len(),
+        2
+    );
+}
+
+#[test]
+fn migrate_moves_sparse_components() {
+    let mut world = World::default();
+    let enemies = create_enemies(&mut world);
+
+    let dead = world
+        .migrate::<EnemyEntity, DeadEnemyEntity>(enemies[4], DeadEnemyEntity::default())
+        .unwrap();
+    let query = world.with_query(Query::<QueryEntityBurning>::new());
+    let QueryEntityBurning(_, burning) = query.get(dead).unwrap();
+    assert_eq!(*burning, Burning(4));
+
+    let dead = world
+        .migrate::<EnemyEntity, DeadEnemyEntity>(
+            enemies[1],
+            DeadEnemyEntity {
+                burning: Some(Burning(1)),
+            },
+        )
+        .unwrap();
+    assert!(world
+        .with_query(Query::<QueryEntityBurning>::new())
+        .get::<QueryEntityBurning>(dead)
+        .is_none());
+}
+
+#[test]
+fn parallel_queries_match_the_serial_ones() {
+    let mut world = World::default();
+    let enemies: Vec<Entity> = (0..1000)
+        .map(|i| {
+            world
+                .create(EnemyEntity {
+                    health: Health(i),
+                    burning: (i % 2 == 0).then_some(Burning(i)),
+                    frozen: (i % 3 == 0).then_some(Frozen),
+                })
+                .entity()
+        })
+        .collect();
+
+    // shuffles the rows and the sparse values out of order
+    for entity in enemies.iter().step_by(7) {
+        world.destroy(*entity);
+    }
+    for entity in enemies.iter().skip(1).step_by(10) {
+        world.insert(*entity, Burning(-1));
+    }
+
+    let query = world.with_query(Query::<QueryEntityBurning>::new());
+    let serial: Vec<(Entity, i32)> = query
+        .iter()
+        .map(|QueryEntityBurning(e, b)| (*e, b.0))
+        .collect();
+    let parallel: Vec<(Entity, i32)> = query
+        .par_iter()
+        .map(|QueryEntityBurning(e, b)| (*e, b.0))
+        .collect();
+    assert_eq!(parallel, serial);
+
+    world
+        .with_query_mut(Query::<QueryHealthMutBurning>::new())
+        .par_iter_mut()
+        .for_each(|QueryHealthMutBurning(health, burning)| {
+            health.0 = burning.0;
+        });
+    world
+        .with_query_mut(Query::<QueryBurningMutFrozen>::new())
+        .par_iter_mut()
+        .for_each(|QueryBurningMutFrozen(burning, _)| {
+            burning.0 += 1000;
+        });
+
+    let query = world.with_query(Query::<QueryHealthBurning>::new());
+    assert_eq!(query.len(), serial.len());
+    for (entity, burning) in serial {
+        let frozen = (enemies.iter().position(|e| *e == entity).unwrap()) % 3 == 0;
+        let QueryHealthBurning(health, b) = query.get(entity).unwrap();
+        assert_eq!(health.0, burning);
+        assert_eq!(b.0, if frozen { burning + 1000 } else { burning });
+    }
+}
@@ -0,0 +1,243 @@
+use zero_ecs::*;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Position(f32, f32);
+
+impl SpatialPoint for Position {
+    fn point(&self) -> [f32; 2] {
+        [self.0, self.1]
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Hits(u32);
+
+pub struct EnemyComponent;
+pub struct PlayerComponent;
+
+#[entity]
+pub struct EnemyEntity {
+    position: Position,
+    enemy: EnemyComponent,
+}
+
+#[entity]
+pub struct PlayerEntity {
+    position: Position,
+    player: PlayerComponent,
+    hits: Hits,
+}
+
+#[entity]
+pub struct BeaconEntity {
+    #[sparse]
+    position: Option<Position>,
+}
+
+ecs_world!(EnemyEntity, PlayerEntity, BeaconEntity; spatial: Position);
+
+make_query!(QueryPositions, Position);
+make_query!(QueryEnemies, Entity, Position, EnemyComponent);
+
+#[system(World)]
+fn count_hits(
+    world: &mut World,
+    players: Query<(&Entity, &Position, &PlayerComponent)>,
+    enemies: Query<(&Position, &EnemyComponent)>,
+) {
+    let near: Vec<(Entity, usize)> = world
+        .with_query(players)
+        .iter()
+        .map(|(entity, position, _)| {
+            let min = [position.0 - 1.0, position.1 - 1.0];
+            let max = [position.0 + 1.0, position.1 + 1.0];
+            (
+                *entity,
+                world
+                    .query_aabb::<_, (&Position, &EnemyComponent)>(enemies, min, max)
+                    .count(),
+            )
+        })
+        .collect();
+    for (player, count) in near {
+        world.set(player, Hits(count as u32));
+    }
+}
+
+#[test]
+fn query_radius_matches_checking_every_entity() {
+    let mut world = World::default();
+    for x in -10..10 {
+        for y in -10..10 {
+            world.create(EnemyEntity {
+                position: Position(x as f32 * 0.7, y as f32 * 1.3),
+                enemy: EnemyComponent,
+            });
+        }
+    }
+    world.rebuild_spatial_hash(2.0);
+
+    let query = Query::<QueryPositions>::new();
+    for (center, radius) in [([0.0, 0.0], 1.5), ([-3.2, 4.1], 2.5), ([6.0, -6.0], 0.1)] {
+        let mut found: Vec<Position> = world
+            .query_radius(query, center, radius)
+            .map(|QueryPositions(position)| *position)
+            .collect();
+        let mut expected: Vec<Position> = world
+            .with_query(query)
+            .iter()
+            .map(|QueryPositions(position)| *position)
+            .filter(|position| {
+                let (dx, dy) = (position.0 - center[0], position.1 - center[1]);
+                dx * dx + dy * dy <= radius * radius
+            })
+            .collect();
+        // the hash returns them cell by cell
+        for positions in [&mut found, &mut expected] {
+            positions.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.total_cmp(&b.1)));
+        }
+        assert_eq!(found, expected);
+    }
+}
+
+#[test]
+fn only_entities_matching_the_query_are_returned() {
+    let mut world = World::default();
+    let near = world.create(EnemyEntity {
+        position: Position(1.0, 1.0),
+        enemy: EnemyComponent,
+    });
+    world.create(EnemyEntity {
+        position: Position(10.0, 10.0),
+        enemy: EnemyComponent,
+    });
+    world.create(PlayerEntity {
+        position: Position(1.5, 1.0),
+        player: PlayerComponent,
+        hits: Hits(0),
+    });
+    world.create(BeaconEntity {
+        position: Some(Position(0.5, 0.5)),
+    });
+    world.create(BeaconEntity { position: None });
+    world.rebuild_spatial_hash(4.0);
+
+    let found: Vec<Entity> = world
+        .query_aabb(Query::<QueryEnemies>::new(), [0.0, 0.0], [2.0, 2.0])
+        .map(|QueryEnemies(entity, _, _)| *entity)
+        .collect();
+    assert_eq!(found, vec![near.entity()]);
+    assert_eq!(
+        world
+            .query_aabb::<_, QueryPositions>(Query::<QueryPositions>::new(), [0.0, 0.0], [2.0, 2.0])
+            .count(),
+        3
+    );
+}
+
+#[test]
+fn the_hash_is_as_of_the_last_rebuild() {
+    let mut world = World::default();
+    let moving = world.create(EnemyEntity {
+        position: Position(0.0, 0.0),
+        enemy: EnemyComponent,
+    });
+    let destroyed = world.create(EnemyEntity {
+        position: Position(0.5, 0.0),
+        enemy: EnemyComponent,
+    });
+    world.rebuild_spatial_hash(1.0);
+
+    world.destroy(destroyed);
+    world.set(moving, Position(5.0, 5.0));
+    let query = Query::<QueryPositions>::new();
+    // the destroyed entity is skipped, the moved one is found where it was
+    assert_eq!(
+        world
+            .query_radius::<_, QueryPositions>(query, [0.0, 0.0], 1.0)
+            .count(),
+        1
+    );
+    assert_eq!(
+        world
+            .query_radius::<_, QueryPositions>(query, [5.0, 5.0], 1.0)
+            .count(),
+        0
+    );
+
+    world.rebuild_spatial_hash(1.0);
+    assert_eq!(
+        world
+            .query_radius::<_, QueryPositions>(query, [0.0, 0.0], 1.0)
+            .count(),
+        0
+    );
+    assert_eq!(
+        world
+            .query_radius::<_, QueryPositions>(query, [5.0, 5.0], 1.0)
+            .count(),
+        1
+    );
+}
+
+#[test]
+fn boxes_much_larger_than_the_cells_only_check_filled_cells() {
+    let mut world = World::default();
+    let near = world.create(EnemyEntity {
+        position: Position(0.5, 0.5),
+        enemy: EnemyComponent,
+    });
+    world.create(EnemyEntity {
+        position: Position(5e5, 0.0),
+        enemy: EnemyComponent,
+    });
+    world.rebuild_spatial_hash(1.0);
+
+    let query = Query::<QueryPositions>::new();
+    let count = |world: &World, min: [f32; 2], max: [f32; 2]| {
+        world
+            .query_aabb::<_, QueryPositions>(query, min, max)
+            .count()
+    };
+    assert_eq!(count(&world, [-2e5, -2e5], [2e5, 2e5]), 1);
+    assert_eq!(count(&world, [f32::NEG_INFINITY; 2], [f32::INFINITY; 2]), 2);
+    assert_eq!(
+        world
+            .query_radius::<_, QueryPositions>(query, [0.0, 0.0], f32::INFINITY)
+            .count(),
+        2
+    );
+
+    let found: Vec<Entity> = world
+        .query_aabb(Query::<QueryEnemies>::new(), [-2e5, -2e5], [2e5, 2e5])
+        .map(|QueryEnemies(entity, _, _)| *entity)
+        .collect();
+    assert_eq!(found, vec![near.entity()]);
+}
+
+#[test]
+fn systems_can_query_nearby_entities() {
+    let mut world = World::default();
+    world.create(EnemyEntity {
+        position: Position(0.0, 0.0),
+        enemy: EnemyComponent,
+    });
+    world.create(EnemyEntity {
+        position: Position(0.5, -0.5),
+        enemy: EnemyComponent,
+    });
+    world.create(EnemyEntity {
+        position: Position(3.0, 0.0),
+        enemy: EnemyComponent,
+    });
+    let player = world.create(PlayerEntity {
+        position: Position(0.0, 0.0),
+        player: PlayerComponent,
+        hits: Hits(0),
+    });
+    world.rebuild_spatial_hash(0.5);
+
+    world.count_hits();
+    let player = world.take(player).unwrap();
+    assert_eq!(player.hits, Hits(2));
+}
@@ -790,11 +790,11 @@ fn test_delete_multiple_entities_and_verify_correct_ones() {
         }
     }
 
-    assert!(found.contains(&e1));
-    assert!(!found.contains(&e2));
-    assert!(found.contains(&e3));
-    assert!(!found.contains(&e4));
-    assert!(found.contains(&e5));
+    assert!(found.contains(&e1.entity()));
+    assert!(!found.contains(&e2.entity()));
+    assert!(found.contains(&e3.entity()));
+    assert!(!found.contains(&e4.entity()));
+    assert!(found.contains(&e5.entity()));
 }
 
 #[test]
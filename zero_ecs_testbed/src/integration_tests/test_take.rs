@@ -0,0 +1,67 @@
+use zero_ecs::*;
+
+#[derive(Debug, PartialEq)]
+pub struct Health(i32);
+
+#[derive(Debug, PartialEq)]
+pub struct Inventory(Vec<&'static str>);
+
+#[entity]
+pub struct EnemyEntity {
+    health: Health,
+    inventory: Inventory,
+}
+
+#[entity]
+pub struct LootEntity {
+    inventory: Inventory,
+}
+
+ecs_world!(EnemyEntity, LootEntity);
+
+make_query!(QueryInventory, Inventory);
+
+#[test]
+fn take_returns_the_entity_and_removes_it() {
+    let mut world = World::default();
+
+    let goblin = world.create(EnemyEntity {
+        health: Health(0),
+        inventory: Inventory(vec!["dagger", "coin"]),
+    });
+    let orc = world.create(EnemyEntity {
+        health: Health(10),
+        inventory: Inventory(vec!["axe"]),
+    });
+
+    let taken = world.take::<EnemyEntity>(goblin).unwrap();
+    assert_eq!(taken.health, Health(0));
+    assert_eq!(taken.inventory, Inventory(vec!["dagger", "coin"]));
+
+    let loot = world.create(LootEntity {
+        inventory: taken.inventory,
+    });
+
+    let query = world.with_query(Query::<QueryInventory>::new());
+    assert_eq!(query.len(), 2);
+    assert!(query.get::<QueryInventory>(goblin).is_none());
+
+    let QueryInventory(inventory) = query.get(orc).unwrap();
+    assert_eq!(inventory.0, vec!["axe"]);
+    let QueryInventory(inventory) = query.get(loot).unwrap();
+    assert_eq!(inventory.0, vec!["dagger", "coin"]);
+}
+
+#[test]
+fn take_rejects_wrong_type_and_stale_handles() {
+    let mut world = World::default();
+
+    let enemy = world.create(EnemyEntity {
+        health: Health(5),
+        inventory: Inventory(vec![]),
+    });
+
+    assert!(world.take::<LootEntity>(enemy.entity()).is_none());
+    assert!(world.take::<EnemyEntity>(enemy).is_some());
+    assert!(world.take::<EnemyEntity>(enemy).is_none());
+}
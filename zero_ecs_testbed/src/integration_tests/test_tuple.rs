@@ -0,0 +1,87 @@
+use zero_ecs::*;
+
+#[derive(Default, Debug, PartialEq, Clone, Copy)]
+pub struct Position(i32, i32);
+
+#[derive(Default, Debug, PartialEq)]
+pub struct MarkerTag(&'static str);
+
+#[derive(Default, Debug, PartialEq)]
+pub struct Label(&'static str);
+
+#[entity]
+pub struct Marker(Position, MarkerTag, #[sparse] Option<Label>);
+
+#[entity]
+pub struct Player {
+    position: Position,
+}
+
+ecs_world!(Marker, Player);
+
+make_query!(QueryPosition, Position);
+make_query!(QueryMarker, Position, MarkerTag);
+
+#[system_for_each(World)]
+fn move_right(position: &mut Position) {
+    position.0 += 1;
+}
+
+#[test]
+fn tuple_entities_are_stored_in_positional_columns() {
+    let mut world = World::default();
+
+    let marker = world.create(Marker(Position(1, 2), MarkerTag("spawn"), None));
+    world.create(Marker(
+        Position(3, 4),
+        MarkerTag("exit"),
+        Some(Label("Exit")),
+    ));
+    world.create(Player {
+        position: Position(0, 0),
+    });
+
+    assert_eq!(world.__marker.field_0, vec![Position(1, 2), Position(3, 4)]);
+    assert_eq!(world.__marker.field_1[1], MarkerTag("exit"));
+    assert_eq!(world.__marker.field_2.len(), 1);
+
+    world.move_right();
+    assert_eq!(world.with_query(Query::<QueryPosition>::new()).len(), 3);
+    assert_eq!(world.with_query(Query::<QueryMarker>::new()).len(), 2);
+
+    let QueryMarker(position, tag) = world.get(marker).unwrap();
+    assert_eq!(*position, Position(2, 2));
+    assert_eq!(*tag, MarkerTag("spawn"));
+
+    world.insert(marker, Label("Spawn"));
+    let taken = world.take(marker).unwrap();
+    assert_eq!(taken.0, Position(2, 2));
+    assert_eq!(taken.1, MarkerTag("spawn"));
+    assert_eq!(taken.2, Some(Label("Spawn")));
+
+    assert_eq!(world.len_of(EntityType::Marker), 1);
+    world.sort_by_key::<Marker, _>(|marker| marker.field_0.0);
+}
+
+#[test]
+fn tuple_entities_can_be_destroyed_and_migrated() {
+    let mut world = World::default();
+
+    let marker = world.create(Marker(Position(5, 6), MarkerTag("a"), None));
+    let player = world
+        .migrate(
+            marker,
+            Player {
+                position: Position::default(),
+            },
+        )
+        .unwrap();
+    assert!(!world.is_alive(marker));
+    let QueryPosition(position) = world.get(player).unwrap();
+    assert_eq!(*position, Position(5, 6));
+
+    let marker = world.create(Marker(Position(0, 0), MarkerTag("b"), None));
+    world.destroy(marker);
+    assert!(!world.is_alive(marker));
+    assert_eq!(world.len(), 1);
+}
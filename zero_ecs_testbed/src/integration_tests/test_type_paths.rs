@@ -0,0 +1,138 @@
+use zero_ecs::*;
+
+pub mod physics {
+    #[derive(Default, Debug, PartialEq, Clone, Copy)]
+    pub struct Velocity(pub i32);
+}
+
+use physics::Velocity;
+
+#[derive(Default, Debug, PartialEq, Clone, Copy)]
+pub struct Waypoint(i32);
+
+#[derive(Default, Debug, PartialEq)]
+pub struct Target(usize);
+
+#[derive(Default, Debug, PartialEq)]
+pub struct Smoothed<T>(T);
+
+#[entity]
+pub struct Walker {
+    velocity: physics::Velocity,
+    waypoints: Vec<Waypoint>,
+    target: Option<Target>,
+    speed: Smoothed<f32>,
+    #[sparse]
+    limit: Option<Smoothed<i32>>,
+}
+
+#[entity]
+pub struct Turret {
+    velocity: Velocity,
+    target: Option<Target>,
+    speed: Smoothed<i32>,
+}
+
+ecs_world!(Walker, Turret);
+
+make_query!(QueryWaypoints, Vec<Waypoint>);
+make_query!(QueryVelocity, physics::Velocity);
+make_query!(QueryTarget, mut Option<Target>);
+make_query!(QuerySmoothedFloat, Smoothed<f32>);
+make_query!(QuerySmoothedInt, Smoothed<i32>);
+
+#[system_for_each(World)]
+fn accelerate(velocity: &mut Velocity, waypoints: &mut Vec<Waypoint>) {
+    if let Some(waypoint) = waypoints.pop() {
+        velocity.0 += waypoint.0;
+    }
+}
+
+#[system(World)]
+fn retarget(world: &mut World, query: Query<(&mut Option<Target>, &physics::Velocity)>) {
+    world
+        .with_query_mut(query)
+        .iter_mut()
+        .for_each(|(target, velocity)| *target = Some(Target(velocity.0 as usize)));
+}
+
+fn walker() -> Walker {
+    Walker {
+        velocity: Velocity(0),
+        waypoints: vec![Waypoint(1), Waypoint(2)],
+        target: None,
+        speed: Smoothed(1.5),
+        limit: None,
+    }
+}
+
+#[test]
+fn components_match_by_path_and_generic_arguments() {
+    let mut world = World::default();
+
+    let walker = world.create(walker());
+    let turret = world.create(Turret {
+        velocity: Velocity(10),
+        target: None,
+        speed: Smoothed(3),
+    });
+
+    // module paths are ignored, both entities have a Velocity
+    assert_eq!(world.with_query(Query::<QueryVelocity>::new()).len(), 2);
+    assert_eq!(world.with_query_mut(Query::<QueryTarget>::new()).len(), 2);
+    // generic arguments are not, only walkers have a Vec<Waypoint> and a Smoothed<f32>
+    assert_eq!(world.with_query(Query::<QueryWaypoints>::new()).len(), 1);
+    assert_eq!(
+        world.with_query(Query::<QuerySmoothedFloat>::new()).len(),
+        1
+    );
+    assert_eq!(world.with_query(Query::<QuerySmoothedInt>::new()).len(), 1);
+
+    world.accelerate();
+    let QueryVelocity(velocity) = world.get(walker).unwrap();
+    assert_eq!(*velocity, Velocity(2));
+    let QueryVelocity(velocity) = world.get(turret).unwrap();
+    assert_eq!(*velocity, Velocity(10));
+
+    world.retarget();
+    let QueryTarget(target) = world.get_mut(walker).unwrap();
+    assert_eq!(*target, Some(Target(2)));
+    let QueryTarget(target) = world.get_mut(turret).unwrap();
+    assert_eq!(*target, Some(Target(10)));
+}
+
+#[test]
+fn generic_components_can_be_set_inserted_and_migrated() {
+    let mut world = World::default();
+
+    let walker = world.create(walker());
+    assert!(world.set(walker, vec![Waypoint(7)]));
+    let QueryWaypoints(waypoints) = world.get(walker).unwrap();
+    assert_eq!(*waypoints, vec![Waypoint(7)]);
+
+    let mut commands = Commands::new();
+    commands.set(walker, Smoothed(2.5f32));
+    world.apply(commands);
+    let QuerySmoothedFloat(speed) = world.get(walker).unwrap();
+    assert_eq!(*speed, Smoothed(2.5));
+
+    assert!(world.insert(walker, Smoothed(4)));
+    assert_eq!(world.remove::<Smoothed<i32>>(walker), Some(Smoothed(4)));
+
+    world.set(walker, Some(Target(3)));
+    world.set(walker, Velocity(5));
+    let turret = world
+        .migrate(
+            walker,
+            Turret {
+                velocity: Velocity::default(),
+                target: None,
+                speed: Smoothed(0),
+            },
+        )
+        .unwrap();
+    let QueryTarget(target) = world.get_mut(turret).unwrap();
+    assert_eq!(*target, Some(Target(3)));
+    let QueryVelocity(velocity) = world.get(turret).unwrap();
+    assert_eq!(*velocity, Velocity(5));
+}
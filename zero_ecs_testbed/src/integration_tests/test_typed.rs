@@ -0,0 +1,130 @@
+use zero_ecs::*;
+
+#[derive(Default, Debug, PartialEq)]
+pub struct Health(i32);
+
+#[derive(Default)]
+pub struct Value(usize);
+
+#[entity]
+#[derive(Default)]
+pub struct EnemyEntity {
+    health: Health,
+    value: Value,
+}
+
+#[entity]
+#[derive(Default)]
+pub struct CoinEntity {
+    value: Value,
+}
+
+ecs_world!(EnemyEntity, CoinEntity);
+
+make_query!(QueryHealth, Health);
+make_query!(QueryValue, Value);
+make_query!(QueryValueMut, mut Value);
+
+#[system(World)]
+fn assert_enemy_health(
+    world: &World,
+    enemy: TypedEntity<EnemyEntity>,
+    healths: Query<&Health>,
+    expected: i32,
+) {
+    let healths = world.with_query(healths);
+    let health: &Health = healths.get(enemy).unwrap();
+    assert_eq!(health.0, expected);
+}
+
+#[test]
+fn create_returns_typed_handle_that_converts_to_entity() {
+    let mut world = World::default();
+
+    let enemy: TypedEntity<EnemyEntity> = world.create(EnemyEntity {
+        health: Health(3),
+        ..Default::default()
+    });
+    let entity: Entity = enemy.into();
+
+    assert_eq!(entity.entity_type, EntityType::EnemyEntity);
+    assert_eq!(enemy, entity);
+    assert_eq!(enemy.id(), entity.id);
+
+    let QueryHealth(health) = world.get(enemy).unwrap();
+    assert_eq!(*health, Health(3));
+    let QueryHealth(health) = world.get(entity).unwrap();
+    assert_eq!(*health, Health(3));
+}
+
+#[test]
+fn untyped_entity_can_be_typed_back() {
+    let mut world = World::default();
+
+    let enemy = world.create(EnemyEntity::default());
+    let coin = world.create(CoinEntity::default());
+    let entities: Vec<Entity> = vec![enemy.into(), coin.into()];
+
+    let enemies: Vec<TypedEntity<EnemyEntity>> = entities
+        .iter()
+        .filter_map(|entity| entity.typed())
+        .collect();
+    assert_eq!(enemies, vec![enemy]);
+
+    let coins: Vec<TypedEntity<CoinEntity>> = entities
+        .iter()
+        .filter_map(|entity| entity.typed())
+        .collect();
+    assert_eq!(coins, vec![coin]);
+}
+
+#[test]
+fn typed_handles_look_in_their_own_collection() {
+    let mut world = World::default();
+
+    let coin = world.create(CoinEntity { value: Value(5) });
+    let enemy = world.create(EnemyEntity {
+        health: Health(1),
+        value: Value(10),
+    });
+    // same id, but another collection
+    assert_eq!(coin.id(), enemy.id());
+
+    let QueryValue(value) = world.get(coin).unwrap();
+    assert_eq!(value.0, 5);
+    let QueryValue(value) = world.get(enemy).unwrap();
+    assert_eq!(value.0, 10);
+
+    let query = world.with_query(Query::<QueryValue>::new());
+    let QueryValue(value) = query.get(coin).unwrap();
+    assert_eq!(value.0, 5);
+
+    let QueryValueMut(value) = world.get_mut(enemy).unwrap();
+    value.0 += 1;
+    let QueryValue(value) = world.get(enemy).unwrap();
+    assert_eq!(value.0, 11);
+
+    world.assert_enemy_health(enemy, 1);
+}
+
+#[test]
+fn take_destroy_and_migrate_infer_the_entity_type() {
+    let mut world = World::default();
+
+    let enemy = world.create(EnemyEntity {
+        health: Health(7),
+        value: Value(3),
+    });
+    let taken = world.take(enemy).unwrap();
+    assert_eq!(taken.health, Health(7));
+    assert!(!world.is_alive(enemy));
+
+    let enemy = world.create(taken);
+    let coin: TypedEntity<CoinEntity> = world.migrate(enemy, CoinEntity::default()).unwrap();
+    assert!(!world.is_alive(enemy));
+    let QueryValue(value) = world.get(coin).unwrap();
+    assert_eq!(value.0, 3);
+
+    world.destroy(coin);
+    assert!(world.is_empty());
+}
@@ -0,0 +1,212 @@
+use zero_ecs::*;
+
+#[derive(Debug, Default, PartialEq)]
+pub struct Position(i32);
+
+#[derive(Debug, Default)]
+pub struct Velocity(i32);
+
+#[derive(Debug, Default)]
+pub struct PlayerComponent;
+
+#[derive(Debug, Default)]
+pub struct EnemyComponent;
+
+#[derive(Debug, Default, PartialEq)]
+pub struct Health(i32);
+
+#[derive(Debug, Default)]
+pub struct Burning;
+
+#[entity]
+#[derive(Default)]
+pub struct PlayerEntity {
+    position: Position,
+    velocity: Velocity,
+    player: PlayerComponent,
+}
+
+#[entity]
+#[derive(Default)]
+pub struct EnemyEntity {
+    position: Position,
+    velocity: Velocity,
+    enemy: EnemyComponent,
+}
+
+#[entity]
+#[derive(Default)]
+pub struct RockEntity {
+    position: Position,
+    velocity: Velocity,
+}
+
+#[entity]
+#[derive(Default)]
+pub struct TreeEntity {
+    health: Health,
+    #[sparse]
+    burning: Option<Burning>,
+}
+
+ecs_world!(PlayerEntity, EnemyEntity, RockEntity, TreeEntity);
+
+make_query!(QueryNotPlayers, Entity; Without<PlayerComponent>);
+make_query!(QueryEnemies, Entity, Position; With<EnemyComponent>);
+make_query!(QueryPosition, Position);
+make_query!(QueryBurning, Health; With<Burning>);
+make_query!(QueryStanding, Entity, Health; Without<Burning>);
+make_query!(QueryHealth, Health);
+
+#[system(World)]
+fn move_non_players(
+    world: &mut World,
+    query: Query<(&mut Position, &Velocity), Without<PlayerComponent>>,
+) {
+    world
+        .with_query_mut(query)
+        .iter_mut()
+        .for_each(|(position, velocity)| position.0 += velocity.0);
+}
+
+#[system(World)]
+fn count_enemies(world: &World, query: Query<&Position, With<EnemyComponent>>, count: &mut usize) {
+    *count = world.with_query(query).len();
+}
+
+#[system(World)]
+fn count_burning(world: &World, query: Query<&Health, With<Burning>>, count: &mut usize) {
+    *count = world.with_query(query).len();
+}
+
+#[system_for_each(World, With<Burning>)]
+fn burn(health: &mut Health) {
+    health.0 -= 1;
+}
+
+#[system_for_each(World, Without<PlayerComponent>, Without<EnemyComponent>)]
+fn push_rocks(position: &mut Position) {
+    position.0 += 100;
+}
+
+#[test]
+fn queries_skip_entity_types_with_or_without_a_component() {
+    let mut world = World::default();
+    let player = world
+        .create(PlayerEntity {
+            velocity: Velocity(1),
+            ..Default::default()
+        })
+        .entity();
+    let enemy = world
+        .create(EnemyEntity {
+            velocity: Velocity(2),
+            ..Default::default()
+        })
+        .entity();
+    let rock = world
+        .create(RockEntity {
+            velocity: Velocity(3),
+            ..Default::default()
+        })
+        .entity();
+
+    let not_players: Vec<Entity> = world
+        .with_query(Query::<QueryNotPlayers>::new())
+        .iter()
+        .map(|QueryNotPlayers(entity)| *entity)
+        .collect();
+    assert!(!not_players.contains(&player));
+    assert_eq!(not_players.len(), 2);
+    assert!(not_players.contains(&enemy) && not_players.contains(&rock));
+
+    let query = world.with_query(Query::<QueryEnemies>::new());
+    assert_eq!(query.len(), 1);
+    let QueryEnemies(entity, position) = query.at(0).unwrap();
+    assert_eq!((*entity, position), (enemy, &Position(0)));
+    let missing: Option<QueryEnemies> = query.get(player);
+    assert!(missing.is_none());
+}
+
+#[test]
+fn systems_accept_with_and_without() {
+    let mut world = World::default();
+    let player = world
+        .create(PlayerEntity {
+            velocity: Velocity(1),
+            ..Default::default()
+        })
+        .entity();
+    let enemy = world
+        .create(EnemyEntity {
+            velocity: Velocity(2),
+            ..Default::default()
+        })
+        .entity();
+    let rock = world
+        .create(RockEntity {
+            velocity: Velocity(3),
+            ..Default::default()
+        })
+        .entity();
+
+    world.move_non_players();
+    let mut count = 0;
+    world.count_enemies(&mut count);
+    assert_eq!(count, 1);
+
+    world.push_rocks();
+
+    let position = |world: &World, entity: Entity| {
+        world
+            .with_query(Query::<QueryPosition>::new())
+            .get(entity)
+            .map(|QueryPosition(position)| position.0)
+    };
+    assert_eq!(position(&world, player), Some(0));
+    assert_eq!(position(&world, enemy), Some(2));
+    assert_eq!(position(&world, rock), Some(103));
+}
+
+#[test]
+fn sparse_components_are_checked_per_entity() {
+    let mut world = World::default();
+    let burning = world
+        .create(TreeEntity {
+            health: Health(10),
+            burning: Some(Burning),
+        })
+        .entity();
+    let standing = world
+        .create(TreeEntity {
+            health: Health(10),
+            burning: None,
+        })
+        .entity();
+
+    let mut count = 0;
+    world.count_burning(&mut count);
+    assert_eq!(count, 1);
+
+    let query = world.with_query(Query::<QueryBurning>::new());
+    assert_eq!(query.len(), 1);
+    assert!(query.get::<QueryBurning>(burning).is_some());
+    assert!(query.get::<QueryBurning>(standing).is_none());
+
+    let query = world.with_query(Query::<QueryStanding>::new());
+    assert_eq!(query.len(), 1);
+    let QueryStanding(entity, health) = query.at(0).unwrap();
+    assert_eq!((*entity, health), (standing, &Health(10)));
+    assert!(query.get::<QueryStanding>(burning).is_none());
+
+    world.burn();
+    world.burn();
+    let health = |world: &World, entity: Entity| {
+        world
+            .with_query(Query::<QueryHealth>::new())
+            .get(entity)
+            .map(|QueryHealth(health)| health.0)
+    };
+    assert_eq!(health(&world, burning), Some(8));
+    assert_eq!(health(&world, standing), Some(10));
+}
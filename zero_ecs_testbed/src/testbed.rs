@@ -1,12 +1,17 @@
 mod integration_tests;
 
-use std::collections::HashSet;
-
 use zero_ecs::*;
 
 #[derive(Default)]
 struct Position(f32, f32);
 
+// lets the world keep a spatial hash of positions, see collide_enemy_and_players
+impl SpatialPoint for Position {
+    fn point(&self) -> [f32; 2] {
+        [self.0, self.1]
+    }
+}
+
 #[derive(Default)]
 struct Velocity(f32, f32);
 
@@ -71,32 +76,30 @@ fn apply_velocity(position: &mut Position, velocity: &Velocity, delta_time: &Del
 // it's fine to nest queries.
 #[system(World)]
 fn collide_enemy_and_players(
-    world: &mut World, // we are destroying entities so it needs to be mutable
+    world: &World, // destroys are queued on commands, so world can be immutable
     players: Query<(&Entity, &Position, &PlayerComponent)>, // include the Entity to be able to identify entities
     enemies: Query<(&Entity, &Position, &EnemyComponent)>,  // same but for enemies
+    commands: &mut Commands, // queued destroys are applied when the system returns
 ) {
-    let mut entities_to_destroy: HashSet<Entity> = HashSet::new(); // we can't (for obvious reasons) destroy entities from within an iteration.
-
     world
         .with_query(players)
         .iter()
         .for_each(|(player_entity, player_position, _)| {
+            // only the enemies near the player are visited, not every enemy. The box includes
+            // its edges, a collision doesn't
+            let min = [player_position.0 - 3.0, player_position.1 - 3.0];
+            let max = [player_position.0 + 3.0, player_position.1 + 3.0];
             world
-                .with_query(enemies)
-                .iter()
-                .for_each(|(enemy_entity, enemy_position, _)| {
-                    if (player_position.0 - enemy_position.0).abs() < 3.0
+                .query_aabb(enemies, min, max)
+                .filter(|(_, enemy_position, _)| {
+                    (player_position.0 - enemy_position.0).abs() < 3.0
                         && (player_position.1 - enemy_position.1).abs() < 3.0
-                    {
-                        entities_to_destroy.insert(*player_entity);
-                        entities_to_destroy.insert(*enemy_entity);
-                    }
+                })
+                .for_each(|(enemy_entity, _, _)| {
+                    commands.destroy(*player_entity);
+                    commands.destroy(*enemy_entity);
                 });
         });
-
-    for entity in entities_to_destroy {
-        world.destroy(entity);
-    }
 }
 
 struct CompanionComponent {
@@ -109,8 +112,8 @@ struct CompanionEntity {
     companion_component: CompanionComponent,
 }
 
-// Defines the world, must include all entities.
-ecs_world!(EnemyEntity, PlayerEntity, CompanionEntity);
+// Defines the world, must include all entities. It keeps a spatial hash of positions.
+ecs_world!(EnemyEntity, PlayerEntity, CompanionEntity; spatial: Position);
 
 #[system(World)]
 fn companion_follow(
@@ -173,11 +176,12 @@ fn main() {
         world.create(CompanionEntity {
             position: Position(0.0, 0.0),
             companion_component: CompanionComponent {
-                target_entity: Some(player_entity),
+                target_entity: Some(player_entity.into()),
             },
         });
     }
 
+    world.rebuild_spatial_hash(8.0);
     world.collide_enemy_and_players();
     world.apply_velocity(&delta_time);
     world.companion_follow();
@@ -0,0 +1,39 @@
+pub mod components {
+    #[derive(Default, Debug, Clone, Copy, PartialEq)]
+    pub struct Position(pub i32);
+
+    #[derive(Default, Debug, Clone, Copy, PartialEq)]
+    pub struct Velocity(pub i32);
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub struct Shield(pub i32);
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub struct Arrow(pub i32);
+}
+
+pub mod entities {
+    use crate::components::{Position, Shield, Velocity};
+    use zero_ecs::*;
+
+    #[entity]
+    pub struct Unit {
+        pub position: Position,
+        pub velocity: Velocity,
+        #[sparse]
+        pub shield: Option<Shield>,
+    }
+
+    #[entity]
+    pub struct Projectile<T> {
+        pub position: Position,
+        pub payload: T,
+    }
+}
+
+pub mod world {
+    use crate::components::Arrow;
+    use zero_ecs::*;
+
+    ecs_world!(CoreWorld: crate::entities::Unit, crate::entities::Projectile<Arrow>);
+}